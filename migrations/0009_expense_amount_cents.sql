@@ -0,0 +1,23 @@
+-- store money as integer cents instead of a floating point amount
+-- Dropping `expense` with the foreign keys enabled fails while `user_expense`
+-- points to it, the migrations run with them disabled (see `run_migrations`).
+
+CREATE TABLE IF NOT EXISTS expense_new (
+    id INTEGER PRIMARY KEY,
+    paid_by INTEGER NOT NULL,
+    amount_cents INTEGER NOT NULL,
+    title VARCHAR(255) NOT NULL,
+    description VARCHAR(255),
+    room_id TEXT NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (paid_by) REFERENCES user (id)
+    FOREIGN KEY (room_id) REFERENCES room (id)
+);
+
+INSERT INTO expense_new (id, paid_by, amount_cents, title, description, room_id, created_at)
+SELECT id, paid_by, CAST(ROUND(amount * 100) AS INTEGER), title, description, room_id, created_at
+FROM expense;
+
+DROP TABLE expense;
+
+ALTER TABLE expense_new RENAME TO expense;
@@ -0,0 +1,13 @@
+ALTER TABLE expense ADD COLUMN split_method TEXT NOT NULL DEFAULT 'equal';
+
+DROP TABLE IF EXISTS expense_split;
+
+CREATE TABLE IF NOT EXISTS expense_split (
+    expense_id INTEGER NOT NULL,
+    user_id INTEGER NOT NULL,
+    percentage REAL,
+    amount_cents INTEGER,
+    PRIMARY KEY (expense_id, user_id),
+    FOREIGN KEY (expense_id) REFERENCES expense (id),
+    FOREIGN KEY (user_id) REFERENCES user (id)
+);
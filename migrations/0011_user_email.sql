@@ -0,0 +1,4 @@
+-- nullable so that the existing users don't break the migration
+ALTER TABLE user ADD COLUMN email TEXT;
+
+CREATE UNIQUE INDEX IF NOT EXISTS user_email_unique ON user (email);
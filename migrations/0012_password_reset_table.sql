@@ -0,0 +1,8 @@
+DROP TABLE IF EXISTS password_reset;
+
+CREATE TABLE IF NOT EXISTS password_reset (
+    user_id INTEGER NOT NULL,
+    token TEXT PRIMARY KEY NOT NULL,
+    expires_at TIMESTAMP NOT NULL,
+    FOREIGN KEY (user_id) REFERENCES user (id)
+);
@@ -0,0 +1,7 @@
+ALTER TABLE room ADD COLUMN base_currency TEXT NOT NULL DEFAULT 'USD';
+
+-- exchange_rate converts the expense currency to the room base currency
+ALTER TABLE expense ADD COLUMN currency TEXT NOT NULL DEFAULT 'USD';
+ALTER TABLE expense ADD COLUMN exchange_rate REAL NOT NULL DEFAULT 1.0;
+
+UPDATE expense SET currency = (SELECT room.base_currency FROM room WHERE room.id = expense.room_id);
@@ -0,0 +1 @@
+ALTER TABLE expense ADD COLUMN category TEXT NOT NULL DEFAULT 'Other';
@@ -0,0 +1,2 @@
+ALTER TABLE user ADD COLUMN display_name TEXT;
+ALTER TABLE user ADD COLUMN avatar_url TEXT;
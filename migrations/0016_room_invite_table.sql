@@ -0,0 +1,12 @@
+DROP TABLE IF EXISTS room_invite;
+
+CREATE TABLE IF NOT EXISTS room_invite (
+    token TEXT PRIMARY KEY NOT NULL,
+    room_id TEXT NOT NULL,
+    created_by INTEGER NOT NULL,
+    expires_at TIMESTAMP NOT NULL,
+    max_uses INTEGER NOT NULL,
+    uses INTEGER NOT NULL DEFAULT 0,
+    FOREIGN KEY (room_id) REFERENCES room (id),
+    FOREIGN KEY (created_by) REFERENCES user (id)
+);
@@ -0,0 +1,7 @@
+ALTER TABLE user_room ADD COLUMN role TEXT NOT NULL DEFAULT 'member';
+
+-- the owners were not always added as members of their rooms
+INSERT OR IGNORE INTO user_room (room_id, user_id) SELECT id, owner FROM room;
+
+UPDATE user_room SET role = 'owner'
+WHERE user_id = (SELECT room.owner FROM room WHERE room.id = user_room.room_id);
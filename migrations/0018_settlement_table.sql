@@ -0,0 +1,13 @@
+DROP TABLE IF EXISTS settlement;
+
+CREATE TABLE IF NOT EXISTS settlement (
+    id INTEGER PRIMARY KEY,
+    room_id TEXT NOT NULL,
+    from_user INTEGER NOT NULL,
+    to_user INTEGER NOT NULL,
+    amount_cents INTEGER NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (room_id) REFERENCES room (id),
+    FOREIGN KEY (from_user) REFERENCES user (id),
+    FOREIGN KEY (to_user) REFERENCES user (id)
+);
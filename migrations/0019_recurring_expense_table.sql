@@ -0,0 +1,14 @@
+DROP TABLE IF EXISTS recurring_expense;
+
+CREATE TABLE IF NOT EXISTS recurring_expense (
+    id INTEGER PRIMARY KEY,
+    room_id TEXT NOT NULL,
+    title VARCHAR(255) NOT NULL,
+    amount_cents INTEGER NOT NULL,
+    paid_by INTEGER NOT NULL,
+    interval TEXT NOT NULL,
+    next_due DATE NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (room_id) REFERENCES room (id),
+    FOREIGN KEY (paid_by) REFERENCES user (id)
+);
@@ -0,0 +1 @@
+ALTER TABLE expense ADD COLUMN deleted_at TIMESTAMP;
@@ -0,0 +1,11 @@
+DROP TABLE IF EXISTS expense_item;
+
+CREATE TABLE IF NOT EXISTS expense_item (
+    id INTEGER PRIMARY KEY,
+    expense_id INTEGER NOT NULL,
+    description TEXT NOT NULL,
+    amount_cents INTEGER NOT NULL,
+    assigned_to INTEGER NOT NULL,
+    FOREIGN KEY (expense_id) REFERENCES expense (id),
+    FOREIGN KEY (assigned_to) REFERENCES user (id)
+);
@@ -0,0 +1,2 @@
+ALTER TABLE expense ADD COLUMN tax_cents INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE expense ADD COLUMN tip_cents INTEGER NOT NULL DEFAULT 0;
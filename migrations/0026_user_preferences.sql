@@ -0,0 +1,3 @@
+-- the defaults fill the existing users too
+ALTER TABLE user ADD COLUMN preferred_currency TEXT NOT NULL DEFAULT 'USD';
+ALTER TABLE user ADD COLUMN locale TEXT NOT NULL DEFAULT 'en';
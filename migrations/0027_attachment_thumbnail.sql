@@ -0,0 +1 @@
+ALTER TABLE attachment ADD COLUMN thumbnail BLOB;
@@ -0,0 +1,13 @@
+DROP TABLE IF EXISTS reminder;
+
+-- one reminder per debt, updated while the debt lasts
+CREATE TABLE IF NOT EXISTS reminder (
+    id INTEGER PRIMARY KEY,
+    room_id TEXT NOT NULL,
+    user_id INTEGER NOT NULL,
+    amount_cents INTEGER NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (room_id, user_id),
+    FOREIGN KEY (room_id) REFERENCES room (id),
+    FOREIGN KEY (user_id) REFERENCES user (id)
+);
@@ -0,0 +1 @@
+ALTER TABLE room ADD COLUMN default_split_method TEXT NOT NULL DEFAULT 'equal';
@@ -0,0 +1,12 @@
+DROP TABLE IF EXISTS idempotency;
+
+-- the keys sent by the clients with the expense they created, kept for a day
+CREATE TABLE IF NOT EXISTS idempotency (
+    user_id INTEGER NOT NULL,
+    key TEXT NOT NULL,
+    expense_id INTEGER NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (user_id, key),
+    FOREIGN KEY (user_id) REFERENCES user (id),
+    FOREIGN KEY (expense_id) REFERENCES expense (id)
+);
@@ -0,0 +1 @@
+ALTER TABLE room ADD COLUMN monthly_budget_cents INTEGER;
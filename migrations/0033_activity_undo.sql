@@ -0,0 +1,3 @@
+-- what is needed to undo the action, e.g. the values before an edit
+ALTER TABLE activity ADD COLUMN details TEXT;
+ALTER TABLE activity ADD COLUMN undone_at TIMESTAMP;
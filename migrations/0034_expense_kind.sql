@@ -0,0 +1 @@
+ALTER TABLE expense ADD COLUMN kind TEXT NOT NULL DEFAULT 'expense';
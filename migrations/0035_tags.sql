@@ -0,0 +1,16 @@
+DROP TABLE IF EXISTS expense_tag;
+DROP TABLE IF EXISTS tag;
+
+-- free-form tags, stored lowercase so that every name is a single tag
+CREATE TABLE IF NOT EXISTS tag (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE
+);
+
+CREATE TABLE IF NOT EXISTS expense_tag (
+    expense_id INTEGER NOT NULL,
+    tag_id INTEGER NOT NULL,
+    PRIMARY KEY (expense_id, tag_id),
+    FOREIGN KEY (expense_id) REFERENCES expense (id),
+    FOREIGN KEY (tag_id) REFERENCES tag (id)
+);
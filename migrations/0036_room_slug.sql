@@ -0,0 +1,14 @@
+-- the rooms created before the slugs keep their id as slug
+ALTER TABLE room ADD COLUMN slug TEXT NOT NULL DEFAULT '';
+UPDATE room SET slug = id;
+CREATE UNIQUE INDEX IF NOT EXISTS room_slug_index ON room (slug);
+
+DROP TABLE IF EXISTS room_slug_history;
+
+-- the slugs a room had before being renamed, so that old links still work
+CREATE TABLE IF NOT EXISTS room_slug_history (
+    slug TEXT PRIMARY KEY NOT NULL,
+    room_id TEXT NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (room_id) REFERENCES room (id)
+);
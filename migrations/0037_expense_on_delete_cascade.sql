@@ -0,0 +1,89 @@
+-- SQLite can't alter a foreign key, so the tables of the rows that belong to
+-- an expense are rebuilt to be deleted with it. The orphan rows are dropped.
+
+CREATE TABLE expense_split_new (
+    expense_id INTEGER NOT NULL,
+    user_id INTEGER NOT NULL,
+    percentage REAL,
+    amount_cents INTEGER,
+    PRIMARY KEY (expense_id, user_id),
+    FOREIGN KEY (expense_id) REFERENCES expense (id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES user (id)
+);
+INSERT INTO expense_split_new SELECT expense_id, user_id, percentage, amount_cents FROM expense_split WHERE expense_id IN (SELECT id FROM expense);
+DROP TABLE expense_split;
+ALTER TABLE expense_split_new RENAME TO expense_split;
+
+CREATE TABLE expense_item_new (
+    id INTEGER PRIMARY KEY,
+    expense_id INTEGER NOT NULL,
+    description TEXT NOT NULL,
+    amount_cents INTEGER NOT NULL,
+    assigned_to INTEGER NOT NULL,
+    FOREIGN KEY (expense_id) REFERENCES expense (id) ON DELETE CASCADE,
+    FOREIGN KEY (assigned_to) REFERENCES user (id)
+);
+INSERT INTO expense_item_new SELECT id, expense_id, description, amount_cents, assigned_to FROM expense_item WHERE expense_id IN (SELECT id FROM expense);
+DROP TABLE expense_item;
+ALTER TABLE expense_item_new RENAME TO expense_item;
+
+CREATE TABLE user_expense_new (
+    user_id INTEGER NOT NULL,
+    expense_id INTEGER NOT NULL,
+    FOREIGN KEY (user_id) REFERENCES user (id),
+    FOREIGN KEY (expense_id) REFERENCES expense (id) ON DELETE CASCADE
+);
+INSERT INTO user_expense_new SELECT user_id, expense_id FROM user_expense WHERE expense_id IN (SELECT id FROM expense);
+DROP TABLE user_expense;
+ALTER TABLE user_expense_new RENAME TO user_expense;
+
+CREATE TABLE attachment_new (
+    id INTEGER PRIMARY KEY,
+    expense_id INTEGER NOT NULL,
+    filename TEXT NOT NULL,
+    mime_type TEXT NOT NULL,
+    data BLOB NOT NULL,
+    thumbnail BLOB,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (expense_id) REFERENCES expense (id) ON DELETE CASCADE
+);
+INSERT INTO attachment_new SELECT id, expense_id, filename, mime_type, data, thumbnail, created_at FROM attachment WHERE expense_id IN (SELECT id FROM expense);
+DROP TABLE attachment;
+ALTER TABLE attachment_new RENAME TO attachment;
+
+CREATE TABLE comment_new (
+    id INTEGER PRIMARY KEY,
+    expense_id INTEGER NOT NULL,
+    user_id INTEGER NOT NULL,
+    body TEXT NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (expense_id) REFERENCES expense (id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES user (id)
+);
+INSERT INTO comment_new SELECT id, expense_id, user_id, body, created_at FROM comment WHERE expense_id IN (SELECT id FROM expense);
+DROP TABLE comment;
+ALTER TABLE comment_new RENAME TO comment;
+
+CREATE TABLE idempotency_new (
+    user_id INTEGER NOT NULL,
+    key TEXT NOT NULL,
+    expense_id INTEGER NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (user_id, key),
+    FOREIGN KEY (user_id) REFERENCES user (id),
+    FOREIGN KEY (expense_id) REFERENCES expense (id) ON DELETE CASCADE
+);
+INSERT INTO idempotency_new SELECT user_id, key, expense_id, created_at FROM idempotency WHERE expense_id IN (SELECT id FROM expense);
+DROP TABLE idempotency;
+ALTER TABLE idempotency_new RENAME TO idempotency;
+
+CREATE TABLE expense_tag_new (
+    expense_id INTEGER NOT NULL,
+    tag_id INTEGER NOT NULL,
+    PRIMARY KEY (expense_id, tag_id),
+    FOREIGN KEY (expense_id) REFERENCES expense (id) ON DELETE CASCADE,
+    FOREIGN KEY (tag_id) REFERENCES tag (id)
+);
+INSERT INTO expense_tag_new SELECT expense_id, tag_id FROM expense_tag WHERE expense_id IN (SELECT id FROM expense);
+DROP TABLE expense_tag;
+ALTER TABLE expense_tag_new RENAME TO expense_tag;
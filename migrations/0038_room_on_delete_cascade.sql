@@ -0,0 +1,139 @@
+-- The tables of the rows that belong to a room are rebuilt to be deleted with
+-- it, the users can't be deleted while something still points to them.
+-- Dropping `expense` with the foreign keys enabled would delete its splits,
+-- items, ..., the migrations run with them disabled (see `run_migrations`).
+-- The rows of rooms that don't exist are dropped.
+
+CREATE TABLE expense_new (
+    id INTEGER PRIMARY KEY,
+    paid_by INTEGER NOT NULL,
+    amount_cents INTEGER NOT NULL,
+    title VARCHAR(255) NOT NULL,
+    description VARCHAR(255),
+    room_id TEXT NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    split_method TEXT NOT NULL DEFAULT 'equal',
+    currency TEXT NOT NULL DEFAULT 'USD',
+    exchange_rate REAL NOT NULL DEFAULT 1.0,
+    category TEXT NOT NULL DEFAULT 'Other',
+    deleted_at TIMESTAMP,
+    tax_cents INTEGER NOT NULL DEFAULT 0,
+    tip_cents INTEGER NOT NULL DEFAULT 0,
+    kind TEXT NOT NULL DEFAULT 'expense',
+    FOREIGN KEY (paid_by) REFERENCES user (id) ON DELETE RESTRICT,
+    FOREIGN KEY (room_id) REFERENCES room (id) ON DELETE CASCADE
+);
+INSERT INTO expense_new (id, paid_by, amount_cents, title, description, room_id, created_at, split_method, currency, exchange_rate, category, deleted_at, tax_cents, tip_cents, kind)
+    SELECT id, paid_by, amount_cents, title, description, room_id, created_at, split_method, currency, exchange_rate, category, deleted_at, tax_cents, tip_cents, kind
+    FROM expense WHERE room_id IN (SELECT id FROM room);
+DROP TABLE expense;
+ALTER TABLE expense_new RENAME TO expense;
+
+CREATE TABLE settlement_new (
+    id INTEGER PRIMARY KEY,
+    room_id TEXT NOT NULL,
+    from_user INTEGER NOT NULL,
+    to_user INTEGER NOT NULL,
+    amount_cents INTEGER NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (room_id) REFERENCES room (id) ON DELETE CASCADE,
+    FOREIGN KEY (from_user) REFERENCES user (id) ON DELETE RESTRICT,
+    FOREIGN KEY (to_user) REFERENCES user (id) ON DELETE RESTRICT
+);
+INSERT INTO settlement_new SELECT id, room_id, from_user, to_user, amount_cents, created_at FROM settlement WHERE room_id IN (SELECT id FROM room);
+DROP TABLE settlement;
+ALTER TABLE settlement_new RENAME TO settlement;
+
+CREATE TABLE recurring_expense_new (
+    id INTEGER PRIMARY KEY,
+    room_id TEXT NOT NULL,
+    title VARCHAR(255) NOT NULL,
+    amount_cents INTEGER NOT NULL,
+    paid_by INTEGER NOT NULL,
+    interval TEXT NOT NULL,
+    next_due DATE NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (room_id) REFERENCES room (id) ON DELETE CASCADE,
+    FOREIGN KEY (paid_by) REFERENCES user (id) ON DELETE RESTRICT
+);
+INSERT INTO recurring_expense_new SELECT id, room_id, title, amount_cents, paid_by, interval, next_due, created_at FROM recurring_expense WHERE room_id IN (SELECT id FROM room);
+DROP TABLE recurring_expense;
+ALTER TABLE recurring_expense_new RENAME TO recurring_expense;
+
+CREATE TABLE room_invite_new (
+    token TEXT PRIMARY KEY NOT NULL,
+    room_id TEXT NOT NULL,
+    created_by INTEGER NOT NULL,
+    expires_at TIMESTAMP NOT NULL,
+    max_uses INTEGER NOT NULL,
+    uses INTEGER NOT NULL DEFAULT 0,
+    FOREIGN KEY (room_id) REFERENCES room (id) ON DELETE CASCADE,
+    FOREIGN KEY (created_by) REFERENCES user (id) ON DELETE CASCADE
+);
+INSERT INTO room_invite_new SELECT token, room_id, created_by, expires_at, max_uses, uses FROM room_invite WHERE room_id IN (SELECT id FROM room);
+DROP TABLE room_invite;
+ALTER TABLE room_invite_new RENAME TO room_invite;
+
+CREATE TABLE activity_new (
+    id INTEGER PRIMARY KEY,
+    room_id TEXT NOT NULL,
+    user_id INTEGER NOT NULL,
+    action TEXT NOT NULL,
+    entity_id INTEGER NOT NULL,
+    details TEXT,
+    undone_at TIMESTAMP,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (room_id) REFERENCES room (id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES user (id) ON DELETE RESTRICT
+);
+INSERT INTO activity_new SELECT id, room_id, user_id, action, entity_id, details, undone_at, created_at FROM activity WHERE room_id IN (SELECT id FROM room);
+DROP TABLE activity;
+ALTER TABLE activity_new RENAME TO activity;
+CREATE INDEX IF NOT EXISTS activity_room_id_index ON activity (room_id, created_at);
+
+CREATE TABLE reminder_new (
+    id INTEGER PRIMARY KEY,
+    room_id TEXT NOT NULL,
+    user_id INTEGER NOT NULL,
+    amount_cents INTEGER NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (room_id, user_id),
+    FOREIGN KEY (room_id) REFERENCES room (id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES user (id) ON DELETE CASCADE
+);
+INSERT INTO reminder_new SELECT id, room_id, user_id, amount_cents, created_at FROM reminder WHERE room_id IN (SELECT id FROM room);
+DROP TABLE reminder;
+ALTER TABLE reminder_new RENAME TO reminder;
+
+-- `room_id` was an INTEGER, but the ids of the rooms are TEXT
+CREATE TABLE user_room_new (
+    user_id INTEGER NOT NULL,
+    room_id TEXT NOT NULL,
+    role TEXT NOT NULL DEFAULT 'member',
+    PRIMARY KEY (user_id, room_id),
+    FOREIGN KEY (user_id) REFERENCES user (id) ON DELETE CASCADE,
+    FOREIGN KEY (room_id) REFERENCES room (id) ON DELETE CASCADE
+);
+INSERT INTO user_room_new SELECT user_id, room_id, role FROM user_room WHERE room_id IN (SELECT id FROM room) AND user_id IN (SELECT id FROM user);
+DROP TABLE user_room;
+ALTER TABLE user_room_new RENAME TO user_room;
+
+CREATE TABLE room_slug_history_new (
+    slug TEXT PRIMARY KEY NOT NULL,
+    room_id TEXT NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (room_id) REFERENCES room (id) ON DELETE CASCADE
+);
+INSERT INTO room_slug_history_new SELECT slug, room_id, created_at FROM room_slug_history WHERE room_id IN (SELECT id FROM room);
+DROP TABLE room_slug_history;
+ALTER TABLE room_slug_history_new RENAME TO room_slug_history;
+
+CREATE TABLE password_reset_new (
+    user_id INTEGER NOT NULL,
+    token TEXT PRIMARY KEY NOT NULL,
+    expires_at TIMESTAMP NOT NULL,
+    FOREIGN KEY (user_id) REFERENCES user (id) ON DELETE CASCADE
+);
+INSERT INTO password_reset_new SELECT user_id, token, expires_at FROM password_reset WHERE user_id IN (SELECT id FROM user);
+DROP TABLE password_reset;
+ALTER TABLE password_reset_new RENAME TO password_reset;
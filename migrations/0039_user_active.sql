@@ -0,0 +1,2 @@
+-- deactivated users can't log in, their rows stay for the balances
+ALTER TABLE user ADD COLUMN active BOOLEAN NOT NULL DEFAULT 1;
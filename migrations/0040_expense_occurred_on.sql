@@ -0,0 +1,14 @@
+-- The day the money was spent, often some days before the expense is added.
+-- ALTER TABLE can't use CURRENT_DATE as default, so the trigger fills it for
+-- the inserts that don't set it.
+ALTER TABLE expense ADD COLUMN occurred_on DATE;
+
+UPDATE expense SET occurred_on = date(created_at);
+
+CREATE TRIGGER expense_default_occurred_on AFTER INSERT ON expense
+WHEN NEW.occurred_on IS NULL
+BEGIN
+    UPDATE expense SET occurred_on = date(COALESCE(NEW.created_at, CURRENT_TIMESTAMP)) WHERE id = NEW.id;
+END;
+
+CREATE INDEX IF NOT EXISTS expense_room_id_occurred_on_index ON expense (room_id, occurred_on);
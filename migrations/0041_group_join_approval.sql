@@ -0,0 +1,12 @@
+-- the users that accepted an invite of a room that requires approval, they
+-- become members when an admin approves them
+ALTER TABLE room ADD COLUMN approval_required BOOLEAN NOT NULL DEFAULT 0;
+
+CREATE TABLE IF NOT EXISTS pending_member (
+    room_id TEXT NOT NULL,
+    user_id INTEGER NOT NULL,
+    requested_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (room_id, user_id),
+    FOREIGN KEY (room_id) REFERENCES room (id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES user (id)
+);
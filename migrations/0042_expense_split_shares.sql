@@ -0,0 +1,2 @@
+-- the weights of the expenses split by shares, e.g. 2:1:1
+ALTER TABLE expense_split ADD COLUMN shares INTEGER;
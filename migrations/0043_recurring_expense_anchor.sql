@@ -0,0 +1,7 @@
+-- The due dates are computed from the first one, adding one interval at a time
+-- moved a monthly expense of the 31st to the 28th for good after February.
+-- The schedules already drifted keep their current day.
+ALTER TABLE recurring_expense ADD COLUMN first_due DATE;
+ALTER TABLE recurring_expense ADD COLUMN occurrences INTEGER NOT NULL DEFAULT 0;
+
+UPDATE recurring_expense SET first_due = next_due;
@@ -5,6 +5,12 @@ SELECT
     user.id,
     user.username,
     '' as password,
+    user.email,
+    user.display_name,
+    user.avatar_url,
+    user.preferred_currency,
+    user.locale,
+    user.active,
     user.created_at
 FROM user_room 
 JOIN user ON user_room.user_id = user.id
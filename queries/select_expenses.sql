@@ -0,0 +1,32 @@
+-- The columns of the expenses, the WHERE, ORDER BY and LIMIT clauses are
+-- appended by the code that builds the query
+
+SELECT 
+    expense.id,
+    expense.paid_by,
+    expense.amount_cents,
+    expense.title,
+    json_group_array(user_expense.user_id) as participants,
+    expense.room_id,
+    expense.description,
+    expense.split_method,
+    (
+        SELECT json_group_array(json_array(expense_split.user_id, expense_split.percentage, expense_split.amount_cents, expense_split.shares))
+        FROM expense_split
+        WHERE expense_split.expense_id = expense.id
+    ) as splits,
+    (
+        SELECT json_group_array(json_array(expense_item.description, expense_item.amount_cents, expense_item.assigned_to))
+        FROM expense_item
+        WHERE expense_item.expense_id = expense.id
+    ) as items,
+    expense.currency,
+    expense.exchange_rate,
+    expense.tax_cents,
+    expense.tip_cents,
+    expense.category,
+    expense.kind,
+    expense.occurred_on,
+    expense.created_at
+FROM expense 
+LEFT JOIN user_expense ON expense.id = user_expense.expense_id
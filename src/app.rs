@@ -3,13 +3,16 @@ use leptos_meta::*;
 use leptos_router::*;
 
 use crate::{
+    components::notification_component::NotificationProvider,
     error_template::{AppError, ErrorTemplate},
+    i18n::{provide_lang, use_lang, Lang},
     pages::{
         auth::{get_user, Login, LoginPage, Logout, LogoutPage, Register, RegisterPage},
         create_room_page::CreateRoomPage,
         dashboard_page::DashboardPage,
         home_page::HomePage,
         join_room_page::JoinRoomPage,
+        settings_page::SettingsPage,
     },
 };
 
@@ -17,8 +20,10 @@ use crate::{
 pub fn App() -> impl IntoView {
     // Provides context that manages stylesheets, titles, meta tags, etc.
     provide_meta_context();
+    let lang = provide_lang();
 
     view! {
+        <Html lang=move || lang.get().as_str()/>
         <Stylesheet id="leptos" href="/pkg/expenses-splitter.css"/>
 
         // sets the document title
@@ -30,19 +35,22 @@ pub fn App() -> impl IntoView {
             outside_errors.insert_with_default_key(AppError::NotFound);
             view! { <ErrorTemplate outside_errors/> }.into_view()
         }>
-            <main class="h-screen">
-                <Routes>
-                    <Route path="register" view=|| view! { <RegisterPage/> }/>
-                    <Route path="login" view=|| view! { <LoginPage/> }/>
-                    <Route path="" view=|| view! { <Page/> }>
-                        <Route path="" view=|| view! { <HomePage/> }/>
-                        <Route path="new" view=|| view! { <CreateRoomPage/> }/>
-                        <Route path="join" view=|| view! { <JoinRoomPage/> }/>
-                        <Route path="room/:id" view=|| view! { <DashboardPage/> }/>
-                        <Route path="logout" view=|| view! { <LogoutPage/> }/>
-                    </Route>
-                </Routes>
-            </main>
+            <NotificationProvider>
+                <main class="h-screen">
+                    <Routes>
+                        <Route path="register" view=|| view! { <RegisterPage/> }/>
+                        <Route path="login" view=|| view! { <LoginPage/> }/>
+                        <Route path="" view=|| view! { <Page/> }>
+                            <Route path="" view=|| view! { <HomePage/> }/>
+                            <Route path="new" view=|| view! { <CreateRoomPage/> }/>
+                            <Route path="join" view=|| view! { <JoinRoomPage/> }/>
+                            <Route path="room/:slug" view=|| view! { <DashboardPage/> }/>
+                            <Route path="settings" view=|| view! { <SettingsPage/> }/>
+                            <Route path="logout" view=|| view! { <LogoutPage/> }/>
+                        </Route>
+                    </Routes>
+                </main>
+            </NotificationProvider>
         </Router>
     }
 }
@@ -67,6 +75,16 @@ pub fn Page() -> impl IntoView {
         },
     );
 
+    // the locale chosen by the user wins over the one of the browser
+    let lang = use_lang();
+    create_effect(move |_| {
+        if let Some(Ok(Some(user))) = user.get() {
+            if let Some(user_lang) = Lang::from_code(&user.locale) {
+                lang.set(user_lang);
+            }
+        }
+    });
+
     view! {
         <Transition fallback=move || {
             view! { <p>"Loading..."</p> }
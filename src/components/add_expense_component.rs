@@ -1,68 +1,260 @@
 use crate::{
     components::{
         input_component::{
-            InputComponent, InputParams, InputType, InputWithControlsComponent,
-            InputWithControlsParams,
+            CurrencyInputComponent, CurrencyInputParams, DateInputComponent, DateInputParams,
+            InputComponent, InputParams, InputType, InputWithControlsComponent, InputWithControlsParams,
         },
-        notification_component::{NotificationComponent, NotificationParams, NotificationType},
+        notification_component::notify_action_result,
         user_in_room_component::get_users_in_room,
     },
-    models::{expense::Expense, user::User},
+    models::{
+        expense::{Category, Expense, ExpenseKind, NewExpense, SplitMethod, SplitMethodKind},
+        user::User,
+    },
+    utils::{
+        money::{validate_amount, Currency},
+        validation::check_not_in_future,
+    },
 };
+use chrono::NaiveDate;
 use leptos::*;
 use leptos_router::{ActionForm, FromFormData};
-use std::{collections::HashMap, iter};
+use std::collections::HashMap;
 
 type SelectedUsers = HashMap<User, bool>;
 
-#[server(AddExpense, "/api")]
-pub async fn add_expense(expense: Expense) -> Result<(), ServerFnError> {
+#[server(CreateExpense, "/api")]
+pub async fn create_expense(
+    room_id: String,
+    title: String,
+    description: Option<String>,
+    amount_cents: i64,
+    participants: Vec<i64>,
+    split_method: Option<SplitMethod>,
+    currency: Currency,
+    exchange_rate: f64,
+    tax_cents: i64,
+    tip_cents: i64,
+    category: Category,
+    kind: ExpenseKind,
+    occurred_on: NaiveDate,
+    idempotency_key: Option<String>,
+) -> Result<i64, ServerFnError> {
+    use crate::models::activity::{log_activity, ActivityAction};
+    use crate::models::room::Room;
+    use crate::services::authz::require_member;
+    use crate::services::expenses::{insert_expense, ValidatedExpense};
     use crate::state::auth;
     use crate::state::pool;
+    use crate::state::{publish_room_event, room_events::RoomEvent, time_server_fn};
+    use crate::utils::money::validate_amount;
+    use crate::utils::validation::{check_max_length, check_not_in_future, check_percentages, check_unique_users, DESCRIPTION_MAX_LENGTH, TITLE_MAX_LENGTH};
     use leptos::logging::log;
 
+    let _timer = time_server_fn("create_expense");
+
     let pool = pool()?;
+    let auth = auth()?;
+
+    let user = require_member(&room_id, &pool, &auth).await?;
+
+    validate_amount(amount_cents).map_err(|error| {
+        log!("fn: create_expense() - invalid amount: {}", amount_cents);
+        error
+    })?;
+
+    check_max_length("Title", &title, TITLE_MAX_LENGTH)?;
+    check_max_length("Description", description.as_deref().unwrap_or_default(), DESCRIPTION_MAX_LENGTH)?;
 
-    log!("fn: add_expense() - adding expense: {:?}", expense);
-
-    // add expense
-    let res = sqlx::query!(
-        "INSERT INTO expense (paid_by, amount, title, description, room_id) VALUES (?, ?, ?, ?, ?) RETURNING id",
-        expense.paid_by,
-        expense.amount,
-        expense.title,
-        expense.description,
-        expense.room_id
-    )
-        .fetch_one(&pool)
-        .await?;
-
-    log::info!("fn: add_expense() - added expense: {:?}", res);
-
-    log::info!("fn: add_expense() - adding participants: {:?}", expense.participants);
-
-    // add values to user_expense
-    for id_participant in expense.participants {
-        sqlx::query!(
-            "INSERT INTO user_expense (user_id, expense_id) VALUES (?, ?)",
-            id_participant,
-            res.id
-        )
-            .execute(&pool)
+    // a day of margin for the users ahead of the server timezone
+    let tomorrow = chrono::Local::now().date_naive().succ_opt().unwrap_or(NaiveDate::MAX);
+    check_not_in_future("Date", occurred_on, tomorrow)?;
+
+    let (base_currency, default_split_method): (String, String) =
+        sqlx::query_as("SELECT base_currency, default_split_method FROM room WHERE id = ?")
+            .bind(&room_id)
+            .fetch_one(&pool)
             .await?;
+
+    // only the equal split can be used without the amounts of every participant
+    let split_method = match split_method {
+        Some(split_method) => split_method,
+        None => match default_split_method.parse().unwrap_or_default() {
+            SplitMethodKind::Equal => SplitMethod::Equal,
+            kind => {
+                log!("fn: create_expense() - room {} needs the amounts for {:?}", room_id, kind);
+                return Err(ServerFnError::ServerError(format!(
+                    "This room splits the expenses by {}, specify the amounts",
+                    kind.as_str().replace('_', " ")
+                )));
+            }
+        },
+    };
+
+    let exchange_rate = if currency.as_str() == base_currency {
+        1.0
+    } else if exchange_rate > 0.0 {
+        exchange_rate
+    } else {
+        log!("fn: create_expense() - invalid exchange rate: {}", exchange_rate);
+        return Err(ServerFnError::ServerError(
+            "Exchange rate must be positive".to_string(),
+        ));
+    };
+
+    // nobody selected means everybody in the room
+    let members = Room::member_ids(&room_id, &pool).await?;
+    let participants = if participants.is_empty() {
+        members.clone()
+    } else {
+        participants
+    };
+
+    if let Some(user_id) = participants.iter().find(|user_id| !members.contains(user_id)) {
+        log!("fn: create_expense() - user {} is not in room {}", user_id, room_id);
+        return Err(ServerFnError::ServerError(
+            "Every participant must be a member of the room".to_string(),
+        ));
+    }
+
+    if tax_cents < 0 || tip_cents < 0 {
+        log!("fn: create_expense() - negative tax or tip");
+        return Err(ServerFnError::ServerError(
+            "Tax and tip cannot be negative".to_string(),
+        ));
+    }
+
+    let is_itemized = matches!(split_method, SplitMethod::Itemized(_));
+    if is_itemized && kind == ExpenseKind::Refund {
+        log!("fn: create_expense() - itemized refund");
+        return Err(ServerFnError::ServerError(
+            "Refunds cannot be itemized".to_string(),
+        ));
+    }
+
+    if !is_itemized && (tax_cents > 0 || tip_cents > 0) {
+        log!("fn: create_expense() - tax or tip on a non itemized expense");
+        return Err(ServerFnError::ServerError(
+            "Tax and tip are only allowed on itemized expenses".to_string(),
+        ));
     }
 
-    log::info!("fn: add_expense() - added participants");
+    match &split_method {
+        SplitMethod::Equal => {}
+        SplitMethod::Percentage(percentages) => {
+            check_unique_users(percentages.iter().map(|(user_id, _)| *user_id))?;
+            check_percentages(percentages)?;
+        }
+        SplitMethod::ExactCents(amounts) => {
+            check_unique_users(amounts.iter().map(|(user_id, _)| *user_id))?;
+
+            let total: i64 = amounts.iter().map(|(_, amount)| amount).sum();
+            if total != amount_cents {
+                log!("fn: create_expense() - exact amounts sum to {}", total);
+                return Err(ServerFnError::ServerError(
+                    "Exact amounts must sum to the total".to_string(),
+                ));
+            }
+        }
+        SplitMethod::Shares(shares) => {
+            check_unique_users(shares.iter().map(|(user_id, _)| *user_id))?;
+
+            // a positive share for everyone also makes the total positive
+            if shares.is_empty() || shares.iter().any(|(_, share)| *share == 0) {
+                log!("fn: create_expense() - empty or zero shares");
+                return Err(ServerFnError::ServerError(
+                    "Every participant must have a positive share".to_string(),
+                ));
+            }
+
+            if shares.iter().any(|(user_id, _)| !members.contains(user_id)) {
+                log!("fn: create_expense() - share of a user not in room {}", room_id);
+                return Err(ServerFnError::ServerError(
+                    "Every participant must be a member of the room".to_string(),
+                ));
+            }
+        }
+        SplitMethod::Itemized(items) => {
+            // the amount is the whole bill, tax and tip included
+            let total: i64 = items.iter().map(|item| item.amount_cents).sum();
+            if items.is_empty() || total + tax_cents + tip_cents != amount_cents {
+                log!("fn: create_expense() - items sum to {}", total);
+                return Err(ServerFnError::ServerError(
+                    "Items, tax and tip must sum to the total".to_string(),
+                ));
+            }
+
+            for item in items {
+                check_max_length("Item description", &item.description, TITLE_MAX_LENGTH)?;
+            }
+
+            if items.iter().any(|item| !members.contains(&item.assigned_to)) {
+                log!("fn: create_expense() - item assigned to a user not in room {}", room_id);
+                return Err(ServerFnError::ServerError(
+                    "Every item must be assigned to a member of the room".to_string(),
+                ));
+            }
+        }
+    }
+
+    let expense = ValidatedExpense {
+        room_id: room_id.clone(),
+        paid_by: user.id,
+        title,
+        description,
+        amount_cents,
+        participants,
+        split_method,
+        currency,
+        exchange_rate,
+        tax_cents,
+        tip_cents,
+        category,
+        kind,
+        occurred_on,
+    };
+
+    let expense_id = insert_expense(&expense, idempotency_key.as_deref(), &pool).await?;
+
+    log_activity(&pool, &room_id, user.id, ActivityAction::ExpenseCreated, expense_id).await?;
+    publish_room_event(&room_id, RoomEvent::ExpenseCreated { expense_id });
+
+    Ok(expense_id)
+}
+
+// Only a warning, the client asks the user before adding the expense anyway.
+#[server(FindPossibleDuplicates, "/api")]
+pub async fn find_possible_duplicates(room_id: String, expense: NewExpense) -> Result<Vec<Expense>, ServerFnError> {
+    use crate::services::authz::require_member;
+    use crate::state::{auth, pool};
+    use leptos::logging::log;
+
+    let pool = pool()?;
+    let user = require_member(&room_id, &pool, &auth()?).await?;
+
+    let paid_by = expense.paid_by.unwrap_or(user.id);
+    let duplicates = Expense::find_possible_duplicates(&room_id, paid_by, &expense, &pool).await?;
+
+    if !duplicates.is_empty() {
+        log!("fn: find_possible_duplicates() - {} possible duplicates of {:?}", duplicates.len(), expense.title);
+    }
 
-    Ok(())
+    Ok(duplicates)
 }
 
 #[component]
 pub fn AddExpenseComponent(room_id: String) -> impl IntoView {
-    let action = create_server_action::<AddExpense>();
+    let action = create_server_action::<CreateExpense>();
 
-    let value = action.value();
-    let has_error = move || value.with(|val| matches!(val, Some(Err(_))));
+    notify_action_result(action.value(), "Expense added");
+
+    // the same key is sent again by a double click, a new one after a success
+    let idempotency_key = create_rw_signal(uuid::Uuid::new_v4().to_string());
+    create_effect(move |_| {
+        if let Some(Ok(_)) = action.value().get() {
+            idempotency_key.set(uuid::Uuid::new_v4().to_string());
+        }
+    });
 
     let room_id_clone = room_id.clone();
     let users = create_resource(
@@ -70,20 +262,16 @@ pub fn AddExpenseComponent(room_id: String) -> impl IntoView {
         move |_| get_users_in_room(room_id_clone.clone()),
     );
 
-    let (who_payed, set_who_payed) = create_signal::<Option<User>>(None);
     let (selected_participants, set_selected_participants) = create_signal(SelectedUsers::new());
-    let (amount, set_amount) = create_signal("".to_string());
+    let (amount_cents, set_amount_cents) = create_signal(0_i64);
     let (title, set_title) = create_signal("".to_string());
     let (description, set_description) = create_signal("".to_string());
+    // today by default, earlier for the expenses added some days later
+    let (occurred_on, set_occurred_on) = create_signal(Some(chrono::Local::now().date_naive()));
 
-    let amount_error = move || {
-        if amount.with(String::is_empty) {
-            return Some("Amount is required".to_string());
-        } else if let Err(_) = amount.get().parse::<f64>() {
-            return Some("Amount must be a number".to_string());
-        } else {
-            return None;
-        }
+    let amount_error = move || match amount_cents.get() {
+        0 => Some("Amount is required".to_string()),
+        cents => validate_amount(cents).err().map(|error| error.to_string()),
     };
 
     let title_error = move || {
@@ -94,6 +282,14 @@ pub fn AddExpenseComponent(room_id: String) -> impl IntoView {
         }
     };
 
+    let occurred_on_error = move || {
+        let today = chrono::Local::now().date_naive();
+        match occurred_on.get() {
+            Some(date) => check_not_in_future("Date", date, today).err().map(|error| error.to_string()),
+            None => Some("Date is required".to_string()),
+        }
+    };
+
     let input_title_params = InputWithControlsParams {
         label: "Title".to_string(),
         placeholder: "Title".to_string(),
@@ -111,86 +307,91 @@ pub fn AddExpenseComponent(room_id: String) -> impl IntoView {
         value: (description, set_description),
     };
 
-    let input_amount_params = InputWithControlsParams {
+    let input_amount_params = CurrencyInputParams {
         label: "Amount".to_string(),
         placeholder: "0.00".to_string(),
         name: "amount".to_string(),
-        input_type: InputType::Text,
-        value: (amount, set_amount),
+        symbol: Currency::USD.symbol().to_string(),
+        value: (amount_cents, set_amount_cents),
         value_error: amount_error,
     };
 
-    let get_notification_params = move || {
-        let server_message = value().unwrap().unwrap_err().to_string();
-        let client_message = server_message.replace("error running server function: ", "");
-
-        NotificationParams {
-            message: client_message,
-            notification_type: NotificationType::Error,
-        }
+    let input_occurred_on_params = DateInputParams {
+        label: "Date".to_string(),
+        name: "occurred_on".to_string(),
+        value: (occurred_on, set_occurred_on),
+        value_error: occurred_on_error,
     };
 
-    let is_form_valid =
-        move || title_error().is_none() && amount_error().is_none() && who_payed.get().is_some();
+    let is_form_valid = move || title_error().is_none() && amount_error().is_none() && occurred_on_error().is_none();
+
+    // the similar expenses found, the user confirms before adding another one
+    let possible_duplicates = create_rw_signal(Vec::<Expense>::new());
+
+    let room_id_clone = room_id.clone();
+    let add_expense = Callback::new(move |_: ()| {
+        possible_duplicates.set(Vec::new());
+
+        let selected_users: Vec<i64> = selected_participants
+            .get()
+            .iter()
+            .filter_map(
+                |(user, is_selected)| {
+                    if *is_selected {
+                        Some(user.id)
+                    } else {
+                        None
+                    }
+                },
+            )
+            .collect();
+
+        let description = if description.get().len() > 0 {
+            Some(description.get())
+        } else {
+            None
+        };
+
+        action.dispatch(CreateExpense {
+            room_id: room_id_clone.clone(),
+            title: title.get(),
+            description,
+            amount_cents: amount_cents.get(),
+            participants: selected_users,
+            // the form has no inputs for the amounts of the other methods, so
+            // the default of the room can't be used here
+            split_method: Some(SplitMethod::Equal),
+            currency: Currency::USD,
+            exchange_rate: 1.0,
+            tax_cents: 0,
+            tip_cents: 0,
+            category: Category::default(),
+            kind: ExpenseKind::Expense,
+            occurred_on: occurred_on.get().unwrap_or_else(|| chrono::Local::now().date_naive()),
+            idempotency_key: Some(idempotency_key.get_untracked()),
+        });
+    });
 
     let add_expense_click = move |_| {
         let room_id = room_id.clone();
+        let expense = NewExpense {
+            title: title.get(),
+            description: None,
+            amount_cents: amount_cents.get(),
+            paid_by: None,
+            participants: Vec::new(),
+            category: Category::default(),
+        };
+
         spawn_local(async move {
-            let selected_users: Vec<i64> = selected_participants
-                .get()
-                .iter()
-                .filter_map(
-                    |(user, is_selected)| {
-                        if *is_selected {
-                            Some(user.id)
-                        } else {
-                            None
-                        }
-                    },
-                )
-                .collect();
-
-            let mut expense = Expense::default();
-            expense.paid_by = who_payed.get().unwrap().id;
-            expense.room_id = room_id;
-            expense.title = title.get();
-            expense.amount = amount.get().parse::<f64>().unwrap();
-            expense.participants = selected_users;
-
-            if description.get().len() > 0 {
-                expense.description = Some(description.get());
+            match find_possible_duplicates(room_id, expense).await {
+                Ok(duplicates) if !duplicates.is_empty() => possible_duplicates.set(duplicates),
+                // the check is only a safeguard, it never blocks the expense
+                _ => add_expense.call(()),
             }
-
-            add_expense(expense).await;
         });
     };
 
-    let paid_by_view = move || match users.get() {
-        Some(Ok(users)) => {
-            let first_option = view! {
-                <option disabled selected required>
-                    "Select who paid"
-                </option>
-            };
-
-            let users_options = users.into_iter().map(|user| {
-                let user_clone = user.clone();
-
-                view! {
-                    <option on:click=move |_| {
-                        set_who_payed(Some(user.clone()))
-                    }>{user_clone.username}</option>
-                }
-            });
-
-            let all_options = iter::once(first_option).chain(users_options).collect_view();
-
-            view! { <select class="select select-primary w-full">{all_options}</select> }
-                .into_view()
-        }
-        _ => view! { <p>"Error"</p> }.into_view(),
-    };
-
     let participants_view = move || match users.get() {
         Some(Ok(users)) => users
             .into_iter()
@@ -210,7 +411,7 @@ pub fn AddExpenseComponent(room_id: String) -> impl IntoView {
                 view! {
                     <div class="form-control">
                         <label class="label cursor-pointer">
-                            <span class="label-text">{user_clone.username}</span>
+                            <span class="label-text">{user_clone.label()}</span>
                             <input
                                 type="checkbox"
                                 class="checkbox checkbox-primary"
@@ -231,17 +432,25 @@ pub fn AddExpenseComponent(room_id: String) -> impl IntoView {
 
                 <InputComponent params=input_description_params.clone()/>
 
-                <label class="label-text font-bold mb-2">"Who paid?"</label>
-                {move || paid_by_view()}
-
-                <div class="mt-6"></div>
-
                 <label class="label-text font-bold mb-2">"Who participated?"</label>
                 {move || participants_view()}
 
-                <InputWithControlsComponent params=input_amount_params.clone()/>
+                <CurrencyInputComponent params=input_amount_params.clone()/>
+
+                <DateInputComponent params=input_occurred_on_params.clone()/>
 
             </Transition>
+            <Show when=move || possible_duplicates.with(|duplicates| !duplicates.is_empty())>
+                <div class="alert alert-warning mb-2">
+                    <span>
+                        "Did you already add this? "
+                        {move || possible_duplicates.with(|duplicates| duplicates.len())}
+                        " similar expense(s) added in the last minutes."
+                    </span>
+                    <button class="btn btn-sm" on:click=move |_| add_expense.call(())>"Add anyway"</button>
+                    <button class="btn btn-sm btn-ghost" on:click=move |_| possible_duplicates.set(Vec::new())>"Cancel"</button>
+                </div>
+            </Show>
             <button
                 class="btn btn-primary btn-lg w-full"
                 prop:disabled=move || !is_form_valid()
@@ -249,10 +458,6 @@ pub fn AddExpenseComponent(room_id: String) -> impl IntoView {
             >
                 <b>ADD EXPENSE</b>
             </button>
-
-            <Show when=has_error fallback=|| ()>
-                <NotificationComponent params=get_notification_params()/>
-            </Show>
         </div>
     }
 }
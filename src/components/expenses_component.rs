@@ -1,37 +1,232 @@
+use chrono::NaiveDate;
 use leptos::*;
 
-use crate::models::expense::Expense;
+use crate::models::expense::{Category, Expense, ExpenseCursorPage, ExpensePage};
+use crate::i18n::use_lang;
+use crate::utils::money::{format_cents, Currency};
 
 #[server(GetExpensesInRoom, "/api")]
 pub async fn get_expenses_in_room(room_id: String) -> Result<Vec<Expense>, ServerFnError> {
-    use crate::state::pool;
-    use crate::models::expense::ExpenseDTO;
+    use crate::services::authz::require_member;
+    use crate::state::{auth, pool};
     use leptos::logging::log;
 
     let pool = pool()?;
+    require_member(&room_id, &pool, &auth()?).await?;
 
     log!("fn: get_expenses_in_room() - getting expenses in room");
 
-    let expenses_dto: Vec<ExpenseDTO> =
-        sqlx::query_file_as!(ExpenseDTO, "queries/get_expenses_in_room.sql", room_id)
-            .fetch_all(&pool)
-            .await?;
+    let expenses = Expense::list_for_room(&room_id, &pool).await?;
 
-    let expenses = expenses_dto
+    log!("fn: get_expenses_in_room() - expenses: {:?}", expenses);
+
+    Ok(expenses)
+}
+
+#[server(GetExpensesPage, "/api")]
+pub async fn get_expenses_page(
+    room_id: String,
+    page: i64,
+    page_size: Option<i64>,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+) -> Result<ExpensePage, ServerFnError> {
+    use crate::error_template::AppError;
+    use crate::services::authz::require_member;
+    use crate::state::{auth, pool};
+    use leptos::logging::log;
+
+    const DEFAULT_PAGE_SIZE: i64 = 25;
+
+    let pool = pool()?;
+    require_member(&room_id, &pool, &auth()?).await?;
+
+    let page = page.max(0);
+    let page_size = page_size.unwrap_or(DEFAULT_PAGE_SIZE).max(1);
+
+    log!("fn: get_expenses_page() - getting page {} of size {}", page, page_size);
+
+    if let (Some(from), Some(to)) = (from, to) {
+        if from > to {
+            log!("fn: get_expenses_page() - invalid range {} - {}", from, to);
+            return Err(AppError::Validation(
+                "The start date must be before the end date".to_string(),
+            ).into());
+        }
+    }
+
+    let expenses = Expense::list_paginated(&room_id, from, to, page_size, page * page_size, &pool).await?;
+    let total_count = Expense::count_for_room(&room_id, from, to, &pool).await?;
+
+    Ok(ExpensePage {
+        expenses,
+        total_count,
+    })
+}
+
+// The first page when `cursor` is None, then the `next_cursor` of the previous page.
+#[server(GetExpensesCursor, "/api")]
+pub async fn get_expenses_cursor(room_id: String, cursor: Option<i64>) -> Result<ExpenseCursorPage, ServerFnError> {
+    use crate::services::authz::require_member;
+    use crate::state::{auth, pool};
+    use leptos::logging::log;
+
+    const PAGE_SIZE: i64 = 25;
+
+    let pool = pool()?;
+    require_member(&room_id, &pool, &auth()?).await?;
+
+    log!("fn: get_expenses_cursor() - getting the page after {:?}", cursor);
+
+    let (expenses, next_cursor) = Expense::list_after(&room_id, cursor, PAGE_SIZE, &pool).await?;
+
+    Ok(ExpenseCursorPage {
+        expenses,
+        next_cursor,
+    })
+}
+
+#[server(SearchExpenses, "/api")]
+pub async fn search_expenses(room_id: String, query: String) -> Result<Vec<Expense>, ServerFnError> {
+    use crate::services::authz::require_member;
+    use crate::state::{auth, pool};
+    use leptos::logging::log;
+
+    const MAX_SEARCH_RESULTS: i64 = 50;
+
+    let pool = pool()?;
+    require_member(&room_id, &pool, &auth()?).await?;
+
+    log!("fn: search_expenses() - searching for {:?}", query);
+
+    let expenses = Expense::search(&room_id, query.trim(), MAX_SEARCH_RESULTS, &pool).await?;
+
+    Ok(expenses)
+}
+
+#[server(GetCategoryTotals, "/api")]
+pub async fn get_category_totals(room_id: String) -> Result<Vec<(Category, i64)>, ServerFnError> {
+    use crate::models::expense::SIGNED_BASE_AMOUNT_SQL;
+    use crate::services::authz::require_member;
+    use crate::state::{auth, pool};
+    use leptos::logging::log;
+
+    let pool = pool()?;
+    require_member(&room_id, &pool, &auth()?).await?;
+
+    log!("fn: get_category_totals() - getting the totals per category");
+
+    // amounts are converted to the room base currency
+    let rows: Vec<(String, i64)> = sqlx::query_as(&format!(
+        "SELECT category, CAST(SUM({}) AS INTEGER) AS total FROM expense WHERE room_id = ? AND deleted_at IS NULL GROUP BY category ORDER BY total DESC",
+        SIGNED_BASE_AMOUNT_SQL
+    ))
+    .bind(&room_id)
+    .fetch_all(&pool)
+    .await?;
+
+    let totals = rows
         .into_iter()
-        .filter(|expense| expense.id.is_some())
-        .map(|expense| Expense::from(expense))
+        .map(|(category, total)| (category.parse().unwrap_or_default(), total))
         .collect();
 
-    log!("fn: get_expenses_in_room() - expenses: {:?}", expenses);
+    log!("fn: get_category_totals() - totals: {:?}", totals);
 
-    Ok(expenses)
+    Ok(totals)
+}
+
+#[server(DeleteExpense, "/api")]
+pub async fn delete_expense(expense_id: i64) -> Result<(), ServerFnError> {
+    use crate::models::activity::{log_activity, ActivityAction};
+    use crate::models::room::Role;
+    use crate::services::authz::require_author_or_role;
+    use crate::state::{auth, pool, publish_room_event, room_events::RoomEvent, time_server_fn};
+    use leptos::logging::log;
+
+    let _timer = time_server_fn("delete_expense");
+
+    let pool = pool()?;
+    let auth = auth()?;
+
+    let expense = Expense::get(expense_id, &pool).await.ok_or_else(|| {
+        log!("fn: delete_expense() - expense {} not found", expense_id);
+        ServerFnError::ServerError("Expense not found".to_string())
+    })?;
+
+    // members can only manage their own expenses, admins and owners all of them
+    let user = require_author_or_role(&expense.room_id, expense.paid_by, Role::Admin, &pool, &auth).await?;
+
+    log!("fn: delete_expense() - deleting expense {}", expense_id);
+
+    // the row is kept so that the delete can be undone, it is purged later
+    sqlx::query("UPDATE expense SET deleted_at = CURRENT_TIMESTAMP WHERE id = ?")
+        .bind(expense_id)
+        .execute(&pool)
+        .await?;
+
+    log_activity(&pool, &expense.room_id, user.id, ActivityAction::ExpenseDeleted, expense_id).await?;
+    publish_room_event(&expense.room_id, RoomEvent::ExpenseDeleted { expense_id });
+
+    Ok(())
+}
+
+#[server(RestoreExpense, "/api")]
+pub async fn restore_expense(expense_id: i64) -> Result<(), ServerFnError> {
+    use crate::models::activity::{log_activity, ActivityAction};
+    use crate::models::room::Role;
+    use crate::services::authz::require_author_or_role;
+    use crate::state::{auth, pool};
+    use chrono::{Duration, NaiveDateTime, Utc};
+    use leptos::logging::log;
+
+    const UNDO_WINDOW_SECONDS: i64 = 30;
+
+    let pool = pool()?;
+    let auth = auth()?;
+
+    let (room_id, paid_by, deleted_at): (String, i64, NaiveDateTime) = sqlx::query_as(
+        "SELECT room_id, paid_by, deleted_at FROM expense WHERE id = ? AND deleted_at IS NOT NULL",
+    )
+    .bind(expense_id)
+    .fetch_optional(&pool)
+    .await?
+    .ok_or_else(|| ServerFnError::ServerError("Expense not found".to_string()))?;
+
+    // members can only manage their own expenses, admins and owners all of them
+    let user = require_author_or_role(&room_id, paid_by, Role::Admin, &pool, &auth).await?;
+
+    // CURRENT_TIMESTAMP is in UTC
+    if Utc::now().naive_utc() - deleted_at > Duration::seconds(UNDO_WINDOW_SECONDS) {
+        log!("fn: restore_expense() - undo window expired for expense {}", expense_id);
+        return Err(ServerFnError::ServerError(
+            "The expense can no longer be restored".to_string(),
+        ));
+    }
+
+    log!("fn: restore_expense() - restoring expense {}", expense_id);
+
+    sqlx::query("UPDATE expense SET deleted_at = NULL WHERE id = ?")
+        .bind(expense_id)
+        .execute(&pool)
+        .await?;
+
+    log_activity(&pool, &room_id, user.id, ActivityAction::ExpenseRestored, expense_id).await?;
+
+    Ok(())
 }
 
 #[component]
 pub fn ExpensesComponent(room_id: String) -> impl IntoView {
+    #[cfg(feature = "hydrate")]
+    let events_room_id = room_id.clone();
+
     let expenses = create_resource(move || (), move |_| get_expenses_in_room(room_id.clone()));
 
+    // other members adding or deleting expenses
+    #[cfg(feature = "hydrate")]
+    crate::state::room_events::subscribe(&events_room_id, move || expenses.refetch());
+    let lang = use_lang();
+
     let expenses_view = move || {
         expenses.get().map(move |expenses| match expenses {
             Err(_) => view! {<p>"Error"</p>}.into_view(),
@@ -47,7 +242,7 @@ pub fn ExpensesComponent(room_id: String) -> impl IntoView {
                             <tr>
                                 <td>{expense.title}</td>
                                 <td>{expense.description}</td>
-                                <td>{expense.amount}</td>
+                                <td>{format_cents(expense.amount_cents, Currency::USD, lang.get())}</td>
                             </tr>
                         }
                     })
@@ -1,10 +1,16 @@
-use leptos::*;
+use chrono::NaiveDate;
+use leptos::{leptos_dom::helpers::TimeoutHandle, *};
+use std::time::Duration;
+
+use crate::utils::money::parse_cents;
 
 #[derive(Clone, Copy)]
 pub enum InputType {
     Text,
     Password,
     Number,
+    Email,
+    Date,
 }
 
 impl InputType {
@@ -13,6 +19,8 @@ impl InputType {
             InputType::Text => "text",
             InputType::Password => "password",
             InputType::Number => "number",
+            InputType::Email => "email",
+            InputType::Date => "date",
         }
     }
 }
@@ -39,12 +47,102 @@ where
     pub value_error: T,
 }
 
+// The value is None while the input is empty or not a valid date.
+#[derive(Clone)]
+pub struct DateInputParams<T>
+where
+    T: Fn() -> Option<String>,
+{
+    pub label: String,
+    pub name: String,
+    pub value: (ReadSignal<Option<NaiveDate>>, WriteSignal<Option<NaiveDate>>),
+    pub value_error: T,
+}
+
+// Returns a signal that follows `source`, but only after it didn't change for
+// `delay_ms` milliseconds.
+pub fn create_debounced_signal<T: Clone + 'static>(
+    source: ReadSignal<T>,
+    delay_ms: u32,
+) -> ReadSignal<T> {
+    let (debounced, set_debounced) = create_signal(source.get_untracked());
+    let timeout_handle = store_value::<Option<TimeoutHandle>>(None);
+
+    create_effect(move |_| {
+        let value = source.get();
+
+        if let Some(handle) = timeout_handle.get_value() {
+            handle.clear();
+        }
+
+        let handle = set_timeout_with_handle(
+            move || set_debounced(value),
+            Duration::from_millis(delay_ms as u64),
+        )
+        .ok();
+        timeout_handle.set_value(handle);
+    });
+
+    return debounced;
+}
+
+#[derive(Clone)]
+pub struct CurrencyInputParams<T>
+where
+    T: Fn() -> Option<String>,
+{
+    pub label: String,
+    pub placeholder: String,
+    pub name: String,
+    pub symbol: String,
+    pub value: (ReadSignal<i64>, WriteSignal<i64>),
+    pub value_error: T,
+}
+
+// Keeps only the digits and the first decimal point, with at most two decimals.
+fn sanitize_currency_input(input: &str) -> String {
+    let mut sanitized = String::new();
+    let mut decimals: Option<usize> = None;
+
+    for c in input.chars() {
+        if c.is_ascii_digit() {
+            match decimals {
+                Some(2) => continue,
+                Some(n) => decimals = Some(n + 1),
+                None => {}
+            }
+            sanitized.push(c);
+        } else if c == '.' && decimals.is_none() {
+            decimals = Some(0);
+            sanitized.push(c);
+        }
+    }
+
+    return sanitized;
+}
+
+// Derived from the name, so that the server and the client render the same id.
+fn input_id(name: &str) -> String {
+    format!("input-{}", name)
+}
+
+// The browsers always send the dates as ISO 8601, whatever the locale.
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+// `node_ref` lets the parent focus the input.
 #[component]
-pub fn InputComponent(params: InputParams) -> impl IntoView {
+pub fn InputComponent(
+    params: InputParams,
+    #[prop(optional)] node_ref: NodeRef<html::Input>,
+) -> impl IntoView {
+    let id = input_id(&params.name);
+
     view! {
         <div class="form-control w-full">
-            <label class="label-text font-bold mb-2">{params.label}</label>
+            <label class="label-text font-bold mb-2" for=id.clone()>{params.label}</label>
             <input
+                id=id
+                node_ref=node_ref
                 class="input input-bordered input-primary w-full"
                 type=params.input_type.as_str()
                 placeholder=params.placeholder
@@ -64,13 +162,24 @@ pub fn InputWithControlsComponent<T: Fn() -> Option<String> + 'static + Clone +
     params: InputWithControlsParams<T>,
 ) -> impl IntoView {
     let (value_touched, set_value_touched) = create_signal(false);
+    let id = input_id(&params.name);
+    let error_id = format!("{}-error", id);
+    // the error is only announced once it is shown
+    let show_error = move || value_touched() && (params.value_error)().is_some();
+    let described_by = {
+        let error_id = error_id.clone();
+        move || show_error().then(|| error_id.clone())
+    };
 
     view! {
         <div class="form-control w-full">
-            <label class="label-text font-bold mb-2">{params.label}</label>
+            <label class="label-text font-bold mb-2" for=id.clone()>{params.label}</label>
             <input
+                id=id
                 class="input input-bordered input-primary w-full"
-                class=("input-error", move || value_touched() && (params.value_error)().is_some())
+                class=("input-error", show_error)
+                aria-invalid=move || show_error().to_string()
+                aria-describedby=described_by
 
                 type=params.input_type.as_str()
                 placeholder=params.placeholder
@@ -81,9 +190,84 @@ pub fn InputWithControlsComponent<T: Fn() -> Option<String> + 'static + Clone +
             />
             <label>
                 {move || {
-                    if value_touched() && (params.value_error)().is_some() {
+                    if show_error() {
+                        view! {
+                            <span id=error_id.clone() class="label-text-alt text-error">
+                                {move || params.value_error}
+                            </span>
+                        }
+                    } else {
+                        view! {
+                            <span class="label-text-alt text-transparent">.</span>
+                        }
+                    }
+                }}
+
+            </label>
+        </div>
+    }
+}
+
+// The value is 0 while the input is empty.
+#[component]
+pub fn CurrencyInputComponent<T: Fn() -> Option<String> + 'static + Clone + Copy>(
+    params: CurrencyInputParams<T>,
+) -> impl IntoView {
+    let (value_touched, set_value_touched) = create_signal(false);
+    let (text, set_text) = create_signal(String::new());
+
+    let on_keypress = move |ev: ev::KeyboardEvent| {
+        let key = ev.key();
+        let is_digit = key.chars().all(|c| c.is_ascii_digit());
+        let is_decimal_point = key == "." && !text.with(|x| x.contains('.'));
+
+        if key.chars().count() == 1 && !is_digit && !is_decimal_point {
+            ev.prevent_default();
+        }
+    };
+
+    let on_input = move |ev| {
+        let sanitized = sanitize_currency_input(&event_target_value(&ev));
+        params.value.1.set(parse_cents(&sanitized).unwrap_or(0));
+        set_text(sanitized);
+    };
+
+    let id = input_id(&params.name);
+    let error_id = format!("{}-error", id);
+    let show_error = move || value_touched() && (params.value_error)().is_some();
+    let described_by = {
+        let error_id = error_id.clone();
+        move || show_error().then(|| error_id.clone())
+    };
+
+    view! {
+        <div class="form-control w-full">
+            <label class="label-text font-bold mb-2" for=id.clone()>{params.label}</label>
+            <label class="input-group">
+                <span>{params.symbol}</span>
+                <input
+                    id=id
+                    class="input input-bordered input-primary w-full"
+                    class=("input-error", show_error)
+                    aria-invalid=move || show_error().to_string()
+                    aria-describedby=described_by
+
+                    type="text"
+                    inputmode="decimal"
+                    placeholder=params.placeholder
+                    name=params.name
+                    prop:value=text
+                    on:keypress=on_keypress
+                    on:input=on_input
+                    on:blur=move |_| set_value_touched(true)
+                    required
+                />
+            </label>
+            <label>
+                {move || {
+                    if show_error() {
                         view! {
-                            <span class="label-text-alt text-error">
+                            <span id=error_id.clone() class="label-text-alt text-error">
                                 {move || params.value_error}
                             </span>
                         }
@@ -98,3 +282,78 @@ pub fn InputWithControlsComponent<T: Fn() -> Option<String> + 'static + Clone +
         </div>
     }
 }
+
+// Like `InputWithControlsComponent`, the dates that can't be parsed are
+// reported with the errors of `value_error`.
+#[component]
+pub fn DateInputComponent<T: Fn() -> Option<String> + 'static + Clone + Copy>(
+    params: DateInputParams<T>,
+) -> impl IntoView {
+    let (value_touched, set_value_touched) = create_signal(false);
+    let (text, set_text) = create_signal(
+        params
+            .value
+            .0
+            .get_untracked()
+            .map(|date| date.format(DATE_FORMAT).to_string())
+            .unwrap_or_default(),
+    );
+
+    let value_error = move || {
+        if !text.with(String::is_empty) && params.value.0.with(Option::is_none) {
+            return Some("Date is not valid".to_string());
+        }
+
+        return (params.value_error)();
+    };
+
+    let on_input = move |ev| {
+        let input = event_target_value(&ev);
+        params.value.1.set(NaiveDate::parse_from_str(&input, DATE_FORMAT).ok());
+        set_text(input);
+    };
+
+    let id = input_id(&params.name);
+    let error_id = format!("{}-error", id);
+    let show_error = move || value_touched() && value_error().is_some();
+    let described_by = {
+        let error_id = error_id.clone();
+        move || show_error().then(|| error_id.clone())
+    };
+
+    view! {
+        <div class="form-control w-full">
+            <label class="label-text font-bold mb-2" for=id.clone()>{params.label}</label>
+            <input
+                id=id
+                class="input input-bordered input-primary w-full"
+                class=("input-error", show_error)
+                aria-invalid=move || show_error().to_string()
+                aria-describedby=described_by
+
+                type=InputType::Date.as_str()
+                name=params.name
+                prop:value=text
+                on:input=on_input
+                on:blur=move |_| set_value_touched(true)
+                required
+            />
+            <label>
+                {move || {
+                    if show_error() {
+                        view! {
+                            <span id=error_id.clone() class="label-text-alt text-error">
+                                {value_error}
+                            </span>
+                        }
+                    } else {
+                        view! {
+                            <span class="label-text-alt text-transparent">.</span>
+                        }
+                    }
+                }}
+
+            </label>
+        </div>
+    }
+}
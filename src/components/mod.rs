@@ -2,4 +2,6 @@ pub mod add_expense_component;
 pub mod expenses_component;
 pub mod input_component;
 pub mod notification_component;
+pub mod password_strength_component;
+pub mod settlements_component;
 pub mod user_in_room_component;
@@ -1,4 +1,8 @@
 use leptos::*;
+use std::time::Duration;
+
+const FADE_OUT_MS: u64 = 500;
+const SUCCESS_DISMISS_MS: u32 = 3000;
 
 #[derive(Copy, Clone)]
 pub enum NotificationType {
@@ -32,65 +36,186 @@ impl NotificationType {
 pub struct NotificationParams {
     pub message: String,
     pub notification_type: NotificationType,
+    pub auto_dismiss_ms: Option<u32>,
 }
 impl Default for NotificationParams {
     fn default() -> Self {
         Self {
             message: "".to_string(),
             notification_type: NotificationType::Info,
+            auto_dismiss_ms: None,
         }
     }
 }
 
+#[derive(Copy, Clone)]
+struct NotificationQueue {
+    notifications: RwSignal<Vec<(usize, NotificationParams)>>,
+    next_id: StoredValue<usize>,
+}
+
+// Returns a function that pushes a notification on the queue rendered by the
+// `NotificationProvider`.
+pub fn use_notifications() -> impl Fn(NotificationParams) + Copy + 'static {
+    let queue = use_context::<NotificationQueue>()
+        .expect("use_notifications() must be called inside a NotificationProvider");
+
+    move |params: NotificationParams| {
+        let id = queue.next_id.get_value();
+        queue.next_id.set_value(id + 1);
+
+        if let Some(auto_dismiss_ms) = params.auto_dismiss_ms {
+            set_timeout(
+                move || {
+                    queue
+                        .notifications
+                        .update(|notifications| notifications.retain(|(x, _)| *x != id))
+                },
+                Duration::from_millis(auto_dismiss_ms as u64 + FADE_OUT_MS),
+            );
+        }
+
+        queue
+            .notifications
+            .update(|notifications| notifications.push((id, params)));
+    }
+}
+
+// Notifies the outcome of a server action: the success toasts go away by
+// themselves, the errors stay until closed.
+pub fn notify_action_result<T: Clone + 'static>(
+    value: RwSignal<Option<Result<T, ServerFnError>>>,
+    success_message: &'static str,
+) {
+    let notify = use_notifications();
+
+    create_effect(move |_| match value.get() {
+        Some(Ok(_)) => notify(NotificationParams {
+            message: success_message.to_string(),
+            notification_type: NotificationType::Success,
+            auto_dismiss_ms: Some(SUCCESS_DISMISS_MS),
+        }),
+        Some(Err(err)) => notify(NotificationParams {
+            message: err.to_string().replace("error running server function: ", ""),
+            notification_type: NotificationType::Error,
+            auto_dismiss_ms: None,
+        }),
+        None => {}
+    });
+}
+
+#[component]
+pub fn NotificationProvider(children: Children) -> impl IntoView {
+    let queue = NotificationQueue {
+        notifications: create_rw_signal(vec![]),
+        next_id: store_value(0),
+    };
+    provide_context(queue);
+
+    view! {
+        {children()}
+        <div class="toast toast-top toast-end">
+            <For
+                each=move || queue.notifications.get()
+                key=|(id, _)| *id
+                children=move |(id, params)| {
+                    let on_dismiss = Callback::new(move |_| {
+                        queue
+                            .notifications
+                            .update(|notifications| notifications.retain(|(x, _)| *x != id))
+                    });
+
+                    view! { <NotificationAlertComponent params=params on_dismiss=Some(on_dismiss)/> }
+                }
+            />
+        </div>
+    }
+}
+
 #[component]
-pub fn NotificationComponent(params: NotificationParams) -> impl IntoView {
+pub fn NotificationComponent(
+    params: NotificationParams,
+    #[prop(optional)] on_dismiss: Option<Callback<()>>,
+) -> impl IntoView {
+    view! {
+        <div class="toast">
+            <NotificationAlertComponent params=params on_dismiss=on_dismiss/>
+        </div>
+    }
+}
+
+// `on_dismiss` is called when the notification is closed by the user.
+#[component]
+fn NotificationAlertComponent(
+    params: NotificationParams,
+    on_dismiss: Option<Callback<()>>,
+) -> impl IntoView {
     let (is_visible, set_is_visible) = create_signal(true);
+    let (is_fading, set_is_fading) = create_signal(false);
     let message = params.message.clone();
 
-    let notification_css_class =
-        "alert w-96 whitespace-normal ".to_owned() + params.notification_type.css_class();
+    if let Some(auto_dismiss_ms) = params.auto_dismiss_ms {
+        set_timeout(
+            move || {
+                set_is_fading(true);
+                set_timeout(move || set_is_visible(false), Duration::from_millis(FADE_OUT_MS));
+            },
+            Duration::from_millis(auto_dismiss_ms as u64),
+        );
+    }
+
+    let notification_css_class = "alert w-96 whitespace-normal transition-opacity duration-500 "
+        .to_owned()
+        + params.notification_type.css_class();
 
     view! {
         {move || {
             if is_visible() {
                 view! {
-                    <div class="toast">
-                        <div class=notification_css_class.clone()>
-                            <div class="w-80 self-center">
-                                <div class="mb-2 flex justify-between">
-                                    <span class="font-bold text-xl">
-                                        {params.notification_type.title()}
-                                    </span>
-                                    <button
-                                        class="close-button"
-                                        on:click=move |_| set_is_visible(false)
+                    <div
+                        class=notification_css_class.clone()
+                        class=("opacity-0", move || is_fading())
+                    >
+                        <div class="w-80 self-center">
+                            <div class="mb-2 flex justify-between">
+                                <span class="font-bold text-xl">
+                                    {params.notification_type.title()}
+                                </span>
+                                <button
+                                    class="close-button"
+                                    aria-label="dismiss"
+                                    on:click=move |_| {
+                                        set_is_visible(false);
+                                        if let Some(on_dismiss) = on_dismiss {
+                                            on_dismiss.call(());
+                                        }
+                                    }
+                                >
+                                    <svg
+                                        xmlns="http://www.w3.org/2000/svg"
+                                        fill="none"
+                                        viewBox="0 0 24 24"
+                                        stroke-width="1.5"
+                                        stroke="currentColor"
+                                        class="w-6 h-6"
                                     >
-                                        <svg
-                                            xmlns="http://www.w3.org/2000/svg"
-                                            fill="none"
-                                            viewBox="0 0 24 24"
-                                            stroke-width="1.5"
-                                            stroke="currentColor"
-                                            class="w-6 h-6"
-                                        >
-                                            <path
-                                                stroke-linecap="round"
-                                                stroke-linejoin="round"
-                                                d="M6 18L18 6M6 6l12 12"
-                                            ></path>
-                                        </svg>
-                                    </button>
-                                </div>
-                                <div class="w-80">
-                                    <span class="withespace-normal">{message.clone()}</span>
-                                </div>
+                                        <path
+                                            stroke-linecap="round"
+                                            stroke-linejoin="round"
+                                            d="M6 18L18 6M6 6l12 12"
+                                        ></path>
+                                    </svg>
+                                </button>
+                            </div>
+                            <div class="w-80">
+                                <span class="withespace-normal">{message.clone()}</span>
                             </div>
                         </div>
                     </div>
                 }
                     .into_view()
             } else {
-                view! { <div></div> }.into_view()
+                ().into_view()
             }
         }}
     }
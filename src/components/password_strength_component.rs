@@ -0,0 +1,102 @@
+use leptos::*;
+
+// Lowercase, compared against the lowercased password.
+const COMMON_PASSWORDS: &[&str] = &[
+    "123456789",
+    "12345678",
+    "1234567890",
+    "password",
+    "password1",
+    "password123",
+    "qwertyuiop",
+    "qwerty123",
+    "iloveyou",
+    "11111111",
+    "00000000",
+    "abc12345",
+    "abcd1234",
+    "sunshine",
+    "princess",
+    "football",
+    "baseball",
+    "welcome1",
+    "superman",
+    "trustno1",
+    "letmein1",
+    "dragon123",
+    "1q2w3e4r",
+    "passw0rd",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PasswordStrength {
+    Weak,
+    Fair,
+    Strong,
+}
+
+impl PasswordStrength {
+    pub fn label(&self) -> &'static str {
+        match self {
+            PasswordStrength::Weak => "Weak",
+            PasswordStrength::Fair => "Fair",
+            PasswordStrength::Strong => "Strong",
+        }
+    }
+
+    pub fn css_class(&self) -> &'static str {
+        match self {
+            PasswordStrength::Weak => "progress-error",
+            PasswordStrength::Fair => "progress-warning",
+            PasswordStrength::Strong => "progress-success",
+        }
+    }
+}
+
+// A point for every class of characters used (lowercase, uppercase, digits,
+// symbols) and one more for long passwords. Common passwords are always weak.
+pub fn password_strength(password: &str) -> PasswordStrength {
+    if password.len() < 8 || COMMON_PASSWORDS.contains(&password.to_lowercase().as_str()) {
+        return PasswordStrength::Weak;
+    }
+
+    let classes = [
+        password.chars().any(|c| c.is_lowercase()),
+        password.chars().any(|c| c.is_uppercase()),
+        password.chars().any(|c| c.is_ascii_digit()),
+        password.chars().any(|c| !c.is_alphanumeric()),
+    ];
+
+    let mut points = classes.iter().filter(|x| **x).count();
+    if password.len() >= 12 {
+        points += 1;
+    }
+
+    match points {
+        0..=1 => PasswordStrength::Weak,
+        2..=3 => PasswordStrength::Fair,
+        _ => PasswordStrength::Strong,
+    }
+}
+
+#[component]
+pub fn PasswordStrengthComponent(password: ReadSignal<String>) -> impl IntoView {
+    let strength = create_memo(move |_| password.with(|x| password_strength(x)));
+
+    let value = move || match strength() {
+        PasswordStrength::Weak => 1,
+        PasswordStrength::Fair => 2,
+        PasswordStrength::Strong => 3,
+    };
+
+    view! {
+        <div class="w-full">
+            <progress
+                class=move || format!("progress w-full {}", strength().css_class())
+                value=value
+                max="3"
+            ></progress>
+            <span class="text-sm">{move || strength().label()}</span>
+        </div>
+    }
+}
@@ -0,0 +1,279 @@
+use leptos::*;
+
+use crate::components::notification_component::notify_action_result;
+use crate::models::settlement::Settlement;
+use crate::pages::auth::get_user;
+use crate::services::balance;
+use crate::i18n::use_lang;
+use crate::utils::money::{format_cents, Currency};
+
+#[server(RecordSettlement, "/api")]
+pub async fn record_settlement(
+    room_id: String,
+    to_user: i64,
+    amount_cents: i64,
+) -> Result<i64, ServerFnError> {
+    use crate::error_template::AppError;
+    use crate::i18n::Lang;
+    use crate::models::activity::{log_activity, ActivityAction};
+    use crate::models::room::Room;
+    use crate::services::authz::require_member;
+    use crate::services::balance::room_balances;
+    use crate::state::{auth, pool};
+    use crate::utils::money::validate_amount;
+    use leptos::logging::log;
+
+    let pool = pool()?;
+    let user = require_member(&room_id, &pool, &auth()?).await?;
+
+    if to_user == user.id {
+        return Err(AppError::Validation("You cannot settle up with yourself".to_string()).into());
+    }
+
+    validate_amount(amount_cents)?;
+
+    if !Room::is_member(&room_id, to_user, &pool).await? {
+        log!("fn: record_settlement() - user {} is not in room {}", to_user, room_id);
+        return Err(AppError::Validation("The recipient is not a member of this room".to_string()).into());
+    }
+
+    // paying more than the debt would turn the payer into a creditor
+    let balances = room_balances(&room_id, &pool).await?;
+    let owed_by_user = -balances.get(&user.id).copied().unwrap_or(0);
+    let owed_to_recipient = balances.get(&to_user).copied().unwrap_or(0);
+    let outstanding = owed_by_user.min(owed_to_recipient).max(0);
+
+    if outstanding == 0 {
+        return Err(AppError::Validation("There is no debt to settle with this user".to_string()).into());
+    }
+
+    if amount_cents > outstanding {
+        let room = Room::get(&room_id, &pool)
+            .await?
+            .ok_or_else(|| ServerFnError::ServerError("Room not found".to_string()))?;
+
+        log!("fn: record_settlement() - {} is more than the outstanding {}", amount_cents, outstanding);
+        return Err(AppError::Validation(format!(
+            "You can settle at most {}",
+            format_cents(
+                outstanding,
+                room.base_currency.parse().unwrap_or(Currency::USD),
+                Lang::from_code(&user.locale).unwrap_or_default(),
+            )
+        ))
+        .into());
+    }
+
+    log!("fn: record_settlement() - user {} paid {} to user {}", user.id, amount_cents, to_user);
+
+    let settlement_id: i64 = sqlx::query_scalar(
+        "INSERT INTO settlement (room_id, from_user, to_user, amount_cents) VALUES (?, ?, ?, ?) RETURNING id",
+    )
+    .bind(&room_id)
+    .bind(user.id)
+    .bind(to_user)
+    .bind(amount_cents)
+    .fetch_one(&pool)
+    .await?;
+
+    log!("fn: record_settlement() - added settlement: {}", settlement_id);
+
+    log_activity(&pool, &room_id, user.id, ActivityAction::SettlementRecorded, settlement_id).await?;
+
+    Ok(settlement_id)
+}
+
+#[server(GetSettlementsInRoom, "/api")]
+pub async fn get_settlements_in_room(room_id: String) -> Result<Vec<Settlement>, ServerFnError> {
+    use crate::services::authz::require_member;
+    use crate::state::{auth, pool};
+    use leptos::logging::log;
+
+    let pool = pool()?;
+    require_member(&room_id, &pool, &auth()?).await?;
+
+    log!("fn: get_settlements_in_room() - getting settlements in room");
+
+    let settlements = Settlement::list_for_room(&room_id, &pool).await?;
+
+    Ok(settlements)
+}
+
+// The transfers that settle all the debts in the room.
+#[server(GetGroupBalances, "/api")]
+pub async fn get_group_balances(room_id: String) -> Result<Vec<balance::Settlement>, ServerFnError> {
+    use crate::components::user_in_room_component::get_users_in_room;
+    use crate::models::user::UserLabels;
+    use crate::services::authz::require_member;
+    use crate::services::balance::{room_balances, simplify_debts};
+    use crate::state::{auth, pool, time_server_fn};
+    use leptos::logging::log;
+
+    let _timer = time_server_fn("get_group_balances");
+
+    let pool = pool()?;
+    require_member(&room_id, &pool, &auth()?).await?;
+
+    let balances = room_balances(&room_id, &pool).await?;
+    let labels = UserLabels::new(&get_users_in_room(room_id).await?);
+
+    let settlements: Vec<balance::Settlement> = simplify_debts(&balances)
+        .into_iter()
+        .map(|settlement| balance::Settlement {
+            from_label: labels.get(settlement.from),
+            to_label: labels.get(settlement.to),
+            ..settlement
+        })
+        .collect();
+
+    log!("fn: get_group_balances() - settlements: {:?}", settlements);
+
+    Ok(settlements)
+}
+
+// What the current user owes and is owed, an empty list when they are settled up.
+#[server(GetMyBalances, "/api")]
+pub async fn get_my_balances(room_id: String) -> Result<Vec<balance::UserBalance>, ServerFnError> {
+    use crate::components::user_in_room_component::get_users_in_room;
+    use crate::models::user::UserLabels;
+    use crate::services::authz::require_member;
+    use crate::services::balance::{balances_for_user, room_balances, simplify_debts};
+    use crate::state::{auth, pool, time_server_fn};
+    use leptos::logging::log;
+
+    let _timer = time_server_fn("get_my_balances");
+
+    let pool = pool()?;
+    let user = require_member(&room_id, &pool, &auth()?).await?;
+
+    let settlements = simplify_debts(&room_balances(&room_id, &pool).await?);
+    let balances = balances_for_user(user.id, &settlements);
+
+    if balances.is_empty() {
+        return Ok(balances);
+    }
+
+    let labels = UserLabels::new(&get_users_in_room(room_id).await?);
+
+    let balances: Vec<balance::UserBalance> = balances
+        .into_iter()
+        .map(|balance| balance::UserBalance {
+            label: labels.get(balance.other_user),
+            ..balance
+        })
+        .collect();
+
+    log!("fn: get_my_balances() - balances: {:?}", balances);
+
+    Ok(balances)
+}
+
+// The balances and the settlements are amounts in the base currency of the room.
+#[component]
+pub fn SettlementsComponent(room_id: String, base_currency: Currency) -> impl IntoView {
+    let action = create_server_action::<RecordSettlement>();
+    notify_action_result(action.value(), "Settlement recorded");
+
+    let room_id_clone = room_id.clone();
+    let settlements = create_resource(
+        move || action.version().get(),
+        move |_| get_settlements_in_room(room_id_clone.clone()),
+    );
+    let room_id_clone = room_id.clone();
+    let suggested = create_resource(
+        move || action.version().get(),
+        move |_| get_group_balances(room_id_clone.clone()),
+    );
+    let user = create_resource(|| (), |_| get_user());
+    let lang = use_lang();
+
+    // only who owes the money can record the payment
+    let suggested_view = move || {
+        let user_id = user.get().and_then(Result::ok).flatten().map(|user| user.id);
+
+        suggested.get().and_then(Result::ok).map(|suggested| {
+            suggested
+                .into_iter()
+                .map(|settlement| {
+                    let room_id = room_id.clone();
+                    let is_debtor = user_id == Some(settlement.from);
+
+                    view! {
+                        <tr>
+                            <td>{settlement.from_label}</td>
+                            <td>{settlement.to_label}</td>
+                            <td>{format_cents(settlement.amount_cents, base_currency, lang.get())}</td>
+                            <td>
+                                <Show when=move || is_debtor fallback=|| ()>
+                                    <button
+                                        class="btn btn-sm btn-primary"
+                                        on:click={
+                                            let room_id = room_id.clone();
+                                            move |_| action.dispatch(RecordSettlement {
+                                                room_id: room_id.clone(),
+                                                to_user: settlement.to,
+                                                amount_cents: settlement.amount_cents,
+                                            })
+                                        }
+                                    >
+                                        "Mark as paid"
+                                    </button>
+                                </Show>
+                            </td>
+                        </tr>
+                    }
+                })
+                .collect_view()
+        })
+    };
+
+    let settlements_view = move || {
+        settlements.get().map(move |settlements| match settlements {
+            Err(_) => view! {<p>"Error"</p>}.into_view(),
+            Ok(settlements) => {
+                if settlements.is_empty() {
+                    return view! {<tr><td>"No settlements"</td></tr>}.into_view();
+                }
+
+                settlements
+                    .into_iter()
+                    .map(|settlement| {
+                        view! {
+                            <tr>
+                                <td>{settlement.from_user}</td>
+                                <td>{settlement.to_user}</td>
+                                <td>{format_cents(settlement.amount_cents, base_currency, lang.get())}</td>
+                            </tr>
+                        }
+                    })
+                    .collect_view()
+            }
+        })
+    };
+
+    view! {
+        <div class="overflow-x-auto w-full">
+            <Transition fallback=move || view!{<p>"Loading..."</p>}>
+                <table class="table table-zebra">
+                    <thead>
+                        <tr>
+                            <th>From</th>
+                            <th>To</th>
+                            <th>Amount</th>
+                        </tr>
+                    </thead>
+                    <tbody>
+                        {move || settlements_view()}
+                    </tbody>
+                </table>
+
+                <p class="font-bold mt-6">"To settle up"</p>
+                <table class="table table-zebra">
+                    <tbody>
+                        {suggested_view}
+                    </tbody>
+                </table>
+            </Transition>
+        </div>
+    }
+}
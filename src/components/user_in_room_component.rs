@@ -1,12 +1,22 @@
-use crate::models::user::User;
+use crate::models::{room::Role, user::User};
+use crate::services::balance::UserStats;
 use leptos::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AddMembersResult {
+    pub added: Vec<String>,
+    pub not_found: Vec<String>,
+}
 
 #[server(GetUsersInRoom, "/api")]
 pub async fn get_users_in_room(room_id: String) -> Result<Vec<User>, ServerFnError> {
-    use crate::state::pool;
+    use crate::services::authz::require_member;
+    use crate::state::{auth, pool};
     use leptos::logging::log;
 
     let pool = pool()?;
+    require_member(&room_id, &pool, &auth()?).await?;
 
     log!("fn: get_users_in_room() - getting users in room");
 
@@ -19,6 +29,234 @@ pub async fn get_users_in_room(room_id: String) -> Result<Vec<User>, ServerFnErr
     Ok(users)
 }
 
+// Only the members of the room can see the statistics of the other members.
+#[server(GetUserStats, "/api")]
+pub async fn get_user_stats(user_id: i64, room_id: String) -> Result<UserStats, ServerFnError> {
+    use crate::models::room::Room;
+    use crate::services::authz::require_member;
+    use crate::services::balance::user_stats;
+    use crate::state::{auth, pool};
+    use leptos::logging::log;
+
+    let pool = pool()?;
+    require_member(&room_id, &pool, &auth()?).await?;
+
+    if !Room::is_member(&room_id, user_id, &pool).await? {
+        log!("fn: get_user_stats() - user {} is not in room {}", user_id, room_id);
+        return Err(ServerFnError::ServerError(
+            "The user is not a member of this room".to_string(),
+        ));
+    }
+
+    let stats = user_stats(&room_id, user_id, &pool).await?;
+
+    Ok(stats)
+}
+
+#[server(SetMemberRole, "/api")]
+pub async fn set_member_role(room_id: String, user_id: i64, role: Role) -> Result<(), ServerFnError> {
+    use crate::models::room::Room;
+    use crate::state::{auth, pool};
+    use leptos::logging::log;
+
+    let pool = pool()?;
+    let auth = auth()?;
+
+    let user = auth
+        .current_user
+        .ok_or_else(|| ServerFnError::ServerError("User not logged in".to_string()))?;
+
+    if Room::user_role(&room_id, user.id, &pool).await? != Some(Role::Owner) {
+        log!("fn: set_member_role() - user {} is not an owner of room {}", user.id, room_id);
+        return Err(ServerFnError::ServerError(
+            "Only the owners can change roles".to_string(),
+        ));
+    }
+
+    let current_role = Room::user_role(&room_id, user_id, &pool)
+        .await?
+        .ok_or_else(|| ServerFnError::ServerError("User is not in the room".to_string()))?;
+
+    if current_role == Role::Owner
+        && role != Role::Owner
+        && Room::count_owners(&room_id, &pool).await? <= 1
+    {
+        log!("fn: set_member_role() - cannot demote the last owner of room {}", room_id);
+        return Err(ServerFnError::ServerError(
+            "The last owner cannot be demoted".to_string(),
+        ));
+    }
+
+    log!("fn: set_member_role() - setting role {:?} for user {}", role, user_id);
+    sqlx::query("UPDATE user_room SET role = ? WHERE room_id = ? AND user_id = ?")
+        .bind(role.as_str())
+        .bind(&room_id)
+        .bind(user_id)
+        .execute(&pool)
+        .await?;
+
+    Ok(())
+}
+
+#[server(RemoveMember, "/api")]
+pub async fn remove_member(room_id: String, user_id: i64) -> Result<(), ServerFnError> {
+    use crate::models::room::Room;
+    use crate::state::{auth, pool};
+    use leptos::logging::log;
+
+    let pool = pool()?;
+    let auth = auth()?;
+
+    let user = auth
+        .current_user
+        .ok_or_else(|| ServerFnError::ServerError("User not logged in".to_string()))?;
+
+    let caller_role = Room::user_role(&room_id, user.id, &pool).await?;
+    if caller_role < Some(Role::Admin) {
+        log!("fn: remove_member() - user {} is not an admin of room {}", user.id, room_id);
+        return Err(ServerFnError::ServerError(
+            "Only the admins can remove members".to_string(),
+        ));
+    }
+
+    let member_role = Room::user_role(&room_id, user_id, &pool)
+        .await?
+        .ok_or_else(|| ServerFnError::ServerError("User is not in the room".to_string()))?;
+
+    if member_role == Role::Owner {
+        log!("fn: remove_member() - cannot remove an owner");
+        return Err(ServerFnError::ServerError(
+            "Owners cannot be removed".to_string(),
+        ));
+    }
+
+    log!("fn: remove_member() - removing user {} from room {}", user_id, room_id);
+    sqlx::query("DELETE FROM user_room WHERE room_id = ? AND user_id = ?")
+        .bind(&room_id)
+        .bind(user_id)
+        .execute(&pool)
+        .await?;
+
+    Ok(())
+}
+
+// Members can leave only once they are settled up.
+#[server(LeaveGroup, "/api")]
+pub async fn leave_group(room_id: String) -> Result<(), ServerFnError> {
+    use crate::models::room::Room;
+    use crate::services::balance::room_balances;
+    use crate::state::{auth, pool};
+    use leptos::logging::log;
+
+    let pool = pool()?;
+    let auth = auth()?;
+
+    let user = auth
+        .current_user
+        .ok_or_else(|| ServerFnError::ServerError("User not logged in".to_string()))?;
+
+    let role = Room::user_role(&room_id, user.id, &pool)
+        .await?
+        .ok_or_else(|| ServerFnError::ServerError("You are not a member of this room".to_string()))?;
+
+    if role == Role::Owner && Room::count_owners(&room_id, &pool).await? <= 1 {
+        log!("fn: leave_group() - user {} is the last owner of room {}", user.id, room_id);
+        return Err(ServerFnError::ServerError(
+            "Transfer the ownership before leaving the room".to_string(),
+        ));
+    }
+
+    let balance = room_balances(&room_id, &pool)
+        .await?
+        .get(&user.id)
+        .copied()
+        .unwrap_or_default();
+
+    if balance != 0 {
+        log!("fn: leave_group() - user {} has a balance of {} in room {}", user.id, balance, room_id);
+        return Err(ServerFnError::ServerError(
+            "Settle up your balance before leaving the room".to_string(),
+        ));
+    }
+
+    log!("fn: leave_group() - removing user {} from room {}", user.id, room_id);
+    sqlx::query("DELETE FROM user_room WHERE room_id = ? AND user_id = ?")
+        .bind(&room_id)
+        .bind(user.id)
+        .execute(&pool)
+        .await?;
+
+    sqlx::query("UPDATE user SET last_group_id = NULL WHERE id = ? AND last_group_id = ?")
+        .bind(user.id)
+        .bind(&room_id)
+        .execute(&pool)
+        .await?;
+
+    leptos_axum::redirect("/");
+
+    Ok(())
+}
+
+// Adds several users at once, the ones already in the room are skipped.
+#[server(AddMembers, "/api")]
+pub async fn add_members(room_id: String, usernames: Vec<String>) -> Result<AddMembersResult, ServerFnError> {
+    use crate::models::room::Room;
+    use crate::state::{auth, pool};
+    use leptos::logging::log;
+
+    let pool = pool()?;
+    let auth = auth()?;
+
+    let user = auth
+        .current_user
+        .ok_or_else(|| ServerFnError::ServerError("User not logged in".to_string()))?;
+
+    if Room::user_role(&room_id, user.id, &pool).await? < Some(Role::Admin) {
+        log!("fn: add_members() - user {} is not an admin of room {}", user.id, room_id);
+        return Err(ServerFnError::ServerError(
+            "Only the admins can add members".to_string(),
+        ));
+    }
+
+    let mut result = AddMembersResult::default();
+    let mut users: Vec<User> = vec![];
+
+    // the same lookup as the login, so " Alice " finds the account of alice
+    for username in usernames {
+        let username = User::normalize_username(&username);
+        if username.is_empty() || result.not_found.contains(&username) {
+            continue;
+        }
+
+        match User::get_user_from_username(username.clone(), &pool).await {
+            Some(found) if users.iter().all(|user| user.id != found.id) => users.push(found),
+            Some(_) => {}
+            None => result.not_found.push(username),
+        }
+    }
+
+    let mut tx = pool.begin().await?;
+
+    for found in users {
+        let inserted = sqlx::query("INSERT OR IGNORE INTO user_room (room_id, user_id) VALUES (?, ?)")
+            .bind(&room_id)
+            .bind(found.id)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+
+        if inserted > 0 {
+            result.added.push(found.username);
+        }
+    }
+
+    tx.commit().await?;
+
+    log!("fn: add_members() - added {:?} to room {}, not found: {:?}", result.added, room_id, result.not_found);
+
+    Ok(result)
+}
+
 #[component]
 pub fn UserInRoomComponent(room_id: String) -> impl IntoView {
     let users = create_resource(move || (), move |_| get_users_in_room(room_id.clone()));
@@ -36,7 +274,7 @@ pub fn UserInRoomComponent(room_id: String) -> impl IntoView {
                     .map(|user| {
                         view! {
                             <tr>
-                                <td>{user.username}</td>
+                                <td>{user.label()}</td>
                             </tr>
                         }
                     })
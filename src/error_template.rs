@@ -6,18 +6,67 @@ use thiserror::Error;
 #[cfg(feature = "ssr")]
 use leptos_axum::ResponseOptions;
 
+const SERVER_FN_ERROR_PREFIX: &str = "error running server function: ";
+
+// The `Display` text is what reaches the client, so it must stay stable.
+// Being an `Error`, it converts to `ServerFnError` with `?` in the server functions.
 #[derive(Clone, Debug, Error)]
 pub enum AppError {
     #[error("Not Found")]
     NotFound,
+    #[error("User not found")]
+    UserNotFound,
+    #[error("Invalid username or password")]
+    InvalidCredentials,
+    #[error("Unauthorized")]
+    Unauthorized,
+    #[error("Account deactivated")]
+    AccountDeactivated,
+    #[error("{0}")]
+    Validation(String),
+    // the details are only logged, they could leak the database schema
+    #[error("Something went wrong, please try again")]
+    Db(String),
 }
 
 impl AppError {
     pub fn status_code(&self) -> StatusCode {
         match self {
-            AppError::NotFound => StatusCode::NOT_FOUND,
+            AppError::NotFound | AppError::UserNotFound => StatusCode::NOT_FOUND,
+            AppError::InvalidCredentials | AppError::Unauthorized => StatusCode::UNAUTHORIZED,
+            AppError::AccountDeactivated => StatusCode::FORBIDDEN,
+            AppError::Validation(_) => StatusCode::BAD_REQUEST,
+            AppError::Db(_) => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
+
+    // Rebuilds the error on the client from the message sent by the server,
+    // the messages that are not known are validation errors.
+    pub fn from_server_fn_error(error: &ServerFnError) -> Self {
+        let message = error.to_string().replace(SERVER_FN_ERROR_PREFIX, "");
+
+        let known = [
+            AppError::NotFound,
+            AppError::UserNotFound,
+            AppError::InvalidCredentials,
+            AppError::Unauthorized,
+            AppError::AccountDeactivated,
+            AppError::Db(String::new()),
+        ];
+
+        return known
+            .into_iter()
+            .find(|error| error.to_string() == message)
+            .unwrap_or(AppError::Validation(message));
+    }
+}
+
+#[cfg(feature = "ssr")]
+impl From<sqlx::Error> for AppError {
+    fn from(error: sqlx::Error) -> Self {
+        log::error!("fn: from() - database error: {}", error);
+        AppError::Db(error.to_string())
+    }
 }
 
 // A basic function to display errors served by the error boundaries.
@@ -72,3 +121,29 @@ pub fn ErrorTemplate(
         />
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_credentials_round_trip_to_the_client() {
+        let error: ServerFnError = AppError::InvalidCredentials.into();
+
+        let error = AppError::from_server_fn_error(&error);
+
+        assert!(matches!(error, AppError::InvalidCredentials));
+        assert_eq!(error.to_string(), "Invalid username or password");
+        assert_eq!(error.status_code(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[test]
+    fn unknown_messages_are_validation_errors() {
+        let error = ServerFnError::ServerError("Amount must be positive".to_string());
+
+        assert!(matches!(
+            AppError::from_server_fn_error(&error),
+            AppError::Validation(message) if message == "Amount must be positive"
+        ));
+    }
+}
@@ -0,0 +1,170 @@
+use cfg_if::cfg_if;
+use leptos::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Lang {
+    #[default]
+    En,
+    It,
+}
+
+impl Lang {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Lang::En => "en",
+            Lang::It => "it",
+        }
+    }
+
+    // Picks the first supported language of an `Accept-Language` header
+    // (e.g. "it-IT,it;q=0.9,en;q=0.8"), the weights are in order anyway.
+    pub fn from_accept_language(header: &str) -> Self {
+        header
+            .split(',')
+            .filter_map(|language| language.split(';').next())
+            .find_map(|language| Lang::from_code(language.trim()))
+            .unwrap_or_default()
+    }
+
+    // Accepts both "it" and regional codes like "it-IT".
+    pub fn from_code(code: &str) -> Option<Self> {
+        let language = code.split(['-', '_']).next()?.to_lowercase();
+
+        match language.as_str() {
+            "en" => Some(Lang::En),
+            "it" => Some(Lang::It),
+            _ => None,
+        }
+    }
+
+    pub fn decimal_separator(&self) -> char {
+        match self {
+            Lang::En => '.',
+            Lang::It => ',',
+        }
+    }
+
+    pub fn thousands_separator(&self) -> char {
+        match self {
+            Lang::En => ',',
+            Lang::It => '.',
+        }
+    }
+
+    fn translations(&self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Lang::En => EN,
+            Lang::It => IT,
+        }
+    }
+}
+
+// The values can contain `{min}`, to be replaced by the caller.
+const EN: &[(&str, &str)] = &[
+    ("login.title", "Log In"),
+    ("login.submit", "LOGIN"),
+    ("login.remember_me", "Remember me"),
+    ("login.reactivate", "Reactivate my account"),
+    ("login.no_account", "Don't have an account? "),
+    ("login.register_now", "Register now!"),
+    ("register.title", "Register"),
+    ("register.submit", "REGISTER"),
+    ("register.has_account", "Already have an account? "),
+    ("register.login_now", "Login now!"),
+    ("field.username", "Username"),
+    ("field.email", "Email"),
+    ("field.password", "Password"),
+    ("field.confirm_password", "Confirm password"),
+    ("error.username_empty", "Username cannot be empty"),
+    ("error.username_too_short", "Username must be at least {min} characters long"),
+    ("error.username_taken", "Username already taken"),
+    ("error.email_empty", "Email cannot be empty"),
+    ("error.email_invalid", "Email is not valid"),
+    ("error.password_empty", "Password cannot be empty"),
+    ("error.password_too_short", "Password must be at least {min} characters long"),
+    ("error.password_needs_digit", "Password must contain a digit"),
+    ("error.password_needs_symbol", "Password must contain a symbol"),
+    ("error.passwords_do_not_match", "Passwords do not match"),
+];
+
+const IT: &[(&str, &str)] = &[
+    ("login.title", "Accedi"),
+    ("login.submit", "ACCEDI"),
+    ("login.remember_me", "Ricordami"),
+    ("login.reactivate", "Riattiva il mio account"),
+    ("login.no_account", "Non hai un account? "),
+    ("login.register_now", "Registrati ora!"),
+    ("register.title", "Registrati"),
+    ("register.submit", "REGISTRATI"),
+    ("register.has_account", "Hai già un account? "),
+    ("register.login_now", "Accedi ora!"),
+    ("field.username", "Nome utente"),
+    ("field.email", "Email"),
+    ("field.password", "Password"),
+    ("field.confirm_password", "Conferma password"),
+    ("error.username_empty", "Il nome utente non può essere vuoto"),
+    ("error.username_too_short", "Il nome utente deve essere lungo almeno {min} caratteri"),
+    ("error.username_taken", "Nome utente già in uso"),
+    ("error.email_empty", "L'email non può essere vuota"),
+    ("error.email_invalid", "L'email non è valida"),
+    ("error.password_empty", "La password non può essere vuota"),
+    ("error.password_too_short", "La password deve essere lunga almeno {min} caratteri"),
+    ("error.password_needs_digit", "La password deve contenere una cifra"),
+    ("error.password_needs_symbol", "La password deve contenere un simbolo"),
+    ("error.passwords_do_not_match", "Le password non coincidono"),
+];
+
+// Falls back to English for the keys missing in `lang`.
+pub fn t(key: &str, lang: Lang) -> &'static str {
+    let find = |translations: &'static [(&'static str, &'static str)]| {
+        translations
+            .iter()
+            .find(|(k, _)| *k == key)
+            .map(|(_, value)| *value)
+    };
+
+    find(lang.translations())
+        .or_else(|| find(EN))
+        .unwrap_or_else(|| {
+            log::warn!("fn: t() - missing translation for {}", key);
+            ""
+        })
+}
+
+cfg_if! {
+    if #[cfg(feature = "ssr")] {
+        fn initial_lang() -> Lang {
+            use_context::<http::request::Parts>()
+                .and_then(|parts| {
+                    parts
+                        .headers
+                        .get(http::header::ACCEPT_LANGUAGE)
+                        .and_then(|value| value.to_str().ok())
+                        .map(Lang::from_accept_language)
+                })
+                .unwrap_or_default()
+        }
+    } else {
+        // the server renders the language in <html lang="..">, so the client
+        // hydrates with the same one
+        fn initial_lang() -> Lang {
+            document()
+                .document_element()
+                .and_then(|element| element.get_attribute("lang"))
+                .and_then(|code| Lang::from_code(&code))
+                .unwrap_or_default()
+        }
+    }
+}
+
+pub fn provide_lang() -> RwSignal<Lang> {
+    let lang = create_rw_signal(initial_lang());
+    provide_context(lang);
+
+    return lang;
+}
+
+pub fn use_lang() -> RwSignal<Lang> {
+    use_context::<RwSignal<Lang>>().unwrap_or_else(|| create_rw_signal(Lang::default()))
+}
@@ -1,10 +1,17 @@
 pub mod app;
 pub mod fileserv;
+pub mod i18n;
+pub mod logger;
 pub mod models;
 pub mod pages;
 pub mod state;
 pub mod error_template;
 pub mod components;
+pub mod services;
+pub mod utils;
+
+#[cfg(all(test, feature = "ssr"))]
+pub mod test_utils;
 use cfg_if::cfg_if;
 
 cfg_if! {
@@ -0,0 +1,49 @@
+use cfg_if::cfg_if;
+
+cfg_if! {
+    if #[cfg(feature = "ssr")] {
+        use log::{Level, Log, Metadata, Record};
+
+        // Writes one JSON object per line, for log aggregators.
+        struct JsonLogger {
+            level: Level,
+        }
+
+        impl Log for JsonLogger {
+            fn enabled(&self, metadata: &Metadata) -> bool {
+                metadata.level() <= self.level
+            }
+
+            fn log(&self, record: &Record) {
+                if !self.enabled(record.metadata()) {
+                    return;
+                }
+
+                let line = serde_json::json!({
+                    "level": record.level().to_string(),
+                    "timestamp": chrono::Utc::now().to_rfc3339(),
+                    "message": record.args().to_string(),
+                    "target": record.target(),
+                });
+
+                println!("{}", line);
+            }
+
+            fn flush(&self) {}
+        }
+
+        // Uses the JSON format when LOG_FORMAT=json, the plain one otherwise.
+        pub fn init(level: Level) {
+            match std::env::var("LOG_FORMAT").as_deref() {
+                Ok("json") => {
+                    log::set_logger(Box::leak(Box::new(JsonLogger { level })))
+                        .map(|()| log::set_max_level(level.to_level_filter()))
+                        .expect("couldn't initialize logging");
+                }
+                _ => {
+                    simple_logger::init_with_level(level).expect("couldn't initialize logging");
+                }
+            }
+        }
+    }
+}
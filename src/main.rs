@@ -4,28 +4,54 @@ cfg_if! {
     if #[cfg(feature = "ssr")] {
         use axum::{
             response::{Response, IntoResponse},
-            routing::get,
-            extract::{Path, State, RawQuery},
-            http::{Request, header::HeaderMap},
+            routing::{get, post},
+            extract::{Path, State, RawQuery, Multipart, DefaultBodyLimit, ConnectInfo, ws::{Message, WebSocket, WebSocketUpgrade}},
+            http::{Request, StatusCode, header::{self, HeaderMap}},
             body::Body as AxumBody,
             Router,
         };
         use leptos_axum::{generate_route_list, LeptosRoutes, handle_server_fns_with_context};
         use leptos::{logging::log, view, provide_context, get_configuration};
-        use sqlx::{SqlitePool, sqlite::SqlitePoolOptions};
-        use expenses_splitter::state::AppState;
+        use sqlx::{SqlitePool, sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions}};
+        use expenses_splitter::state::{run_migrations, AppState, ClientIp};
+        use expenses_splitter::services::recurring::post_due_recurring_expenses;
+        use expenses_splitter::services::reminders::record_overdue_reminders;
+        use expenses_splitter::services::report::room_report_pdf;
+        use expenses_splitter::services::thumbnail::{image_mime_type, make_thumbnail};
+        use expenses_splitter::services::password::Argon2Hasher;
+        use expenses_splitter::state::metrics::Metrics;
+        use expenses_splitter::state::rate_limiter::RateLimiter;
+        use expenses_splitter::state::room_events::{RoomEvent, RoomEvents};
+        use expenses_splitter::utils::validation::PasswordPolicy;
+        use tokio::sync::broadcast;
+        use expenses_splitter::pages::auth::{LOGIN_MAX_ATTEMPTS, LOGIN_ATTEMPTS_WINDOW, REGISTER_MAX_ATTEMPTS, REGISTER_ATTEMPTS_WINDOW, SESSION_TABLE_NAME};
+        use std::sync::Arc;
+        use std::net::SocketAddr;
         use expenses_splitter::models::user::User;
+        use expenses_splitter::models::room::Room;
+        use expenses_splitter::models::expense::Expense;
+        use expenses_splitter::models::attachment::Attachment;
         use expenses_splitter::app::App;
-        use axum_session::{SessionConfig, SessionLayer, SessionStore};
+        use axum_session::{SameSite, SessionConfig, SessionLayer, SessionStore};
         use axum_session_auth::{AuthSessionLayer, AuthConfig, SessionSqlitePool};
         use expenses_splitter::pages::auth::AuthSession;
 
-        async fn server_fn_handler(State(app_state): State<AppState>, auth_session: AuthSession,path: Path<String>, headers: HeaderMap, raw_query: RawQuery,
-            request: Request<AxumBody>) -> impl IntoResponse {
+        const DEFAULT_DATABASE_URL: &str = "sqlite:expenses.db";
+        const DEFAULT_DB_MAX_CONNECTIONS: u32 = 5;
+        const DB_BUSY_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(5000);
+        const MAX_ATTACHMENT_BYTES: usize = 5 * 1024 * 1024;
+        const DEFAULT_SESSION_COOKIE_NAME: &str = "session";
+
+        async fn server_fn_handler(State(app_state): State<AppState>, auth_session: AuthSession, ConnectInfo(client_addr): ConnectInfo<SocketAddr>,
+            path: Path<String>, headers: HeaderMap, raw_query: RawQuery, request: Request<AxumBody>) -> impl IntoResponse {
+
+            let client_ip = ClientIp::from_request(&headers, client_addr, app_state.behind_proxy);
 
             handle_server_fns_with_context(path, headers, raw_query, move || {
                 provide_context(auth_session.clone());
+                provide_context(client_ip);
                 provide_context(app_state.pool.clone());
+                provide_context(app_state.clone());
             }, request).await
         }
 
@@ -34,38 +60,379 @@ cfg_if! {
                 move || {
                     provide_context(auth_session.clone());
                     provide_context(app_state.pool.clone());
+                    provide_context(app_state.clone());
                 },
                 || view! {<App/> }
             );
             handler(req).await.into_response()
         }
 
+        async fn health_handler(State(pool): State<SqlitePool>) -> impl IntoResponse {
+            match sqlx::query("SELECT 1").execute(&pool).await {
+                Ok(_) => (StatusCode::OK, "ok"),
+                Err(e) => {
+                    log::error!("fn: health_handler() - database unreachable: {}", e);
+                    (StatusCode::SERVICE_UNAVAILABLE, "database unavailable")
+                }
+            }
+        }
+
+        // compares every byte, so that the time taken doesn't tell how much of the token is right
+        fn tokens_match(provided: &str, expected: &str) -> bool {
+            provided.len() == expected.len()
+                && provided.bytes().zip(expected.bytes()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+        }
+
+        // Only for the scrapers sending `Authorization: Bearer <METRICS_TOKEN>`,
+        // without the variable the endpoint is disabled.
+        async fn metrics_handler(State(app_state): State<AppState>, headers: HeaderMap) -> Response {
+            let Some(token) = &app_state.metrics_token else {
+                return StatusCode::NOT_FOUND.into_response();
+            };
+
+            let provided = headers
+                .get(header::AUTHORIZATION)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.strip_prefix("Bearer "));
+
+            if !provided.is_some_and(|provided| tokens_match(provided, token)) {
+                return StatusCode::UNAUTHORIZED.into_response();
+            }
+
+            ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], app_state.metrics.render()).into_response()
+        }
+
+        // the attachment is readable/writable only by the members of the expense room
+        async fn can_access_expense(expense_id: i64, auth_session: &AuthSession, pool: &SqlitePool) -> Result<(), StatusCode> {
+            let user = auth_session.current_user.as_ref().ok_or(StatusCode::UNAUTHORIZED)?;
+            let expense = Expense::get(expense_id, pool).await.ok_or(StatusCode::NOT_FOUND)?;
+
+            match Room::is_member(&expense.room_id, user.id, pool).await {
+                Ok(true) => Ok(()),
+                Ok(false) => Err(StatusCode::FORBIDDEN),
+                Err(e) => {
+                    log::error!("fn: can_access_expense() - error: {}", e);
+                    Err(StatusCode::INTERNAL_SERVER_ERROR)
+                }
+            }
+        }
+
+        async fn upload_attachment_handler(auth_session: AuthSession, State(pool): State<SqlitePool>, Path(expense_id): Path<i64>,
+            mut multipart: Multipart) -> Result<String, StatusCode> {
+
+            can_access_expense(expense_id, &auth_session, &pool).await?;
+
+            let field = multipart.next_field().await
+                .map_err(|_| StatusCode::BAD_REQUEST)?
+                .ok_or(StatusCode::BAD_REQUEST)?;
+            let field_content_type = field.content_type().map(str::to_string);
+
+            let filename = field.file_name().unwrap_or("receipt").to_string();
+            let data = field.bytes().await.map_err(|_| StatusCode::PAYLOAD_TOO_LARGE)?;
+            if data.len() > MAX_ATTACHMENT_BYTES {
+                return Err(StatusCode::PAYLOAD_TOO_LARGE);
+            }
+
+            // the declared content type is not trusted, it's served back as is
+            let mime_type = image_mime_type(&data).ok_or_else(|| {
+                log!("fn: upload_attachment_handler() - rejected {:?}, declared as {:?}", filename, field_content_type);
+                StatusCode::UNSUPPORTED_MEDIA_TYPE
+            })?;
+
+            // nothing is stored if the bytes can't be decoded as an image
+            let image = data.clone();
+            let thumbnail = tokio::task::spawn_blocking(move || make_thumbnail(&image))
+                .await
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+                .map_err(|e| {
+                    log!("fn: upload_attachment_handler() - invalid image: {}", e);
+                    StatusCode::UNPROCESSABLE_ENTITY
+                })?;
+
+            let id = Attachment::create(expense_id, &filename, mime_type, &data, &thumbnail, &pool).await.map_err(|e| {
+                log::error!("fn: upload_attachment_handler() - error: {}", e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+
+            Ok(id.to_string())
+        }
+
+        async fn get_attachment_handler(auth_session: AuthSession, State(pool): State<SqlitePool>, Path(id): Path<i64>) -> Result<Response, StatusCode> {
+            let attachment = Attachment::get(id, &pool).await
+                .map_err(|e| {
+                    log::error!("fn: get_attachment_handler() - error: {}", e);
+                    StatusCode::INTERNAL_SERVER_ERROR
+                })?
+                .ok_or(StatusCode::NOT_FOUND)?;
+
+            can_access_expense(attachment.expense_id, &auth_session, &pool).await?;
+
+            // the attachments stored before the type was checked can be anything
+            let mime_type = image_mime_type(&attachment.data).ok_or(StatusCode::UNSUPPORTED_MEDIA_TYPE)?;
+
+            Ok((
+                [(header::CONTENT_TYPE, mime_type), (header::X_CONTENT_TYPE_OPTIONS, "nosniff")],
+                attachment.data,
+            ).into_response())
+        }
+
+        async fn get_attachment_thumbnail_handler(auth_session: AuthSession, State(pool): State<SqlitePool>, Path(id): Path<i64>) -> Result<Response, StatusCode> {
+            let attachment = Attachment::get(id, &pool).await
+                .map_err(|e| {
+                    log::error!("fn: get_attachment_thumbnail_handler() - error: {}", e);
+                    StatusCode::INTERNAL_SERVER_ERROR
+                })?
+                .ok_or(StatusCode::NOT_FOUND)?;
+
+            can_access_expense(attachment.expense_id, &auth_session, &pool).await?;
+
+            let thumbnail = attachment.thumbnail.ok_or(StatusCode::NOT_FOUND)?;
+
+            Ok(([(header::CONTENT_TYPE, "image/jpeg"), (header::X_CONTENT_TYPE_OPTIONS, "nosniff")], thumbnail).into_response())
+        }
+
+        // posts the due recurring expenses every hour
+        fn spawn_recurring_expenses_task(pool: SqlitePool) {
+            tokio::spawn(async move {
+                let mut interval = tokio::time::interval(std::time::Duration::from_secs(60 * 60));
+
+                loop {
+                    interval.tick().await;
+
+                    let today = chrono::Local::now().date_naive();
+                    if let Err(e) = post_due_recurring_expenses(today, &pool).await {
+                        log::error!("fn: spawn_recurring_expenses_task() - error: {}", e);
+                    }
+                }
+            });
+        }
+
+        async fn room_report_handler(auth_session: AuthSession, State(pool): State<SqlitePool>, Path(room_id): Path<String>) -> Result<Response, StatusCode> {
+            let user = auth_session.current_user.as_ref().ok_or(StatusCode::UNAUTHORIZED)?;
+
+            match Room::is_member(&room_id, user.id, &pool).await {
+                Ok(true) => {}
+                Ok(false) => return Err(StatusCode::FORBIDDEN),
+                Err(e) => {
+                    log::error!("fn: room_report_handler() - error: {}", e);
+                    return Err(StatusCode::INTERNAL_SERVER_ERROR);
+                }
+            }
+
+            let pdf = room_report_pdf(&room_id, &pool).await.map_err(|e| {
+                log::error!("fn: room_report_handler() - error: {}", e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+
+            Ok((
+                [
+                    (header::CONTENT_TYPE, "application/pdf"),
+                    (header::CONTENT_DISPOSITION, "attachment; filename=\"report.pdf\""),
+                ],
+                pdf,
+            ).into_response())
+        }
+
+        // only the members of the room can follow its events
+        async fn room_events_handler(ws: WebSocketUpgrade, auth_session: AuthSession, State(pool): State<SqlitePool>,
+            State(room_events): State<Arc<RoomEvents>>, Path(room_id): Path<String>) -> Result<Response, StatusCode> {
+
+            let user = auth_session.current_user.as_ref().ok_or(StatusCode::UNAUTHORIZED)?;
+
+            match Room::is_member(&room_id, user.id, &pool).await {
+                Ok(true) => {}
+                Ok(false) => return Err(StatusCode::FORBIDDEN),
+                Err(e) => {
+                    log::error!("fn: room_events_handler() - error: {}", e);
+                    return Err(StatusCode::INTERNAL_SERVER_ERROR);
+                }
+            }
+
+            let receiver = room_events.subscribe(&room_id);
+            Ok(ws.on_upgrade(move |socket| forward_room_events(socket, receiver)))
+        }
+
+        // the receiver is dropped when the client disconnects
+        async fn forward_room_events(mut socket: WebSocket, mut receiver: broadcast::Receiver<RoomEvent>) {
+            loop {
+                tokio::select! {
+                    event = receiver.recv() => match event {
+                        Ok(event) => {
+                            let Ok(json) = serde_json::to_string(&event) else { continue };
+                            if socket.send(Message::Text(json)).await.is_err() {
+                                break;
+                            }
+                        }
+                        // the skipped events don't matter, the client refetches everything anyway
+                        Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    },
+                    message = socket.recv() => match message {
+                        Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                        _ => {}
+                    },
+                }
+            }
+        }
+
+        // purges every night the expenses soft-deleted more than 30 days ago
+        fn spawn_purge_deleted_expenses_task(pool: SqlitePool) {
+            tokio::spawn(async move {
+                let mut interval = tokio::time::interval(std::time::Duration::from_secs(24 * 60 * 60));
+
+                loop {
+                    interval.tick().await;
+
+                    let cutoff = chrono::Utc::now().naive_utc() - chrono::Duration::days(30);
+                    if let Err(e) = Expense::purge_deleted_before(cutoff, &pool).await {
+                        log::error!("fn: spawn_purge_deleted_expenses_task() - error: {}", e);
+                    }
+                }
+            });
+        }
+
+        // reminds every day the debts not settled for a week
+        fn spawn_reminders_task(pool: SqlitePool) {
+            tokio::spawn(async move {
+                let mut interval = tokio::time::interval(std::time::Duration::from_secs(24 * 60 * 60));
+
+                loop {
+                    interval.tick().await;
+
+                    let cutoff = chrono::Utc::now().naive_utc() - chrono::Duration::days(7);
+                    if let Err(e) = record_overdue_reminders(cutoff, &pool).await {
+                        log::error!("fn: spawn_reminders_task() - error: {}", e);
+                    }
+                }
+            });
+        }
+
+        async fn shutdown_signal() {
+            let ctrl_c = async {
+                tokio::signal::ctrl_c()
+                    .await
+                    .expect("failed to install Ctrl+C handler");
+            };
+
+            #[cfg(unix)]
+            let terminate = async {
+                tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+                    .expect("failed to install SIGTERM handler")
+                    .recv()
+                    .await;
+            };
+
+            #[cfg(not(unix))]
+            let terminate = std::future::pending::<()>();
+
+            tokio::select! {
+                _ = ctrl_c => {},
+                _ = terminate => {},
+            }
+
+            log::info!("fn: shutdown_signal() - shutting down gracefully");
+        }
+
         #[tokio::main]
         async fn main() {
             use expenses_splitter::app::*;
             use expenses_splitter::fileserv::file_and_error_handler;
 
-            simple_logger::init_with_level(log::Level::Info).expect("couldn't initialize logging");
+            expenses_splitter::logger::init(log::Level::Info);
 
             let conf = get_configuration(None).await.unwrap();
             let leptos_options = conf.leptos_options;
-            let addr = leptos_options.site_addr;
+            let addr = match std::env::var("BIND_ADDR") {
+                Ok(value) => value.parse::<SocketAddr>().unwrap_or_else(|_| panic!("BIND_ADDR must be an address like 0.0.0.0:3000, got {:?}", value)),
+                Err(_) => leptos_options.site_addr,
+            };
             let routes = generate_route_list(|| view! { <App/> });
 
+            let database_url = std::env::var("DATABASE_URL").unwrap_or_else(|_| DEFAULT_DATABASE_URL.to_string());
+            let max_connections = match std::env::var("DB_MAX_CONNECTIONS") {
+                Ok(value) => value.parse::<u32>().expect("DB_MAX_CONNECTIONS must be a positive integer"),
+                Err(_) => DEFAULT_DB_MAX_CONNECTIONS,
+            };
+
+            // WAL lets the readers run alongside a writer, the busy timeout makes
+            // the writers wait for each other instead of failing with SQLITE_BUSY.
+            // The foreign keys are off by default in SQLite, without them nothing cascades.
+            let connect_options = database_url
+                .parse::<SqliteConnectOptions>()
+                .unwrap_or_else(|e| panic!("DATABASE_URL is not a valid SQLite url: {}", e))
+                .journal_mode(SqliteJournalMode::Wal)
+                .foreign_keys(true)
+                .busy_timeout(DB_BUSY_TIMEOUT);
+
+            log::info!("fn: main - connecting to {} with {} max connections", database_url, max_connections);
             let pool = SqlitePoolOptions::new()
-                .connect("sqlite:expenses.db")
+                .max_connections(max_connections)
+                .connect_with(connect_options)
                 .await
-                .expect("Could not make pool.");
+                .unwrap_or_else(|e| panic!("Could not connect to the database at {}: {}", database_url, e));
 
             log::info!("fn: main - running migrations...");
-            sqlx::migrate!()
-                .run(&pool)
+            run_migrations(&pool)
                 .await
                 .expect("could not run SQLx migrations");
             log::info!("fn: main - migrations done");
 
+            spawn_recurring_expenses_task(pool.clone());
+            spawn_purge_deleted_expenses_task(pool.clone());
+            spawn_reminders_task(pool.clone());
+
+            // the proxy terminates TLS, so the cookies can be marked as secure, and
+            // sets X-Forwarded-For, the only way to know the address of the client
+            let behind_proxy = std::env::var("BEHIND_PROXY").map(|value| value == "1" || value.eq_ignore_ascii_case("true")).unwrap_or(false);
+            if behind_proxy {
+                log::info!("fn: main - expecting a TLS terminating proxy, session cookies are secure");
+            } else {
+                log::warn!("fn: main - not behind a TLS terminating proxy, session cookies can be sent over plain HTTP");
+            }
+
+            let password_policy = PasswordPolicy::from_env().unwrap_or_else(|e| panic!("invalid password policy: {}", e));
+            log::info!("fn: main - password policy: {:?}", password_policy);
+
+            // the bcrypt cost only mattered for the new hashes, which are Argon2 now
+            if std::env::var("BCRYPT_COST").is_ok() {
+                log::warn!("fn: main - BCRYPT_COST is deprecated and ignored, use ARGON2_MEMORY_KIB, ARGON2_ITERATIONS and ARGON2_PARALLELISM");
+            }
+
+            let password_hasher = Argon2Hasher::from_env();
+            log::info!("fn: main - password hasher: {:?}", password_hasher);
+
+            let metrics_token = std::env::var("METRICS_TOKEN").ok().filter(|token| !token.is_empty());
+            if metrics_token.is_none() {
+                log::info!("fn: main - METRICS_TOKEN is not set, /metrics is disabled");
+            }
+
+            let session_cookie_name = std::env::var("SESSION_COOKIE_NAME").unwrap_or_else(|_| DEFAULT_SESSION_COOKIE_NAME.to_string());
+
+            // the groups are shared through links, with `Strict` the users that
+            // open one from another site look logged out
+            let session_same_site = match std::env::var("SESSION_SAME_SITE") {
+                Ok(value) if value.eq_ignore_ascii_case("lax") => SameSite::Lax,
+                Ok(value) if value.eq_ignore_ascii_case("strict") => SameSite::Strict,
+                Ok(value) => panic!("SESSION_SAME_SITE must be Lax or Strict, got {:?}", value),
+                Err(_) => SameSite::Lax,
+            };
+
+            log::info!(
+                "fn: main - session cookie: Set-Cookie: {}=...; Path=/; HttpOnly; SameSite={:?}{}",
+                session_cookie_name,
+                session_same_site,
+                if behind_proxy { "; Secure" } else { "" },
+            );
+
             // Auth section
-            let session_config = SessionConfig::default().with_table_name("axum_sessions");
+            // short sessions by default, "remember me" sessions are long term
+            let session_config = SessionConfig::default()
+                .with_table_name(SESSION_TABLE_NAME)
+                .with_cookie_name(&session_cookie_name)
+                .with_cookie_same_site(session_same_site)
+                .with_lifetime(chrono::Duration::hours(1))
+                .with_max_lifetime(chrono::Duration::weeks(2))
+                .with_secure(behind_proxy);
             let auth_config = AuthConfig::<i64>::default();
             let session_store = SessionStore::<SessionSqlitePool>::new(Some(pool.clone().into()), session_config).await.unwrap();
 
@@ -73,10 +440,28 @@ cfg_if! {
                 leptos_options,
                 pool: pool.clone(),
                 routes: routes.clone(),
+                login_rate_limiter: Arc::new(RateLimiter::new(LOGIN_MAX_ATTEMPTS, LOGIN_ATTEMPTS_WINDOW)),
+                register_rate_limiter: Arc::new(RateLimiter::new(REGISTER_MAX_ATTEMPTS, REGISTER_ATTEMPTS_WINDOW)),
+                room_events: Arc::new(RoomEvents::default()),
+                password_policy,
+                password_hasher,
+                session_store: session_store.clone(),
+                metrics: Arc::new(Metrics::default()),
+                behind_proxy,
+                metrics_token,
             };
 
             // build our application with a route
             let app = Router::new()
+                .route("/health", get(health_handler))
+                .route("/metrics", get(metrics_handler))
+                .route("/api/expense/:id/attachment", post(upload_attachment_handler)
+                    // leaves some room for the multipart boundaries, the file size is checked in the handler
+                    .layer(DefaultBodyLimit::max(MAX_ATTACHMENT_BYTES + 64 * 1024)))
+                .route("/api/attachment/:id", get(get_attachment_handler))
+                .route("/api/attachment/:id/thumb", get(get_attachment_thumbnail_handler))
+                .route("/ws/group/:id", get(room_events_handler))
+                .route("/api/group/:id/report.pdf", get(room_report_handler))
                 .route("/api/*fn_name", get(server_fn_handler).post(server_fn_handler))
                 .leptos_routes_with_handler(routes, get(leptos_routes_handler) )
                 .fallback(file_and_error_handler)
@@ -89,9 +474,14 @@ cfg_if! {
             // `axum::Server` is a re-export of `hyper::Server`
             log!("listening on http://{}", &addr);
             axum::Server::bind(&addr)
-                .serve(app.into_make_service())
+                .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+                .with_graceful_shutdown(shutdown_signal())
                 .await
                 .unwrap();
+
+            // closing the pool flushes the pending WAL checkpoints
+            pool.close().await;
+            log::info!("fn: main - server stopped");
         }
     } else {
         pub fn main() {
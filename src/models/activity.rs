@@ -0,0 +1,156 @@
+use cfg_if::cfg_if;
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+// Stored as a code, the UI builds the sentence from it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ActivityAction {
+    ExpenseCreated,
+    ExpenseEdited,
+    ExpenseDeleted,
+    ExpenseRestored,
+    SettlementRecorded,
+}
+
+impl ActivityAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ActivityAction::ExpenseCreated => "expense_created",
+            ActivityAction::ExpenseEdited => "expense_edited",
+            ActivityAction::ExpenseDeleted => "expense_deleted",
+            ActivityAction::ExpenseRestored => "expense_restored",
+            ActivityAction::SettlementRecorded => "settlement_recorded",
+        }
+    }
+}
+
+impl FromStr for ActivityAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "expense_created" => Ok(ActivityAction::ExpenseCreated),
+            "expense_edited" => Ok(ActivityAction::ExpenseEdited),
+            "expense_deleted" => Ok(ActivityAction::ExpenseDeleted),
+            "expense_restored" => Ok(ActivityAction::ExpenseRestored),
+            "settlement_recorded" => Ok(ActivityAction::SettlementRecorded),
+            _ => Err(format!("Unknown activity action: {}", s)),
+        }
+    }
+}
+
+// The values of an expense before an edit, stored in the activity to undo it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExpenseSnapshot {
+    pub title: String,
+    pub description: Option<String>,
+    pub amount_cents: i64,
+    // only for the exact splits, the other methods don't store cents
+    pub split_amounts: Vec<(i64, i64)>,
+    // missing in the snapshots taken before the expenses had it
+    #[serde(default)]
+    pub occurred_on: Option<NaiveDate>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Activity {
+    pub id: i64,
+    pub room_id: String,
+    pub user_id: i64,
+    pub action: ActivityAction,
+    pub entity_id: i64,
+    pub created_at: Option<NaiveDateTime>,
+}
+
+cfg_if! {
+    if #[cfg(feature = "ssr")] {
+        use sqlx::sqlite::SqlitePool;
+
+        type ActivityRow = (i64, String, i64, String, i64, Option<NaiveDateTime>);
+
+        // Records that `user_id` did `action` on the expense or settlement `entity_id`.
+        pub async fn log_activity(pool: &SqlitePool, room_id: &str, user_id: i64, action: ActivityAction, entity_id: i64) -> Result<(), sqlx::Error> {
+            return log_activity_with_details(pool, room_id, user_id, action, entity_id, None).await;
+        }
+
+        // `details` is the JSON needed to undo the action, if any.
+        pub async fn log_activity_with_details(pool: &SqlitePool, room_id: &str, user_id: i64, action: ActivityAction, entity_id: i64, details: Option<String>) -> Result<(), sqlx::Error> {
+            log::info!("fn: log_activity() - user {} {} {}", user_id, action.as_str(), entity_id);
+
+            sqlx::query("INSERT INTO activity (room_id, user_id, action, entity_id, details) VALUES (?, ?, ?, ?, ?)")
+                .bind(room_id)
+                .bind(user_id)
+                .bind(action.as_str())
+                .bind(entity_id)
+                .bind(details)
+                .execute(pool)
+                .await?;
+
+            return Ok(());
+        }
+
+        impl Activity {
+            // The last action of the user in the room not undone yet, with its details,
+            // if it happened less than `max_age` ago (a SQLite datetime modifier).
+            pub async fn last_undoable(room_id: &str, user_id: i64, max_age: &str, pool: &SqlitePool) -> Result<Option<(Self, Option<String>)>, sqlx::Error> {
+                let row = sqlx::query_as::<_, (i64, String, i64, String, i64, Option<NaiveDateTime>, Option<String>, bool)>(
+                    "SELECT id, room_id, user_id, action, entity_id, created_at, details, created_at >= datetime('now', ?) FROM activity WHERE room_id = ? AND user_id = ? AND undone_at IS NULL ORDER BY created_at DESC, id DESC LIMIT 1",
+                )
+                .bind(max_age)
+                .bind(room_id)
+                .bind(user_id)
+                .fetch_optional(pool)
+                .await?;
+
+                let Some((id, room_id, user_id, action, entity_id, created_at, details, is_recent)) = row else {
+                    return Ok(None);
+                };
+
+                if !is_recent {
+                    return Ok(None);
+                }
+
+                let activity = action.parse().ok().map(|action| Activity {
+                    id,
+                    room_id,
+                    user_id,
+                    action,
+                    entity_id,
+                    created_at,
+                });
+
+                return Ok(activity.map(|activity| (activity, details)));
+            }
+
+            pub async fn list_for_room(room_id: &str, limit: i64, pool: &SqlitePool) -> Result<Vec<Self>, sqlx::Error> {
+                log::info!("fn: list_for_room()");
+
+                let rows = sqlx::query_as::<_, ActivityRow>(
+                    "SELECT id, room_id, user_id, action, entity_id, created_at FROM activity WHERE room_id = ? ORDER BY created_at DESC, id DESC LIMIT ?",
+                )
+                .bind(room_id)
+                .bind(limit)
+                .fetch_all(pool)
+                .await?;
+
+                // rows with an action unknown to this version are skipped
+                let activities = rows
+                    .into_iter()
+                    .filter_map(|(id, room_id, user_id, action, entity_id, created_at)| {
+                        Some(Activity {
+                            id,
+                            room_id,
+                            user_id,
+                            action: action.parse().ok()?,
+                            entity_id,
+                            created_at,
+                        })
+                    })
+                    .collect();
+
+                return Ok(activities);
+            }
+        }
+    }
+}
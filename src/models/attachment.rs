@@ -0,0 +1,48 @@
+use cfg_if::cfg_if;
+
+cfg_if! {
+    if #[cfg(feature = "ssr")] {
+        use sqlx::sqlite::SqlitePool;
+
+        #[derive(sqlx::FromRow, Debug, Clone)]
+        pub struct Attachment {
+            pub id: i64,
+            pub expense_id: i64,
+            pub filename: String,
+            pub mime_type: String,
+            pub data: Vec<u8>,
+            pub thumbnail: Option<Vec<u8>>,
+            pub created_at: Option<sqlx::types::chrono::NaiveDateTime>,
+        }
+
+        impl Attachment {
+            // The image and its thumbnail are stored by the same statement, so
+            // there is never an attachment without a thumbnail.
+            pub async fn create(expense_id: i64, filename: &str, mime_type: &str, data: &[u8], thumbnail: &[u8], pool: &SqlitePool) -> Result<i64, sqlx::Error> {
+                log::info!("fn: create() - {} bytes for expense {}", data.len(), expense_id);
+
+                let id = sqlx::query_scalar(
+                    "INSERT INTO attachment (expense_id, filename, mime_type, data, thumbnail) VALUES (?, ?, ?, ?, ?) RETURNING id",
+                )
+                .bind(expense_id)
+                .bind(filename)
+                .bind(mime_type)
+                .bind(data)
+                .bind(thumbnail)
+                .fetch_one(pool)
+                .await?;
+
+                return Ok(id);
+            }
+
+            pub async fn get(id: i64, pool: &SqlitePool) -> Result<Option<Self>, sqlx::Error> {
+                let attachment = sqlx::query_as::<_, Attachment>("SELECT * FROM attachment WHERE id = ?")
+                    .bind(id)
+                    .fetch_optional(pool)
+                    .await?;
+
+                return Ok(attachment);
+            }
+        }
+    }
+}
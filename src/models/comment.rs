@@ -0,0 +1,50 @@
+use cfg_if::cfg_if;
+use serde::{Deserialize, Serialize};
+
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Comment {
+    pub id: i64,
+    pub expense_id: i64,
+    pub user_id: i64,
+    pub user_label: String,
+    pub body: String,
+
+    #[cfg(feature = "ssr")]
+    pub created_at: Option<sqlx::types::chrono::NaiveDateTime>,
+}
+
+cfg_if! {
+    if #[cfg(feature = "ssr")] {
+        use sqlx::sqlite::SqlitePool;
+
+        impl Comment {
+            pub async fn create(expense_id: i64, user_id: i64, body: &str, pool: &SqlitePool) -> Result<i64, sqlx::Error> {
+                log::info!("fn: create()");
+
+                let id = sqlx::query_scalar("INSERT INTO comment (expense_id, user_id, body) VALUES (?, ?, ?) RETURNING id")
+                    .bind(expense_id)
+                    .bind(user_id)
+                    .bind(body)
+                    .fetch_one(pool)
+                    .await?;
+
+                return Ok(id);
+            }
+
+            // Oldest first, with the label of the commenter.
+            pub async fn list_for_expense(expense_id: i64, pool: &SqlitePool) -> Result<Vec<Self>, sqlx::Error> {
+                log::info!("fn: list_for_expense()");
+
+                let comments = sqlx::query_as::<_, Comment>(
+                    "SELECT comment.id, comment.expense_id, comment.user_id, COALESCE(user.display_name, user.username) AS user_label, comment.body, comment.created_at FROM comment JOIN user ON user.id = comment.user_id WHERE comment.expense_id = ? ORDER BY comment.created_at, comment.id",
+                )
+                .bind(expense_id)
+                .fetch_all(pool)
+                .await?;
+
+                return Ok(comments);
+            }
+        }
+    }
+}
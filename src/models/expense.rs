@@ -1,46 +1,316 @@
 use cfg_if::cfg_if;
+use chrono::{Days, Months, NaiveDate};
 use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+
+use crate::utils::money::Currency;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExpenseItem {
+    pub description: String,
+    pub amount_cents: i64,
+    pub assigned_to: i64,
+}
+
+// Itemized expenses are paid item by item, so they never have another split.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub enum SplitMethod {
+    #[default]
+    Equal,
+    Percentage(Vec<(i64, f64)>),
+    ExactCents(Vec<(i64, i64)>),
+    Itemized(Vec<ExpenseItem>),
+    // integer weights, e.g. 2:1:1 for a couple and two singles
+    Shares(Vec<(i64, u32)>),
+}
+
+impl SplitMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SplitMethod::Equal => "equal",
+            SplitMethod::Percentage(_) => "percentage",
+            SplitMethod::ExactCents(_) => "exact_cents",
+            SplitMethod::Itemized(_) => "itemized",
+            SplitMethod::Shares(_) => "shares",
+        }
+    }
+
+    pub fn kind(&self) -> SplitMethodKind {
+        match self {
+            SplitMethod::Equal => SplitMethodKind::Equal,
+            SplitMethod::Percentage(_) => SplitMethodKind::Percentage,
+            SplitMethod::ExactCents(_) => SplitMethodKind::ExactCents,
+            SplitMethod::Itemized(_) => SplitMethodKind::Itemized,
+            SplitMethod::Shares(_) => SplitMethodKind::Shares,
+        }
+    }
+}
+
+// A split method without its amounts, e.g. the default method of a room.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SplitMethodKind {
+    #[default]
+    Equal,
+    Percentage,
+    ExactCents,
+    Itemized,
+    Shares,
+}
+
+impl SplitMethodKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SplitMethodKind::Equal => "equal",
+            SplitMethodKind::Percentage => "percentage",
+            SplitMethodKind::ExactCents => "exact_cents",
+            SplitMethodKind::Itemized => "itemized",
+            SplitMethodKind::Shares => "shares",
+        }
+    }
+}
+
+impl FromStr for SplitMethodKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "equal" => Ok(SplitMethodKind::Equal),
+            "percentage" => Ok(SplitMethodKind::Percentage),
+            "exact_cents" => Ok(SplitMethodKind::ExactCents),
+            "itemized" => Ok(SplitMethodKind::Itemized),
+            "shares" => Ok(SplitMethodKind::Shares),
+            _ => Err(format!("Unknown split method: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Category {
+    Food,
+    Transport,
+    Rent,
+    Utilities,
+    Entertainment,
+    #[default]
+    Other,
+}
+
+impl fmt::Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Category::Food => "Food",
+            Category::Transport => "Transport",
+            Category::Rent => "Rent",
+            Category::Utilities => "Utilities",
+            Category::Entertainment => "Entertainment",
+            Category::Other => "Other",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Category {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Food" => Ok(Category::Food),
+            "Transport" => Ok(Category::Transport),
+            "Rent" => Ok(Category::Rent),
+            "Utilities" => Ok(Category::Utilities),
+            "Entertainment" => Ok(Category::Entertainment),
+            "Other" => Ok(Category::Other),
+            _ => Err(format!("Unknown category: {}", s)),
+        }
+    }
+}
+
+// Refunds are money coming back to the payer, e.g. a deposit returned, so
+// they are shared among the participants like an expense but with the opposite sign.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ExpenseKind {
+    #[default]
+    Expense,
+    Refund,
+}
+
+impl ExpenseKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExpenseKind::Expense => "expense",
+            ExpenseKind::Refund => "refund",
+        }
+    }
+
+    pub fn sign(&self) -> i64 {
+        match self {
+            ExpenseKind::Expense => 1,
+            ExpenseKind::Refund => -1,
+        }
+    }
+}
+
+impl FromStr for ExpenseKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "expense" => Ok(ExpenseKind::Expense),
+            "refund" => Ok(ExpenseKind::Refund),
+            _ => Err(format!("Unknown expense kind: {}", s)),
+        }
+    }
+}
+
+// The size of the periods the spending is grouped by, every bucket is
+// identified by its first day (weeks start on Monday).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Bucket {
+    Day,
+    Week,
+    Month,
+}
+
+impl Bucket {
+    pub fn next(&self, date: NaiveDate) -> Option<NaiveDate> {
+        match self {
+            Bucket::Day => date.checked_add_days(Days::new(1)),
+            Bucket::Week => date.checked_add_days(Days::new(7)),
+            Bucket::Month => date.checked_add_months(Months::new(1)),
+        }
+    }
+
+    // Adds the empty buckets between the ones with some spending, the totals must be sorted by date.
+    pub fn fill_gaps(&self, totals: Vec<(NaiveDate, i64)>) -> Vec<(NaiveDate, i64)> {
+        let mut filled: Vec<(NaiveDate, i64)> = Vec::with_capacity(totals.len());
+
+        for (date, total) in totals {
+            if let Some((last, _)) = filled.last().copied() {
+                let mut current = self.next(last);
+                while let Some(missing) = current.filter(|missing| *missing < date) {
+                    filled.push((missing, 0));
+                    current = self.next(missing);
+                }
+            }
+
+            filled.push((date, total));
+        }
+
+        return filled;
+    }
+}
+
+// What the room spent in every currency, as paid (not converted), to be
+// checked against the bank statements, and the total in the base currency.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CurrencyBreakdown {
+    pub by_currency: Vec<(Currency, i64)>,
+    pub base_currency: Currency,
+    pub base_total_cents: i64,
+}
 
 #[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExpenseDTO {
     pub id: Option<i64>,
     pub paid_by: Option<i64>,
-    pub amount: Option<f64>,
+    pub amount_cents: Option<i64>,
     pub participants: Option<String>,
     pub title: Option<String>,
     pub description: Option<String>,
     pub room_id: Option<String>,
+    pub split_method: Option<String>,
+    pub splits: Option<String>,
+    pub items: Option<String>,
+    pub currency: Option<String>,
+    pub exchange_rate: Option<f64>,
+    pub tax_cents: Option<i64>,
+    pub tip_cents: Option<i64>,
+    pub category: Option<String>,
+    pub kind: Option<String>,
+    pub occurred_on: Option<NaiveDate>,
 
     #[cfg(feature = "ssr")]
     pub created_at: Option<sqlx::types::chrono::NaiveDateTime>,
 }
 
-#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Expense {
     pub id: i64,
     pub paid_by: i64,
-    pub amount: f64,
+    pub amount_cents: i64,
     pub participants: Vec<i64>,
     pub title: String,
     pub description: Option<String>,
     pub room_id: String,
+    pub split_method: SplitMethod,
+    pub currency: Currency,
+    pub exchange_rate: f64,
+    // part of the amount, only for itemized expenses
+    pub tax_cents: i64,
+    pub tip_cents: i64,
+    pub category: Category,
+    pub kind: ExpenseKind,
+    // when the money was spent, `created_at` is when the expense was added
+    pub occurred_on: NaiveDate,
 
     #[cfg(feature = "ssr")]
     pub created_at: Option<sqlx::types::chrono::NaiveDateTime>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExpensePage {
+    pub expenses: Vec<Expense>,
+    pub total_count: i64,
+}
+
+// `next_cursor` is None on the last page.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExpenseCursorPage {
+    pub expenses: Vec<Expense>,
+    pub next_cursor: Option<i64>,
+}
+
+// A row of a batch of expenses, split equally in the room base currency.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NewExpense {
+    pub title: String,
+    pub description: Option<String>,
+    pub amount_cents: i64,
+    // None means the current user
+    pub paid_by: Option<i64>,
+    // empty means everybody in the room
+    pub participants: Vec<i64>,
+    pub category: Category,
+}
+
+impl Expense {
+    // The amount converted to the room base currency with the rate stored
+    // when the expense was created.
+    pub fn base_amount_cents(&self) -> i64 {
+        return (self.amount_cents as f64 * self.exchange_rate).round() as i64;
+    }
+}
+
 impl Default for Expense {
     fn default() -> Self {
         Self {
             id: -1,
             paid_by: -1,
-            amount: 0.0,
+            amount_cents: 0,
             participants: vec![],
             title: "".to_string(),
             description: None,
             room_id: "".to_string(),
+            split_method: SplitMethod::Equal,
+            currency: Currency::default(),
+            exchange_rate: 1.0,
+            tax_cents: 0,
+            tip_cents: 0,
+            category: Category::default(),
+            kind: ExpenseKind::default(),
+            occurred_on: NaiveDate::default(),
 
             #[cfg(feature = "ssr")]
             created_at: None,
@@ -50,21 +320,525 @@ impl Default for Expense {
 
 cfg_if! {
     if #[cfg(feature = "ssr")] {
+        use chrono::NaiveDateTime;
+        use sqlx::{sqlite::SqlitePool, QueryBuilder, Sqlite};
+        use crate::utils::time::to_sql_timestamp;
+
+        // The amount of an `expense` row with the sign of its kind, the refunds
+        // count as negative amounts in every total. Like `ExpenseKind::sign`.
+        pub const SIGNED_AMOUNT_SQL: &str = "(CASE WHEN kind = 'refund' THEN -1 ELSE 1 END * amount_cents)";
+
+        // The signed amount converted to the room base currency, like
+        // `kind.sign() * expense.base_amount_cents()`.
+        pub const SIGNED_BASE_AMOUNT_SQL: &str = "(CASE WHEN kind = 'refund' THEN -1 ELSE 1 END * ROUND(amount_cents * exchange_rate))";
+
+        // The days in [from, to), like `totals_paid_between`: `from` is included
+        // and `to` is not. The clauses are added only for the bounds that are
+        // present, the days are compared as the YYYY-MM-DD strings.
+        fn push_date_range(query: &mut QueryBuilder<Sqlite>, from: Option<NaiveDate>, to: Option<NaiveDate>) {
+            if let Some(from) = from {
+                query.push(" AND expense.occurred_on >= ").push_bind(from.to_string());
+            }
+
+            if let Some(to) = to {
+                query.push(" AND expense.occurred_on < ").push_bind(to.to_string());
+            }
+        }
+
+        impl Expense {
+            pub async fn get(id: i64, pool: &SqlitePool) -> Option<Self> {
+                log::info!("fn: get()");
+
+                let mut query = QueryBuilder::<Sqlite>::new(include_str!("../../queries/select_expenses.sql"));
+                query.push(" WHERE expense.id = ").push_bind(id);
+                query.push(" AND expense.deleted_at IS NULL");
+                query.push(" GROUP BY expense.id");
+
+                let expense = query
+                    .build_query_as::<ExpenseDTO>()
+                    .fetch_optional(pool)
+                    .await;
+
+                log::info!("fn: get() - expense: {:?}", expense);
+                return expense.ok().flatten().filter(|expense| expense.id.is_some()).map(Expense::from);
+            }
+
+            pub async fn list_for_room(room_id: &str, pool: &SqlitePool) -> Result<Vec<Self>, sqlx::Error> {
+                log::info!("fn: list_for_room()");
+
+                let mut query = QueryBuilder::<Sqlite>::new(include_str!("../../queries/select_expenses.sql"));
+                query.push(" WHERE expense.room_id = ").push_bind(room_id);
+                query.push(" AND expense.deleted_at IS NULL");
+                query.push(" GROUP BY expense.id");
+
+                let expenses_dto = query
+                    .build_query_as::<ExpenseDTO>()
+                    .fetch_all(pool)
+                    .await?;
+
+                let expenses = expenses_dto
+                    .into_iter()
+                    .filter(|expense| expense.id.is_some())
+                    .map(Expense::from)
+                    .collect();
+
+                return Ok(expenses);
+            }
+
+            pub async fn list_recent_for_user(user_id: i64, limit: i64, pool: &SqlitePool) -> Result<Vec<Self>, sqlx::Error> {
+                log::info!("fn: list_recent_for_user()");
+
+                let mut query = QueryBuilder::<Sqlite>::new(include_str!("../../queries/select_expenses.sql"));
+                query.push(" WHERE expense.room_id IN (SELECT room_id FROM user_room WHERE user_id = ").push_bind(user_id).push(")");
+                query.push(" AND expense.deleted_at IS NULL");
+                query.push(" GROUP BY expense.id ORDER BY expense.occurred_on DESC, expense.created_at DESC, expense.id DESC");
+                query.push(" LIMIT ").push_bind(limit);
+
+                let expenses_dto = query
+                    .build_query_as::<ExpenseDTO>()
+                    .fetch_all(pool)
+                    .await?;
+
+                let expenses = expenses_dto
+                    .into_iter()
+                    .filter(|expense| expense.id.is_some())
+                    .map(Expense::from)
+                    .collect();
+
+                return Ok(expenses);
+            }
+
+            // Newest first, in the [from, to) range of days when the bounds are present.
+            pub async fn list_paginated(room_id: &str, from: Option<NaiveDate>, to: Option<NaiveDate>, limit: i64, offset: i64, pool: &SqlitePool) -> Result<Vec<Self>, sqlx::Error> {
+                log::info!("fn: list_paginated()");
+
+                let mut query = QueryBuilder::<Sqlite>::new(include_str!("../../queries/select_expenses.sql"));
+                query.push(" WHERE expense.room_id = ").push_bind(room_id);
+                query.push(" AND expense.deleted_at IS NULL");
+                push_date_range(&mut query, from, to);
+                query.push(" GROUP BY expense.id ORDER BY expense.occurred_on DESC, expense.created_at DESC, expense.id DESC");
+                query.push(" LIMIT ").push_bind(limit);
+                query.push(" OFFSET ").push_bind(offset);
+
+                let expenses_dto = query
+                    .build_query_as::<ExpenseDTO>()
+                    .fetch_all(pool)
+                    .await?;
+
+                let expenses = expenses_dto
+                    .into_iter()
+                    .filter(|expense| expense.id.is_some())
+                    .map(Expense::from)
+                    .collect();
+
+                return Ok(expenses);
+            }
+
+            // Newest first like `list_paginated`, but starting right after the
+            // expense `after_id` instead of skipping rows, so that every page
+            // costs the same. Returns the cursor of the next page, if any.
+            pub async fn list_after(room_id: &str, after_id: Option<i64>, limit: i64, pool: &SqlitePool) -> Result<(Vec<Self>, Option<i64>), sqlx::Error> {
+                log::info!("fn: list_after()");
+
+                let mut query = QueryBuilder::<Sqlite>::new(include_str!("../../queries/select_expenses.sql"));
+                query.push(" WHERE expense.room_id = ").push_bind(room_id);
+                query.push(" AND expense.deleted_at IS NULL");
+                if let Some(after_id) = after_id {
+                    query.push(" AND (expense.occurred_on, expense.created_at, expense.id) < (SELECT occurred_on, created_at, id FROM expense WHERE id = ").push_bind(after_id).push(")");
+                }
+                query.push(" GROUP BY expense.id ORDER BY expense.occurred_on DESC, expense.created_at DESC, expense.id DESC");
+                // one more row tells whether there is a next page
+                query.push(" LIMIT ").push_bind(limit + 1);
+
+                let expenses_dto = query
+                    .build_query_as::<ExpenseDTO>()
+                    .fetch_all(pool)
+                    .await?;
+
+                let mut expenses: Vec<Self> = expenses_dto
+                    .into_iter()
+                    .filter(|expense| expense.id.is_some())
+                    .map(Expense::from)
+                    .collect();
+
+                let next_cursor = if expenses.len() as i64 > limit {
+                    expenses.truncate(limit as usize);
+                    expenses.last().map(|expense| expense.id)
+                } else {
+                    None
+                };
+
+                return Ok((expenses, next_cursor));
+            }
+
+            // The expenses added in the last minutes by the same payer, with the
+            // same amount and title (ignoring the case), likely added twice.
+            pub async fn find_possible_duplicates(room_id: &str, paid_by: i64, new: &NewExpense, pool: &SqlitePool) -> Result<Vec<Self>, sqlx::Error> {
+                log::info!("fn: find_possible_duplicates()");
+
+                // for SQLite datetime()
+                const DUPLICATE_WINDOW: &str = "-10 minutes";
+
+                let mut query = QueryBuilder::<Sqlite>::new(include_str!("../../queries/select_expenses.sql"));
+                query.push(" WHERE expense.room_id = ").push_bind(room_id);
+                query.push(" AND expense.deleted_at IS NULL");
+                query.push(" AND expense.paid_by = ").push_bind(paid_by);
+                query.push(" AND expense.amount_cents = ").push_bind(new.amount_cents);
+                query.push(" AND lower(trim(expense.title)) = lower(").push_bind(new.title.trim()).push(")");
+                query.push(" AND expense.created_at >= datetime('now', ").push_bind(DUPLICATE_WINDOW).push(")");
+                query.push(" GROUP BY expense.id ORDER BY expense.created_at DESC, expense.id DESC");
+
+                let expenses_dto = query
+                    .build_query_as::<ExpenseDTO>()
+                    .fetch_all(pool)
+                    .await?;
+
+                let expenses = expenses_dto
+                    .into_iter()
+                    .filter(|expense| expense.id.is_some())
+                    .map(Expense::from)
+                    .collect();
+
+                return Ok(expenses);
+            }
+
+            pub async fn list_by_category(room_id: &str, category: Category, pool: &SqlitePool) -> Result<Vec<Self>, sqlx::Error> {
+                log::info!("fn: list_by_category()");
+
+                let mut query = QueryBuilder::<Sqlite>::new(include_str!("../../queries/select_expenses.sql"));
+                query.push(" WHERE expense.room_id = ").push_bind(room_id);
+                query.push(" AND expense.deleted_at IS NULL");
+                query.push(" AND expense.category = ").push_bind(category.to_string());
+                query.push(" GROUP BY expense.id ORDER BY expense.occurred_on DESC, expense.created_at DESC, expense.id DESC");
+
+                let expenses_dto = query
+                    .build_query_as::<ExpenseDTO>()
+                    .fetch_all(pool)
+                    .await?;
+
+                let expenses = expenses_dto
+                    .into_iter()
+                    .filter(|expense| expense.id.is_some())
+                    .map(Expense::from)
+                    .collect();
+
+                return Ok(expenses);
+            }
+
+            // `tag` must be already normalized.
+            pub async fn list_by_tag(room_id: &str, tag: &str, pool: &SqlitePool) -> Result<Vec<Self>, sqlx::Error> {
+                log::info!("fn: list_by_tag()");
+
+                let mut query = QueryBuilder::<Sqlite>::new(include_str!("../../queries/select_expenses.sql"));
+                query.push(" JOIN expense_tag ON expense_tag.expense_id = expense.id");
+                query.push(" JOIN tag ON tag.id = expense_tag.tag_id");
+                query.push(" WHERE expense.room_id = ").push_bind(room_id);
+                query.push(" AND expense.deleted_at IS NULL");
+                query.push(" AND tag.name = ").push_bind(tag);
+                query.push(" GROUP BY expense.id ORDER BY expense.occurred_on DESC, expense.created_at DESC, expense.id DESC");
+
+                let expenses_dto = query
+                    .build_query_as::<ExpenseDTO>()
+                    .fetch_all(pool)
+                    .await?;
+
+                let expenses = expenses_dto
+                    .into_iter()
+                    .filter(|expense| expense.id.is_some())
+                    .map(Expense::from)
+                    .collect();
+
+                return Ok(expenses);
+            }
+
+            // LIKE is case-insensitive in SQLite, `%` and `_` in the query are matched literally.
+            pub async fn search(room_id: &str, query: &str, limit: i64, pool: &SqlitePool) -> Result<Vec<Self>, sqlx::Error> {
+                log::info!("fn: search()");
+
+                let escaped = query.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+                let pattern = format!("%{}%", escaped);
+
+                let mut query = QueryBuilder::<Sqlite>::new(include_str!("../../queries/select_expenses.sql"));
+                query.push(" WHERE expense.room_id = ").push_bind(room_id);
+                query.push(" AND expense.deleted_at IS NULL");
+                query.push(" AND (expense.title LIKE ").push_bind(pattern.clone()).push(" ESCAPE '\\'");
+                query.push(" OR expense.description LIKE ").push_bind(pattern).push(" ESCAPE '\\')");
+                query.push(" GROUP BY expense.id ORDER BY expense.occurred_on DESC, expense.created_at DESC, expense.id DESC");
+                query.push(" LIMIT ").push_bind(limit);
+
+                let expenses_dto = query
+                    .build_query_as::<ExpenseDTO>()
+                    .fetch_all(pool)
+                    .await?;
+
+                let expenses = expenses_dto
+                    .into_iter()
+                    .filter(|expense| expense.id.is_some())
+                    .map(Expense::from)
+                    .collect();
+
+                return Ok(expenses);
+            }
+
+            // Total spent in every bucket with some expenses, in the room base currency.
+            pub async fn spend_by_bucket(room_id: &str, bucket: Bucket, pool: &SqlitePool) -> Result<Vec<(NaiveDate, i64)>, sqlx::Error> {
+                log::info!("fn: spend_by_bucket()");
+
+                let bucket_start = match bucket {
+                    Bucket::Day => "date(occurred_on)",
+                    Bucket::Week => "date(occurred_on, 'weekday 0', '-6 days')",
+                    Bucket::Month => "date(occurred_on, 'start of month')",
+                };
+
+                let totals = sqlx::query_as::<_, (String, i64)>(&format!(
+                    "SELECT strftime('%Y-%m-%d', {}) AS bucket, CAST(SUM({}) AS INTEGER) AS total FROM expense WHERE room_id = ? AND deleted_at IS NULL GROUP BY bucket ORDER BY bucket",
+                    bucket_start, SIGNED_BASE_AMOUNT_SQL
+                ))
+                .bind(room_id)
+                .fetch_all(pool)
+                .await?;
+
+                let totals = totals
+                    .into_iter()
+                    .filter_map(|(date, total)| Some((NaiveDate::parse_from_str(&date, "%Y-%m-%d").ok()?, total)))
+                    .collect();
+
+                return Ok(totals);
+            }
+
+            // Totals by currency, both as paid and converted to the room base currency.
+            pub async fn totals_by_currency(room_id: &str, pool: &SqlitePool) -> Result<Vec<(Currency, i64, i64)>, sqlx::Error> {
+                log::info!("fn: totals_by_currency()");
+
+                let totals = sqlx::query_as::<_, (String, i64, i64)>(&format!(
+                    "SELECT currency, CAST(SUM({}) AS INTEGER) AS total, CAST(SUM({}) AS INTEGER) AS base_total FROM expense WHERE room_id = ? AND deleted_at IS NULL GROUP BY currency ORDER BY currency",
+                    SIGNED_AMOUNT_SQL, SIGNED_BASE_AMOUNT_SQL
+                ))
+                .bind(room_id)
+                .fetch_all(pool)
+                .await?;
+
+                let totals = totals
+                    .into_iter()
+                    .filter_map(|(currency, total, base_total)| Some((currency.parse().ok()?, total, base_total)))
+                    .collect();
+
+                return Ok(totals);
+            }
+
+            // Total paid by every user in the [from, to) range of days, in the room base currency.
+            pub async fn totals_paid_between(room_id: &str, from: NaiveDate, to: NaiveDate, pool: &SqlitePool) -> Result<Vec<(i64, i64)>, sqlx::Error> {
+                log::info!("fn: totals_paid_between()");
+
+                let totals = sqlx::query_as::<_, (i64, i64)>(&format!(
+                    "SELECT paid_by, CAST(SUM({}) AS INTEGER) AS total FROM expense WHERE room_id = ? AND deleted_at IS NULL AND occurred_on >= ? AND occurred_on < ? GROUP BY paid_by ORDER BY total DESC",
+                    SIGNED_BASE_AMOUNT_SQL
+                ))
+                .bind(room_id)
+                .bind(from.to_string())
+                .bind(to.to_string())
+                .fetch_all(pool)
+                .await?;
+
+                return Ok(totals);
+            }
+
+            // With the same range as `list_paginated`.
+            pub async fn count_for_room(room_id: &str, from: Option<NaiveDate>, to: Option<NaiveDate>, pool: &SqlitePool) -> Result<i64, sqlx::Error> {
+                log::info!("fn: count_for_room()");
+
+                let mut query = QueryBuilder::<Sqlite>::new("SELECT COUNT(*) FROM expense WHERE expense.room_id = ");
+                query.push_bind(room_id);
+                query.push(" AND expense.deleted_at IS NULL");
+                push_date_range(&mut query, from, to);
+
+                let count = query
+                    .build_query_scalar()
+                    .fetch_one(pool)
+                    .await?;
+
+                return Ok(count);
+            }
+
+            // Permanently removes the expenses soft-deleted before `cutoff`, their
+            // splits, items, attachments, ... are deleted with them by the database.
+            pub async fn purge_deleted_before(cutoff: NaiveDateTime, pool: &SqlitePool) -> Result<u64, sqlx::Error> {
+                log::info!("fn: purge_deleted_before()");
+
+                let purged = sqlx::query("DELETE FROM expense WHERE deleted_at < ?")
+                    .bind(to_sql_timestamp(cutoff))
+                    .execute(pool)
+                    .await?
+                    .rows_affected();
+
+                log::info!("fn: purge_deleted_before() - purged {} expenses", purged);
+                return Ok(purged);
+            }
+        }
+
         impl From<ExpenseDTO> for Expense {
             fn from(expense: ExpenseDTO) -> Self {
                 println!("expense: {:?}", expense);
 
+                // every split row is [user_id, percentage, amount_cents, shares]
+                let splits: Vec<(i64, Option<f64>, Option<i64>, Option<u32>)> = expense
+                    .splits
+                    .and_then(|splits| serde_json::from_str(&splits).ok())
+                    .unwrap_or_default();
+
+                let split_method = match expense.split_method.as_deref() {
+                    Some("percentage") => SplitMethod::Percentage(
+                        splits.iter().map(|(user_id, percentage, _, _)| (*user_id, percentage.unwrap_or_default())).collect(),
+                    ),
+                    Some("exact_cents") => SplitMethod::ExactCents(
+                        splits.iter().map(|(user_id, _, amount_cents, _)| (*user_id, amount_cents.unwrap_or_default())).collect(),
+                    ),
+                    Some("shares") => SplitMethod::Shares(
+                        splits.iter().map(|(user_id, _, _, shares)| (*user_id, shares.unwrap_or_default())).collect(),
+                    ),
+                    Some("itemized") => {
+                        // every item row is [description, amount_cents, assigned_to]
+                        let items: Vec<(String, i64, i64)> = expense
+                            .items
+                            .and_then(|items| serde_json::from_str(&items).ok())
+                            .unwrap_or_default();
+
+                        SplitMethod::Itemized(
+                            items
+                                .into_iter()
+                                .map(|(description, amount_cents, assigned_to)| ExpenseItem { description, amount_cents, assigned_to })
+                                .collect(),
+                        )
+                    }
+                    _ => SplitMethod::Equal,
+                };
+
+                // equal splits store who shares the cost in expense_split, the
+                // older ones only have the rows in user_expense
+                let mut participants: Vec<i64> = expense.participants.unwrap().replace(&['[', ']'], "").split(",").filter_map(|s| s.parse::<i64>().ok()).collect();
+                if split_method == SplitMethod::Equal && !splits.is_empty() {
+                    participants = splits.iter().map(|(user_id, _, _, _)| *user_id).collect();
+                }
+
                 Self {
                     id: expense.id.unwrap(),
                     paid_by: expense.paid_by.unwrap(),
-                    amount: expense.amount.unwrap(),
-                    participants: expense.participants.unwrap().replace(&['[', ']'], "").split(",").map(|s| s.parse::<i64>().unwrap()).collect(),
+                    amount_cents: expense.amount_cents.unwrap(),
+                    participants,
                     title: expense.title.unwrap(),
                     description: expense.description,
                     room_id: expense.room_id.unwrap(),
+                    split_method,
+                    currency: expense.currency.and_then(|currency| currency.parse().ok()).unwrap_or_default(),
+                    exchange_rate: expense.exchange_rate.unwrap_or(1.0),
+                    tax_cents: expense.tax_cents.unwrap_or_default(),
+                    tip_cents: expense.tip_cents.unwrap_or_default(),
+                    category: expense.category.and_then(|category| category.parse().ok()).unwrap_or_default(),
+                    kind: expense.kind.and_then(|kind| kind.parse().ok()).unwrap_or_default(),
+                    occurred_on: expense.occurred_on.or(expense.created_at.map(|created_at| created_at.date())).unwrap_or_default(),
                     created_at: expense.created_at,
                 }
             }
         }
     }
 }
+
+#[cfg(all(test, feature = "ssr"))]
+mod tests {
+    use super::*;
+    use crate::services::expenses::{insert_expense, ValidatedExpense};
+    use crate::test_utils::{create_room, create_user, test_pool};
+
+    // paid by `paid_by` alone, on 2024-01-10
+    fn new_expense(room_id: &str, paid_by: i64, title: &str) -> ValidatedExpense {
+        ValidatedExpense {
+            room_id: room_id.to_string(),
+            paid_by,
+            title: title.to_string(),
+            description: None,
+            amount_cents: 10_00,
+            participants: vec![paid_by],
+            split_method: SplitMethod::Equal,
+            currency: Currency::USD,
+            exchange_rate: 1.0,
+            tax_cents: 0,
+            tip_cents: 0,
+            category: Category::Other,
+            kind: ExpenseKind::Expense,
+            occurred_on: NaiveDate::from_ymd_opt(2024, 1, 10).unwrap(),
+        }
+    }
+
+    #[tokio::test]
+    async fn refunds_count_as_negative_in_the_totals() {
+        let pool = test_pool().await;
+        let alice = create_user("alice", &pool).await;
+        let room = create_room("Trip", &alice, &pool).await;
+
+        for (amount_cents, kind) in [(100_00, ExpenseKind::Expense), (30_00, ExpenseKind::Refund)] {
+            let expense = ValidatedExpense {
+                amount_cents,
+                kind,
+                ..new_expense(&room.id, alice.id, "Hotel")
+            };
+            insert_expense(&expense, None, &pool).await.unwrap();
+        }
+
+        let from = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let to = NaiveDate::from_ymd_opt(2024, 2, 1).unwrap();
+        assert_eq!(Expense::totals_paid_between(&room.id, from, to, &pool).await.unwrap(), vec![(alice.id, 70_00)]);
+        assert_eq!(Expense::totals_by_currency(&room.id, &pool).await.unwrap(), vec![(Currency::USD, 70_00, 70_00)]);
+    }
+
+    #[tokio::test]
+    async fn the_date_range_excludes_its_end() {
+        let pool = test_pool().await;
+        let alice = create_user("alice", &pool).await;
+        let room = create_room("Trip", &alice, &pool).await;
+        let day = |d: u32| NaiveDate::from_ymd_opt(2024, 1, d).unwrap();
+
+        for d in [1, 15, 31] {
+            let expense = ValidatedExpense {
+                occurred_on: day(d),
+                ..new_expense(&room.id, alice.id, "Lunch")
+            };
+            insert_expense(&expense, None, &pool).await.unwrap();
+        }
+
+        let in_range: Vec<NaiveDate> = Expense::list_paginated(&room.id, Some(day(1)), Some(day(31)), 10, 0, &pool)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|expense| expense.occurred_on)
+            .collect();
+
+        assert_eq!(in_range, vec![day(15), day(1)]);
+        assert_eq!(Expense::count_for_room(&room.id, Some(day(1)), Some(day(31)), &pool).await.unwrap(), 2);
+        assert_eq!(Expense::count_for_room(&room.id, Some(day(1)), None, &pool).await.unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn searches_the_titles_and_the_descriptions() {
+        let pool = test_pool().await;
+        let alice = create_user("alice", &pool).await;
+        let room = create_room("Trip", &alice, &pool).await;
+
+        let pizza = insert_expense(&new_expense(&room.id, alice.id, "Pizza night"), None, &pool).await.unwrap();
+        let museum = ValidatedExpense {
+            description: Some("Tickets for the pizza museum".to_string()),
+            ..new_expense(&room.id, alice.id, "Museum")
+        };
+        let museum = insert_expense(&museum, None, &pool).await.unwrap();
+        insert_expense(&new_expense(&room.id, alice.id, "Taxi"), None, &pool).await.unwrap();
+
+        let mut found: Vec<i64> = Expense::search(&room.id, "pizza", 10, &pool)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|expense| expense.id)
+            .collect();
+        found.sort();
+
+        assert_eq!(found, vec![pizza, museum]);
+    }
+}
@@ -1,3 +1,10 @@
 pub mod room;
 pub mod user;
 pub mod expense;
+pub mod recurring_expense;
+pub mod settlement;
+pub mod attachment;
+pub mod comment;
+pub mod activity;
+pub mod reminder;
+pub mod tag;
@@ -0,0 +1,128 @@
+use cfg_if::cfg_if;
+use chrono::{Days, Months, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Interval {
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+impl Interval {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Interval::Weekly => "weekly",
+            Interval::Monthly => "monthly",
+            Interval::Yearly => "yearly",
+        }
+    }
+
+    // The date of the occurrence `n` (0 is `first_due`). Computed from the first
+    // date, so the months keep its day when they have it
+    // (e.g. Jan 31 -> Feb 28 -> Mar 31).
+    pub fn occurrence(&self, first_due: NaiveDate, n: u32) -> NaiveDate {
+        let date = match self {
+            Interval::Weekly => first_due.checked_add_days(Days::new(7 * n as u64)),
+            Interval::Monthly => first_due.checked_add_months(Months::new(n)),
+            Interval::Yearly => n.checked_mul(12).and_then(|months| first_due.checked_add_months(Months::new(months))),
+        };
+
+        return date.unwrap_or(NaiveDate::MAX);
+    }
+}
+
+impl FromStr for Interval {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "weekly" => Ok(Interval::Weekly),
+            "monthly" => Ok(Interval::Monthly),
+            "yearly" => Ok(Interval::Yearly),
+            _ => Err(format!("Unknown interval: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecurringExpense {
+    pub id: i64,
+    pub room_id: String,
+    pub title: String,
+    pub amount_cents: i64,
+    pub paid_by: i64,
+    pub interval: Interval,
+    pub first_due: NaiveDate,
+    // the expenses already posted
+    pub occurrences: u32,
+    pub next_due: NaiveDate,
+}
+
+cfg_if! {
+    if #[cfg(feature = "ssr")] {
+        use sqlx::sqlite::SqlitePool;
+
+        type RecurringExpenseRow = (i64, String, String, i64, i64, String, NaiveDate, u32, NaiveDate);
+
+        impl From<RecurringExpenseRow> for RecurringExpense {
+            fn from(row: RecurringExpenseRow) -> Self {
+                let (id, room_id, title, amount_cents, paid_by, interval, first_due, occurrences, next_due) = row;
+
+                Self {
+                    id,
+                    room_id,
+                    title,
+                    amount_cents,
+                    paid_by,
+                    interval: interval.parse().unwrap_or(Interval::Monthly),
+                    first_due,
+                    occurrences,
+                    next_due,
+                }
+            }
+        }
+
+        impl RecurringExpense {
+            pub async fn list_due(today: NaiveDate, pool: &SqlitePool) -> Result<Vec<Self>, sqlx::Error> {
+                log::info!("fn: list_due()");
+
+                let rows = sqlx::query_as::<_, RecurringExpenseRow>(
+                    "SELECT id, room_id, title, amount_cents, paid_by, interval, first_due, occurrences, next_due FROM recurring_expense WHERE next_due <= ?",
+                )
+                .bind(today)
+                .fetch_all(pool)
+                .await?;
+
+                return Ok(rows.into_iter().map(RecurringExpense::from).collect());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn monthly_occurrences_keep_the_day_of_the_first_one() {
+        let first_due = date(2024, 1, 31);
+
+        assert_eq!(Interval::Monthly.occurrence(first_due, 0), first_due);
+        assert_eq!(Interval::Monthly.occurrence(first_due, 1), date(2024, 2, 29));
+        assert_eq!(Interval::Monthly.occurrence(first_due, 2), date(2024, 3, 31));
+        assert_eq!(Interval::Monthly.occurrence(first_due, 3), date(2024, 4, 30));
+    }
+
+    #[test]
+    fn weekly_and_yearly_occurrences() {
+        assert_eq!(Interval::Weekly.occurrence(date(2024, 1, 1), 2), date(2024, 1, 15));
+        assert_eq!(Interval::Yearly.occurrence(date(2024, 2, 29), 1), date(2025, 2, 28));
+        assert_eq!(Interval::Yearly.occurrence(date(2024, 2, 29), 4), date(2028, 2, 29));
+    }
+}
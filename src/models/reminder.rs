@@ -0,0 +1,61 @@
+use cfg_if::cfg_if;
+use serde::{Deserialize, Serialize};
+
+// A debt in a room that has not been settled for a while.
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Reminder {
+    pub id: i64,
+    pub room_id: String,
+    pub room_name: String,
+    pub amount_cents: i64,
+    pub base_currency: String,
+
+    #[cfg(feature = "ssr")]
+    pub created_at: Option<sqlx::types::chrono::NaiveDateTime>,
+}
+
+cfg_if! {
+    if #[cfg(feature = "ssr")] {
+        use sqlx::sqlite::SqlitePool;
+
+        impl Reminder {
+            // Creates the reminder of the debt, or updates its amount if it already exists.
+            pub async fn upsert(room_id: &str, user_id: i64, amount_cents: i64, pool: &SqlitePool) -> Result<(), sqlx::Error> {
+                sqlx::query(
+                    "INSERT INTO reminder (room_id, user_id, amount_cents) VALUES (?, ?, ?) ON CONFLICT (room_id, user_id) DO UPDATE SET amount_cents = excluded.amount_cents",
+                )
+                .bind(room_id)
+                .bind(user_id)
+                .bind(amount_cents)
+                .execute(pool)
+                .await?;
+
+                return Ok(());
+            }
+
+            pub async fn delete(room_id: &str, user_id: i64, pool: &SqlitePool) -> Result<(), sqlx::Error> {
+                sqlx::query("DELETE FROM reminder WHERE room_id = ? AND user_id = ?")
+                    .bind(room_id)
+                    .bind(user_id)
+                    .execute(pool)
+                    .await?;
+
+                return Ok(());
+            }
+
+            pub async fn list_for_user(user_id: i64, pool: &SqlitePool) -> Result<Vec<Self>, sqlx::Error> {
+                log::info!("fn: list_for_user()");
+
+                let reminders = sqlx::query_as::<_, Reminder>(
+                    "SELECT reminder.id, reminder.room_id, room.room_name, reminder.amount_cents, room.base_currency, reminder.created_at FROM reminder JOIN room ON room.id = reminder.room_id WHERE reminder.user_id = ? ORDER BY reminder.created_at",
+                )
+                .bind(user_id)
+                .fetch_all(pool)
+                .await?;
+
+                return Ok(reminders);
+            }
+        }
+    }
+}
@@ -1,4 +1,37 @@
+use cfg_if::cfg_if;
 use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+// Ordered by privileges, so that `role >= Role::Admin` can be used in checks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Role {
+    Member,
+    Admin,
+    Owner,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Member => "member",
+            Role::Admin => "admin",
+            Role::Owner => "owner",
+        }
+    }
+}
+
+impl FromStr for Role {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "member" => Ok(Role::Member),
+            "admin" => Ok(Role::Admin),
+            "owner" => Ok(Role::Owner),
+            _ => Err(format!("Unknown role: {}", s)),
+        }
+    }
+}
 
 #[cfg_attr(feature="ssr", derive(sqlx::FromRow))]
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -7,7 +40,428 @@ pub struct Room {
     pub room_name: String,
     pub max_participants: i64,
     pub owner: i64,
+    pub base_currency: String,
+    pub default_split_method: String,
+    pub monthly_budget_cents: Option<i64>,
+    pub slug: String,
+    // the users that accept an invite wait for an admin to approve them
+    pub approval_required: bool,
 
     #[cfg(feature = "ssr")]
     pub created_at: Option<sqlx::types::chrono::NaiveDateTime>,
 }
+
+// A user waiting to be approved as a member of a room.
+#[cfg_attr(feature="ssr", derive(sqlx::FromRow))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingMember {
+    pub user_id: i64,
+    pub username: String,
+    pub display_name: Option<String>,
+    pub requested_at: Option<chrono::NaiveDateTime>,
+}
+
+impl Room {
+    // Lowercase and hyphenated, e.g. "Trip to Rome!" becomes "trip-to-rome".
+    pub fn slugify(room_name: &str) -> String {
+        let slug = room_name
+            .to_lowercase()
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .collect::<Vec<_>>()
+            .join("-");
+
+        if slug.is_empty() {
+            return "room".to_string();
+        }
+
+        return slug;
+    }
+}
+
+cfg_if! {
+    if #[cfg(feature = "ssr")] {
+        use sqlx::sqlite::{SqliteConnection, SqlitePool};
+
+        const DEFAULT_MAX_PARTICIPANTS: i64 = 20;
+
+        // The slug of the name, suffixed with "-2", "-3", ... when it is already
+        // taken by another room or kept as a redirect of another room. The
+        // slugs of `own_room_id`, current or old, are free for it.
+        async fn unique_slug(room_name: &str, own_room_id: Option<&str>, conn: &mut SqliteConnection) -> Result<String, sqlx::Error> {
+            let base = Room::slugify(room_name);
+            let mut slug = base.clone();
+            let mut suffix = 1;
+
+            loop {
+                let taken = sqlx::query(
+                    "SELECT 1 FROM room WHERE slug = ? AND id IS NOT ? UNION SELECT 1 FROM room_slug_history WHERE slug = ? AND room_id IS NOT ?",
+                )
+                .bind(&slug)
+                .bind(own_room_id)
+                .bind(&slug)
+                .bind(own_room_id)
+                .fetch_optional(&mut *conn)
+                .await?;
+
+                if taken.is_none() {
+                    return Ok(slug);
+                }
+
+                suffix += 1;
+                slug = format!("{}-{}", base, suffix);
+            }
+        }
+
+        impl Room {
+            pub async fn create(room_name: String, owner_id: i64, base_currency: &str, pool: &SqlitePool) -> Result<Self, sqlx::Error> {
+                log::info!("fn: create()");
+
+                let random_uuid = uuid::Uuid::new_v4().to_string();
+                log::info!("fn: create() - generated a random uuid: {}", random_uuid);
+
+                let mut tx = pool.begin().await?;
+
+                let slug = unique_slug(&room_name, None, &mut *tx).await?;
+
+                let room = sqlx::query_as::<_, Room>(
+                    "INSERT INTO room (id, room_name, max_participants, owner, base_currency, slug) VALUES (?, ?, ?, ?, ?, ?) RETURNING *",
+                )
+                .bind(&random_uuid)
+                .bind(&room_name)
+                .bind(DEFAULT_MAX_PARTICIPANTS)
+                .bind(owner_id)
+                .bind(base_currency)
+                .bind(&slug)
+                .fetch_one(&mut *tx)
+                .await?;
+
+                sqlx::query("INSERT INTO user_room (room_id, user_id, role) VALUES (?, ?, ?)")
+                    .bind(&room.id)
+                    .bind(owner_id)
+                    .bind(Role::Owner.as_str())
+                    .execute(&mut *tx)
+                    .await?;
+
+                tx.commit().await?;
+
+                log::info!("fn: create() - room: {:?}", room);
+                return Ok(room);
+            }
+
+            pub async fn get(room_id: &str, pool: &SqlitePool) -> Result<Option<Self>, sqlx::Error> {
+                let room = sqlx::query_as::<_, Room>("SELECT * FROM room WHERE id = ?")
+                    .bind(room_id)
+                    .fetch_optional(pool)
+                    .await?;
+
+                return Ok(room);
+            }
+
+            // Also finds the rooms by the slugs they had before being renamed,
+            // the caller can compare `slug` with `room.slug` to redirect.
+            pub async fn get_by_slug(slug: &str, pool: &SqlitePool) -> Result<Option<Self>, sqlx::Error> {
+                log::info!("fn: get_by_slug()");
+
+                let room = sqlx::query_as::<_, Room>(
+                    "SELECT * FROM room WHERE slug = ? OR id = (SELECT room_id FROM room_slug_history WHERE slug = ?)",
+                )
+                .bind(slug)
+                .bind(slug)
+                .fetch_optional(pool)
+                .await?;
+
+                return Ok(room);
+            }
+
+            // The slug follows the new name, the old one is kept as a redirect.
+            pub async fn rename(room_id: &str, room_name: &str, pool: &SqlitePool) -> Result<Self, sqlx::Error> {
+                log::info!("fn: rename() - renaming room {} to {:?}", room_id, room_name);
+
+                let mut tx = pool.begin().await?;
+
+                let (old_name, old_slug): (String, String) = sqlx::query_as("SELECT room_name, slug FROM room WHERE id = ?")
+                    .bind(room_id)
+                    .fetch_one(&mut *tx)
+                    .await?;
+
+                // e.g. "Trip" to "trip", the slug (maybe "trip-2") stays the same
+                if Room::slugify(&old_name) == Room::slugify(room_name) {
+                    let room = sqlx::query_as::<_, Room>("UPDATE room SET room_name = ? WHERE id = ? RETURNING *")
+                        .bind(room_name)
+                        .bind(room_id)
+                        .fetch_one(&mut *tx)
+                        .await?;
+
+                    tx.commit().await?;
+                    return Ok(room);
+                }
+
+                let slug = unique_slug(room_name, Some(room_id), &mut *tx).await?;
+
+                if slug != old_slug {
+                    sqlx::query("INSERT OR IGNORE INTO room_slug_history (slug, room_id) VALUES (?, ?)")
+                        .bind(&old_slug)
+                        .bind(room_id)
+                        .execute(&mut *tx)
+                        .await?;
+
+                    // the old slug may come back, it can't be both a redirect and a room
+                    sqlx::query("DELETE FROM room_slug_history WHERE room_id = ? AND slug = ?")
+                        .bind(room_id)
+                        .bind(&slug)
+                        .execute(&mut *tx)
+                        .await?;
+                }
+
+                let room = sqlx::query_as::<_, Room>("UPDATE room SET room_name = ?, slug = ? WHERE id = ? RETURNING *")
+                    .bind(room_name)
+                    .bind(&slug)
+                    .bind(room_id)
+                    .fetch_one(&mut *tx)
+                    .await?;
+
+                tx.commit().await?;
+
+                log::info!("fn: rename() - room {} is now at {:?}", room_id, room.slug);
+                return Ok(room);
+            }
+
+            pub async fn list_for_user(user_id: i64, pool: &SqlitePool) -> Result<Vec<Self>, sqlx::Error> {
+                log::info!("fn: list_for_user()");
+
+                let rooms = sqlx::query_as::<_, Room>(
+                    "SELECT room.* FROM room JOIN user_room ON user_room.room_id = room.id WHERE user_room.user_id = ? ORDER BY room.room_name",
+                )
+                .bind(user_id)
+                .fetch_all(pool)
+                .await?;
+
+                log::info!("fn: list_for_user() - rooms: {:?}", rooms);
+                return Ok(rooms);
+            }
+
+            pub async fn member_ids(room_id: &str, pool: &SqlitePool) -> Result<Vec<i64>, sqlx::Error> {
+                let ids = sqlx::query_scalar("SELECT user_id FROM user_room WHERE room_id = ? ORDER BY user_id")
+                    .bind(room_id)
+                    .fetch_all(pool)
+                    .await?;
+
+                return Ok(ids);
+            }
+
+            pub async fn is_member(room_id: &str, user_id: i64, pool: &SqlitePool) -> Result<bool, sqlx::Error> {
+                let row = sqlx::query("SELECT 1 FROM user_room WHERE room_id = ? AND user_id = ?")
+                    .bind(room_id)
+                    .bind(user_id)
+                    .fetch_optional(pool)
+                    .await?;
+
+                return Ok(row.is_some());
+            }
+
+            pub async fn user_role(room_id: &str, user_id: i64, pool: &SqlitePool) -> Result<Option<Role>, sqlx::Error> {
+                let role: Option<String> = sqlx::query_scalar("SELECT role FROM user_room WHERE room_id = ? AND user_id = ?")
+                    .bind(room_id)
+                    .bind(user_id)
+                    .fetch_optional(pool)
+                    .await?;
+
+                return Ok(role.and_then(|role| role.parse().ok()));
+            }
+
+            pub async fn count_owners(room_id: &str, pool: &SqlitePool) -> Result<i64, sqlx::Error> {
+                let count = sqlx::query_scalar("SELECT COUNT(*) FROM user_room WHERE room_id = ? AND role = ?")
+                    .bind(room_id)
+                    .bind(Role::Owner.as_str())
+                    .fetch_one(pool)
+                    .await?;
+
+                return Ok(count);
+            }
+
+            pub async fn add_member(room_id: &str, user_id: i64, pool: &SqlitePool) -> Result<(), sqlx::Error> {
+                log::info!("fn: add_member()");
+
+                sqlx::query("INSERT OR IGNORE INTO user_room (room_id, user_id) VALUES (?, ?)")
+                    .bind(room_id)
+                    .bind(user_id)
+                    .execute(pool)
+                    .await?;
+
+                return Ok(());
+            }
+
+            // True if the request is new, false if the user was already waiting.
+            pub async fn add_pending_member(room_id: &str, user_id: i64, pool: &SqlitePool) -> Result<bool, sqlx::Error> {
+                log::info!("fn: add_pending_member()");
+
+                let result = sqlx::query("INSERT OR IGNORE INTO pending_member (room_id, user_id) VALUES (?, ?)")
+                    .bind(room_id)
+                    .bind(user_id)
+                    .execute(pool)
+                    .await?;
+
+                return Ok(result.rows_affected() > 0);
+            }
+
+            // Oldest requests first.
+            pub async fn pending_members(room_id: &str, pool: &SqlitePool) -> Result<Vec<PendingMember>, sqlx::Error> {
+                log::info!("fn: pending_members()");
+
+                let pending_members = sqlx::query_as::<_, PendingMember>(
+                    "SELECT user.id AS user_id, user.username, user.display_name, pending_member.requested_at FROM pending_member JOIN user ON user.id = pending_member.user_id WHERE pending_member.room_id = ? ORDER BY pending_member.requested_at, user.id",
+                )
+                .bind(room_id)
+                .fetch_all(pool)
+                .await?;
+
+                return Ok(pending_members);
+            }
+
+            // Removes the request, then adds the user to the room if approved.
+            // False if the user wasn't waiting.
+            pub async fn resolve_pending_member(room_id: &str, user_id: i64, approved: bool, pool: &SqlitePool) -> Result<bool, sqlx::Error> {
+                log::info!("fn: resolve_pending_member() - user {} in room {}, approved: {}", user_id, room_id, approved);
+
+                let mut tx = pool.begin().await?;
+
+                let result = sqlx::query("DELETE FROM pending_member WHERE room_id = ? AND user_id = ?")
+                    .bind(room_id)
+                    .bind(user_id)
+                    .execute(&mut *tx)
+                    .await?;
+
+                if result.rows_affected() == 0 {
+                    return Ok(false);
+                }
+
+                if approved {
+                    sqlx::query("INSERT OR IGNORE INTO user_room (room_id, user_id) VALUES (?, ?)")
+                        .bind(room_id)
+                        .bind(user_id)
+                        .execute(&mut *tx)
+                        .await?;
+                }
+
+                tx.commit().await?;
+
+                return Ok(true);
+            }
+
+            // Removes the room, everything that belongs to it is deleted with it
+            // by the database.
+            pub async fn delete(room_id: &str, pool: &SqlitePool) -> Result<(), sqlx::Error> {
+                log::info!("fn: delete() - deleting room {}", room_id);
+
+                let mut tx = pool.begin().await?;
+
+                sqlx::query("UPDATE user SET last_group_id = NULL WHERE last_group_id = ?")
+                    .bind(room_id)
+                    .execute(&mut *tx)
+                    .await?;
+
+                sqlx::query("DELETE FROM room WHERE id = ?")
+                    .bind(room_id)
+                    .execute(&mut *tx)
+                    .await?;
+
+                tx.commit().await?;
+
+                return Ok(());
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "ssr"))]
+mod tests {
+    use super::*;
+    use crate::test_utils::{create_room, create_user, test_pool};
+
+    async fn count(sql: &str, room_id: &str, pool: &SqlitePool) -> i64 {
+        sqlx::query_scalar(sql).bind(room_id).fetch_one(pool).await.unwrap()
+    }
+
+    async fn add_expense(room_id: &str, paid_by: i64, pool: &SqlitePool) -> i64 {
+        let expense_id: i64 = sqlx::query_scalar("INSERT INTO expense (paid_by, amount_cents, title, room_id) VALUES (?, 100, 'Lunch', ?) RETURNING id")
+            .bind(paid_by)
+            .bind(room_id)
+            .fetch_one(pool)
+            .await
+            .unwrap();
+
+        sqlx::query("INSERT INTO expense_split (expense_id, user_id) VALUES (?, ?)")
+            .bind(expense_id)
+            .bind(paid_by)
+            .execute(pool)
+            .await
+            .unwrap();
+
+        return expense_id;
+    }
+
+    #[tokio::test]
+    async fn deleting_a_room_cascades_to_its_expenses() {
+        let pool = test_pool().await;
+        let alice = create_user("alice", &pool).await;
+        let room = create_room("Trip", &alice, &pool).await;
+        let expense_id = add_expense(&room.id, alice.id, &pool).await;
+
+        Room::delete(&room.id, &pool).await.unwrap();
+
+        assert_eq!(count("SELECT COUNT(*) FROM expense WHERE room_id = ?", &room.id, &pool).await, 0);
+        assert_eq!(count("SELECT COUNT(*) FROM user_room WHERE room_id = ?", &room.id, &pool).await, 0);
+
+        let splits: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM expense_split WHERE expense_id = ?")
+            .bind(expense_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(splits, 0);
+    }
+
+    #[tokio::test]
+    async fn a_user_with_expenses_cannot_be_deleted() {
+        let pool = test_pool().await;
+        let alice = create_user("alice", &pool).await;
+        let room = create_room("Trip", &alice, &pool).await;
+        add_expense(&room.id, alice.id, &pool).await;
+
+        let result = sqlx::query("DELETE FROM user WHERE id = ?").bind(alice.id).execute(&pool).await;
+
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn renaming_to_the_same_slug_keeps_the_suffix() {
+        let pool = test_pool().await;
+        let alice = create_user("alice", &pool).await;
+        create_room("Trip", &alice, &pool).await;
+        let room = create_room("Trip", &alice, &pool).await;
+        assert_eq!(room.slug, "trip-2");
+
+        let renamed = Room::rename(&room.id, "TRIP", &pool).await.unwrap();
+        assert_eq!(renamed.slug, "trip-2");
+        assert_eq!(renamed.room_name, "TRIP");
+
+        let renamed = Room::rename(&room.id, "Trip", &pool).await.unwrap();
+        assert_eq!(renamed.slug, "trip-2");
+    }
+
+    #[tokio::test]
+    async fn renaming_back_reuses_the_old_slug() {
+        let pool = test_pool().await;
+        let alice = create_user("alice", &pool).await;
+        create_room("Trip", &alice, &pool).await;
+        let room = create_room("Trip", &alice, &pool).await;
+
+        let renamed = Room::rename(&room.id, "Rome", &pool).await.unwrap();
+        assert_eq!(renamed.slug, "rome");
+        // the old slug still leads to the room
+        assert_eq!(Room::get_by_slug("trip-2", &pool).await.unwrap().map(|room| room.id), Some(room.id.clone()));
+
+        let renamed = Room::rename(&room.id, "Trip", &pool).await.unwrap();
+        assert_eq!(renamed.slug, "trip-2");
+    }
+}
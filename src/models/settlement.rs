@@ -0,0 +1,37 @@
+use cfg_if::cfg_if;
+use serde::{Deserialize, Serialize};
+
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Settlement {
+    pub id: i64,
+    pub room_id: String,
+    pub from_user: i64,
+    pub to_user: i64,
+    pub amount_cents: i64,
+
+    #[cfg(feature = "ssr")]
+    pub created_at: Option<sqlx::types::chrono::NaiveDateTime>,
+}
+
+cfg_if! {
+    if #[cfg(feature = "ssr")] {
+        use sqlx::sqlite::SqlitePool;
+
+        impl Settlement {
+            pub async fn list_for_room(room_id: &str, pool: &SqlitePool) -> Result<Vec<Self>, sqlx::Error> {
+                log::info!("fn: list_for_room()");
+
+                let settlements = sqlx::query_as::<_, Settlement>(
+                    "SELECT * FROM settlement WHERE room_id = ? ORDER BY created_at DESC, id DESC",
+                )
+                .bind(room_id)
+                .fetch_all(pool)
+                .await?;
+
+                log::info!("fn: list_for_room() - settlements: {:?}", settlements);
+                return Ok(settlements);
+            }
+        }
+    }
+}
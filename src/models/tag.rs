@@ -0,0 +1,73 @@
+use cfg_if::cfg_if;
+use serde::{Deserialize, Serialize};
+
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Tag {
+    pub id: i64,
+    pub name: String,
+}
+
+impl Tag {
+    pub fn normalize(name: &str) -> String {
+        return name.trim().to_lowercase();
+    }
+}
+
+cfg_if! {
+    if #[cfg(feature = "ssr")] {
+        use sqlx::sqlite::SqlitePool;
+
+        impl Tag {
+            // The tag is created the first time it is used, tagging an
+            // expense twice with the same tag does nothing.
+            pub async fn add_to_expense(expense_id: i64, name: &str, pool: &SqlitePool) -> Result<(), sqlx::Error> {
+                log::info!("fn: add_to_expense()");
+
+                let mut tx = pool.begin().await?;
+
+                sqlx::query("INSERT OR IGNORE INTO tag (name) VALUES (?)")
+                    .bind(name)
+                    .execute(&mut *tx)
+                    .await?;
+
+                sqlx::query("INSERT OR IGNORE INTO expense_tag (expense_id, tag_id) SELECT ?, id FROM tag WHERE name = ?")
+                    .bind(expense_id)
+                    .bind(name)
+                    .execute(&mut *tx)
+                    .await?;
+
+                tx.commit().await?;
+
+                return Ok(());
+            }
+
+            // Returns false when the expense didn't have the tag.
+            pub async fn remove_from_expense(expense_id: i64, name: &str, pool: &SqlitePool) -> Result<bool, sqlx::Error> {
+                log::info!("fn: remove_from_expense()");
+
+                let removed = sqlx::query("DELETE FROM expense_tag WHERE expense_id = ? AND tag_id = (SELECT id FROM tag WHERE name = ?)")
+                    .bind(expense_id)
+                    .bind(name)
+                    .execute(pool)
+                    .await?
+                    .rows_affected();
+
+                return Ok(removed > 0);
+            }
+
+            pub async fn list_for_expense(expense_id: i64, pool: &SqlitePool) -> Result<Vec<Self>, sqlx::Error> {
+                log::info!("fn: list_for_expense()");
+
+                let tags = sqlx::query_as::<_, Tag>(
+                    "SELECT tag.id, tag.name FROM tag JOIN expense_tag ON expense_tag.tag_id = tag.id WHERE expense_tag.expense_id = ? ORDER BY tag.name",
+                )
+                .bind(expense_id)
+                .fetch_all(pool)
+                .await?;
+
+                return Ok(tags);
+            }
+        }
+    }
+}
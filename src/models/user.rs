@@ -1,6 +1,6 @@
 use cfg_if::cfg_if;
 use serde::{Deserialize, Serialize};
-use std::{fmt, string};
+use std::{collections::HashMap, fmt, string};
 
 #[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
 #[derive(Clone, Serialize, Deserialize, Eq, PartialEq, Hash)]
@@ -8,6 +8,12 @@ pub struct User {
     pub id: i64,
     pub username: String,
     pub password: String,
+    pub email: Option<String>,
+    pub display_name: Option<String>,
+    pub avatar_url: Option<String>,
+    pub preferred_currency: String,
+    pub locale: String,
+    pub active: bool,
 
     #[cfg(feature = "ssr")]
     pub created_at: Option<sqlx::types::chrono::NaiveDateTime>,
@@ -28,13 +34,50 @@ impl string::ToString for User {
     }
 }
 
+impl User {
+    // Usernames are stored trimmed and lowercase, so " Alice " and "alice"
+    // are the same account.
+    pub fn normalize_username(username: &str) -> String {
+        username.trim().to_lowercase()
+    }
+
+    // The name shown in the UI: the display name if set, the username otherwise.
+    pub fn label(&self) -> String {
+        match &self.display_name {
+            Some(display_name) if !display_name.trim().is_empty() => display_name.clone(),
+            _ => self.username.clone(),
+        }
+    }
+}
+
+// The names shown for the users of a room, looked up by id where only the ids
+// are at hand, e.g. the balances. The users who left the room are shown by id.
+#[derive(Debug, Clone, Default)]
+pub struct UserLabels(HashMap<i64, String>);
+
+impl UserLabels {
+    pub fn new(users: &[User]) -> Self {
+        Self(users.iter().map(|user| (user.id, user.label())).collect())
+    }
+
+    pub fn get(&self, user_id: i64) -> String {
+        self.0.get(&user_id).cloned().unwrap_or_else(|| format!("#{}", user_id))
+    }
+}
+
 impl Default for User {
     fn default() -> Self {
         Self {
             id: -1,
             username: "Guest".to_string(),
             password: "".to_string(),
-             
+            email: None,
+            display_name: None,
+            avatar_url: None,
+            preferred_currency: "USD".to_string(),
+            locale: "en".to_string(),
+            active: true,
+
             #[cfg(feature = "ssr")]
             created_at: None,
         }
@@ -62,7 +105,7 @@ cfg_if! {
             pub async fn get_user_from_username(username: String, pool: &SqlitePool) -> Option<Self> {
                 log::info!("fn: get_user_from_username()");
 
-                let user = sqlx::query_as::<_, User>("SELECT * FROM user WHERE username = ?")
+                let user = sqlx::query_as::<_, User>("SELECT * FROM user WHERE username = ? COLLATE NOCASE")
                     .bind(username.trim())
                     .fetch_one(pool)
                     .await;
@@ -70,6 +113,40 @@ cfg_if! {
                 log::info!("fn: get_user_from_username() - user: {:?}", user);
                 return user.ok();
             }
+
+            pub async fn get_user_from_email(email: String, pool: &SqlitePool) -> Option<Self> {
+                log::info!("fn: get_user_from_email()");
+
+                let user = sqlx::query_as::<_, User>("SELECT * FROM user WHERE email = ? COLLATE NOCASE")
+                    .bind(email.trim())
+                    .fetch_one(pool)
+                    .await;
+
+                log::info!("fn: get_user_from_email() - user: {:?}", user);
+                return user.ok();
+            }
+
+            // The last room opened by the user, only if the user is still a member.
+            pub async fn last_group(id: i64, pool: &SqlitePool) -> Result<Option<String>, sqlx::Error> {
+                let room_id = sqlx::query_scalar(
+                    "SELECT user.last_group_id FROM user JOIN user_room ON user_room.room_id = user.last_group_id AND user_room.user_id = user.id WHERE user.id = ?",
+                )
+                .bind(id)
+                .fetch_optional(pool)
+                .await?;
+
+                return Ok(room_id);
+            }
+
+            pub async fn set_last_group(id: i64, room_id: &str, pool: &SqlitePool) -> Result<(), sqlx::Error> {
+                sqlx::query("UPDATE user SET last_group_id = ? WHERE id = ?")
+                    .bind(room_id)
+                    .bind(id)
+                    .execute(pool)
+                    .await?;
+
+                return Ok(());
+            }
         }
 
         #[async_trait::async_trait]
@@ -78,7 +155,8 @@ cfg_if! {
                 log::info!("fn: load_user()");
 
                 let pool = pool.unwrap();
-                let user = User::get(userid, pool).await;
+                // the sessions of a deactivated account are not valid anymore
+                let user = User::get(userid, pool).await.filter(|user| user.active);
 
                 return user.ok_or_else(|| anyhow::anyhow!("Cannot get user"));
             }
@@ -90,7 +168,7 @@ cfg_if! {
 
             fn is_active(&self) -> bool {
                 log::info!("fn: is_active()");
-                todo!()
+                self.active
             }
 
             fn is_anonymous(&self) -> bool {
@@ -100,3 +178,41 @@ cfg_if! {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_the_case_and_the_spaces() {
+        assert_eq!(User::normalize_username("  Alice "), "alice");
+        assert_eq!(User::normalize_username("BOB"), "bob");
+    }
+
+    #[test]
+    fn labels_the_users_that_left_by_id() {
+        let alice = User {
+            id: 1,
+            username: "alice".to_string(),
+            display_name: Some("Alice".to_string()),
+            ..User::default()
+        };
+        let labels = UserLabels::new(&[alice]);
+
+        assert_eq!(labels.get(1), "Alice");
+        assert_eq!(labels.get(2), "#2");
+    }
+
+    #[cfg(feature = "ssr")]
+    #[tokio::test]
+    async fn finds_the_user_whatever_the_case() {
+        use crate::test_utils::{create_user, test_pool};
+
+        let pool = test_pool().await;
+        let alice = create_user("alice", &pool).await;
+
+        let found = User::get_user_from_username(" ALICE ".to_string(), &pool).await;
+
+        assert_eq!(found.map(|user| user.id), Some(alice.id));
+    }
+}
@@ -4,21 +4,86 @@ use leptos_router::*;
 
 use crate::{
     components::{input_component::{
-        InputComponent, InputParams, InputType, InputWithControlsComponent, InputWithControlsParams,
-    }, notification_component::{NotificationParams, NotificationType, NotificationComponent}},
+        create_debounced_signal, InputComponent, InputParams, InputType, InputWithControlsComponent,
+        InputWithControlsParams,
+    }, notification_component::{NotificationParams, NotificationType, NotificationComponent, use_notifications},
+    password_strength_component::{password_strength, PasswordStrength, PasswordStrengthComponent}},
+    error_template::AppError,
+    i18n::{t, use_lang},
     models::user::User,
+    utils::validation::{check_max_length, join_password_errors, PasswordPolicy, PasswordRule, PASSWORD_MAX_LENGTH, USERNAME_MAX_LENGTH},
 };
 
 cfg_if! {
 if #[cfg(feature = "ssr")] {
     use sqlx::SqlitePool;
     use axum_session_auth::{SessionSqlitePool};
-    use bcrypt::{verify, hash, DEFAULT_COST};
-    use crate::state::{auth, pool};
+    use crate::services::password::{verify_password, Argon2Hasher, PasswordHasher};
+    use std::time::Duration;
+    use crate::state::{app_state, auth, pool, time_server_fn, ClientIp};
+
+    pub const LOGIN_MAX_ATTEMPTS: u32 = 5;
+    pub const LOGIN_ATTEMPTS_WINDOW: Duration = Duration::from_secs(15 * 60);
+
+    pub const REGISTER_MAX_ATTEMPTS: u32 = 3;
+    pub const REGISTER_ATTEMPTS_WINDOW: Duration = Duration::from_secs(60 * 60);
 
     pub type AuthSession = axum_session_auth::AuthSession<User, i64, SessionSqlitePool, SqlitePool>;
+
+    // shared with the session config, the sessions of a user are looked up in it
+    pub const SESSION_TABLE_NAME: &str = "axum_sessions";
+
+    // the user id is stored in the session data too, so that all the
+    // sessions of a user can be found in the session table
+    const SESSION_USER_ID_KEY: &str = "user_id";
+
+    // Ends every session of the user through the store, which removes them
+    // from its cache too: a row deleted only from the table stays valid
+    // until the cached session expires.
+    async fn destroy_user_sessions(user_id: i64) -> Result<(), ServerFnError> {
+        let pool = pool()?;
+        let session_store = app_state()?.session_store;
+
+        // the session data values are stored as JSON strings
+        let session_ids: Vec<String> = sqlx::query_scalar(&format!(
+            "SELECT id FROM {} WHERE json_extract(session, '$.data.' || ?) = ?",
+            SESSION_TABLE_NAME
+        ))
+        .bind(SESSION_USER_ID_KEY)
+        .bind(user_id.to_string())
+        .fetch_all(&pool)
+        .await
+        .map_err(AppError::from)?;
+
+        log::info!("fn: destroy_user_sessions() - destroying {} sessions of user {}", session_ids.len(), user_id);
+
+        for session_id in session_ids {
+            session_store
+                .destroy_session(&session_id)
+                .await
+                .map_err(|e| ServerFnError::ServerError(e.to_string()))?;
+        }
+
+        Ok(())
+    }
+
+    // the parameters never change after the startup, so the first hasher is the right one
+    fn dummy_hash(hasher: &Argon2Hasher) -> &'static str {
+        static DUMMY_HASH: std::sync::OnceLock<String> = std::sync::OnceLock::new();
+        DUMMY_HASH.get_or_init(|| hasher.hash("dummy password").unwrap())
+    }
 }}
 
+const USERNAME_MIN_LENGTH: usize = 5;
+const VALIDATION_DEBOUNCE_MS: u32 = 300;
+
+#[server(GetPasswordPolicy, "/api")]
+pub async fn get_password_policy() -> Result<PasswordPolicy, ServerFnError> {
+    log::info!("fn: get_password_policy()");
+
+    return Ok(app_state()?.password_policy);
+}
+
 #[server(GetUser, "/api")]
 pub async fn get_user() -> Result<Option<User>, ServerFnError> {
     log::info!("fn: get_user()");
@@ -31,10 +96,17 @@ pub async fn get_user() -> Result<Option<User>, ServerFnError> {
 }
 
 #[server(Logout, "/api")]
-pub async fn logout() -> Result<(), ServerFnError> {
+pub async fn logout(all_devices: bool) -> Result<(), ServerFnError> {
     log::info!("fn: logout()");
     let auth = auth()?;
 
+    if all_devices {
+        if let Some(user) = &auth.current_user {
+            log::info!("fn: logout() - deleting all the sessions of user {}", user.id);
+            destroy_user_sessions(user.id).await?;
+        }
+    }
+
     log::info!("fn: logout() - logging out user");
     auth.logout_user();
 
@@ -45,70 +117,200 @@ pub async fn logout() -> Result<(), ServerFnError> {
 }
 
 #[server(Login, "/api")]
-pub async fn login(username: String, password: String) -> Result<(), ServerFnError> {
+pub async fn login(username: String, password: String, remember: bool, reactivate: bool) -> Result<(), ServerFnError> {
+    let _timer = time_server_fn("login");
     log::info!("fn: login()");
 
+    check_max_length("Username", &username, USERNAME_MAX_LENGTH)?;
+    check_max_length("Password", &password, PASSWORD_MAX_LENGTH)?;
+
+    let username = User::normalize_username(&username);
+
+    let app_state = app_state()?;
+    let rate_limiter = app_state.login_rate_limiter;
+    // the attempts are counted before checking the password, a correct one resets them
+    if !rate_limiter.try_attempt(&username) {
+        log::info!("fn: login() - too many attempts for user: {}", username);
+        return Err(AppError::Validation("Too many attempts, try again later".to_string()).into());
+    }
+
     let pool = pool()?;
     let auth = auth()?;
 
-    let user = User::get_user_from_username(username, &pool)
-        .await
-        .ok_or_else(|| {
-            log::info!("fn: login() - user does not exist");
-            return ServerFnError::ServerError("User does not exist".to_string());
-        })?;
+    let Some(user) = User::get_user_from_username(username.clone(), &pool).await else {
+        log::info!("fn: login() - user does not exist");
 
-    if verify(&password, &user.password)? {
+        // verify against a dummy hash so that the response takes as long as
+        // with a wrong password and doesn't tell if the username exists
+        let _ = verify_password(&password, dummy_hash(&app_state.password_hasher));
+
+        // the same error for unknown users and wrong passwords, so that the
+        // client can't tell which usernames are registered
+        return Err(AppError::InvalidCredentials.into());
+    };
+
+    if verify_password(&password, &user.password).unwrap_or(false) {
         log::info!("fn: login() - password is correct");
+        rate_limiter.reset(&username);
+
+        // checked after the password, so that it doesn't tell if the account exists
+        if !user.active {
+            if !reactivate {
+                log::info!("fn: login() - account of user {} is deactivated", user.id);
+                return Err(AppError::AccountDeactivated.into());
+            }
+
+            log::info!("fn: login() - reactivating the account of user {}", user.id);
+            sqlx::query("UPDATE user SET active = 1 WHERE id = ?")
+                .bind(user.id)
+                .execute(&pool)
+                .await
+                .map_err(AppError::from)?;
+        }
+
         log::info!("fn: login() - logging in user");
         auth.login_user(user.id);
+        auth.session.set(SESSION_USER_ID_KEY, user.id);
+        auth.session.set_longterm(remember);
+
+        let user_id = user.id;
+        let redirect_pool = pool.clone();
+
+        // move the bcrypt hashes and the ones made with a lower cost to the
+        // current Argon2 parameters, in the background so that a failure
+        // doesn't change the outcome of the login
+        let hasher = app_state.password_hasher;
+        if hasher.needs_rehash(&user.password) {
+            tokio::spawn(async move {
+                log::info!("fn: login() - rehashing the password of user {} with argon2", user.id);
+
+                let result = match hasher.hash(&password) {
+                    Ok(hashed_password) => sqlx::query("UPDATE user SET password = ? WHERE id = ?")
+                        .bind(hashed_password)
+                        .bind(user.id)
+                        .execute(&pool)
+                        .await
+                        .map(|_| ())
+                        .map_err(|e| e.to_string()),
+                    Err(e) => Err(e.to_string()),
+                };
+
+                if let Err(e) = result {
+                    log::error!("fn: login() - could not rehash the password: {}", e);
+                }
+            });
+        }
+
+        // back to the room opened last time, if any
+        let redirect_to = match User::last_group(user_id, &redirect_pool).await {
+            Ok(Some(room_id)) => format!("/room/{}", room_id),
+            _ => "/".to_string(),
+        };
 
-        log::info!("fn: login() - redirecting to \"/\"");
-        leptos_axum::redirect("/");
+        log::info!("fn: login() - redirecting to {:?}", redirect_to);
+        leptos_axum::redirect(&redirect_to);
         return Ok(());
     } else {
         log::info!("fn: login() - password is incorrect");
-        return Err(ServerFnError::ServerError(
-            "Password is incorrect".to_string(),
-        ));
+
+        return Err(AppError::InvalidCredentials.into());
     }
 }
 
 #[server(Register, "/api")]
 pub async fn register(
     username: String,
+    email: String,
     password: String,
     confirm_password: String,
 ) -> Result<(), ServerFnError> {
+    let _timer = time_server_fn("register");
     log::info!("fn: register()");
 
+    check_max_length("Username", &username, USERNAME_MAX_LENGTH)?;
+    check_max_length("Password", &password, PASSWORD_MAX_LENGTH)?;
+    check_max_length("Password", &confirm_password, PASSWORD_MAX_LENGTH)?;
+
+    // provided by the server_fn_handler
+    let client_ip = use_context::<ClientIp>()
+        .map(|client_ip| client_ip.0.to_string())
+        .unwrap_or_default();
+
+    let app_state = app_state()?;
+    // only the accounts created count, a form with a typo doesn't
+    let rate_limiter = app_state.register_rate_limiter;
+    if rate_limiter.is_limited(&client_ip) {
+        log::info!("fn: register() - too many accounts created from {}", client_ip);
+        return Err(ServerFnError::ServerError(
+            "Too many accounts created, try later".to_string(),
+        ));
+    }
+
     let pool = pool()?;
     let auth = auth()?;
 
+    // the original casing is kept as the display name
+    let display_name = Some(username.trim().to_string()).filter(|name| *name != User::normalize_username(name));
+    let username = User::normalize_username(&username);
+
+    if username.chars().count() < USERNAME_MIN_LENGTH {
+        log::info!("fn: register() - username too short");
+        return Err(AppError::Validation(format!(
+            "Username must be at least {} characters long",
+            USERNAME_MIN_LENGTH
+        ))
+        .into());
+    }
+
+    let email = email.trim().to_string();
+    if !email.contains('@') {
+        log::info!("fn: register() - invalid email");
+        return Err(AppError::Validation("Email is not valid".to_string()).into());
+    }
+
     if password != confirm_password {
         log::info!("fn: register() - passwords do not match");
-        return Err(ServerFnError::ServerError(
-            "Passwords do not match".to_string(),
-        ));
+        return Err(AppError::Validation("Passwords do not match".to_string()).into());
+    }
+
+    app_state.password_policy.check(&password).map_err(|error| {
+        log::info!("fn: register() - password does not follow the policy");
+        error
+    })?;
+
+    log::info!("fn: register() - checking if the username is already taken");
+    if User::get_user_from_username(username.clone(), &pool).await.is_some() {
+        log::info!("fn: register() - username already taken");
+        return Err(AppError::Validation("Username already taken".to_string()).into());
     }
 
-    let hashed_password = hash(password, DEFAULT_COST).unwrap();
+    log::info!("fn: register() - checking if the email is already used");
+    if User::get_user_from_email(email.clone(), &pool).await.is_some() {
+        log::info!("fn: register() - email already used");
+        return Err(AppError::Validation("Email already used".to_string()).into());
+    }
+
+    let hashed_password = app_state.password_hasher.hash(&password)?;
 
     log::info!("fn: register() - creating user on the database");
-    sqlx::query("INSERT INTO user (username, password) VALUES (?, ?)")
+    sqlx::query("INSERT INTO user (username, display_name, email, password) VALUES (?, ?, ?, ?)")
         .bind(&username)
+        .bind(&display_name)
+        .bind(&email)
         .bind(&hashed_password)
         .execute(&pool)
-        .await?;
+        .await
+        .map_err(AppError::from)?;
+
+    rate_limiter.record_attempt(&client_ip);
 
     log::info!("fn: register() - logging in user");
     let user = User::get_user_from_username(username, &pool)
         .await
-        .ok_or_else(|| {
-            return ServerFnError::ServerError("User not found".to_string());
-        })?;
+        .ok_or(AppError::UserNotFound)?;
 
     auth.login_user(user.id);
+    auth.session.set(SESSION_USER_ID_KEY, user.id);
 
     log::info!("fn: register() - redirecting to \"/\"");
     leptos_axum::redirect("/");
@@ -116,22 +318,304 @@ pub async fn register(
     return Ok(());
 }
 
+#[server(IsUsernameAvailable, "/api")]
+pub async fn is_username_available(username: String) -> Result<bool, ServerFnError> {
+    log::info!("fn: is_username_available()");
+
+    let pool = pool()?;
+
+    let row = sqlx::query("SELECT 1 FROM user WHERE lower(username) = ?")
+        .bind(User::normalize_username(&username))
+        .fetch_optional(&pool)
+        .await?;
+
+    log::info!("fn: is_username_available() - available: {}", row.is_none());
+    return Ok(row.is_none());
+}
+
+#[server(ChangePassword, "/api")]
+pub async fn change_password(
+    current_password: String,
+    new_password: String,
+    confirm_password: String,
+) -> Result<(), ServerFnError> {
+    log::info!("fn: change_password()");
+
+    check_max_length("Password", &new_password, PASSWORD_MAX_LENGTH)?;
+
+    let pool = pool()?;
+    let auth = auth()?;
+
+    let user = auth.current_user.ok_or_else(|| {
+        log::info!("fn: change_password() - user not logged in");
+        return ServerFnError::ServerError("User not logged in".to_string());
+    })?;
+
+    if !verify_password(&current_password, &user.password)? {
+        log::info!("fn: change_password() - current password is incorrect");
+        return Err(ServerFnError::ServerError(
+            "Password is incorrect".to_string(),
+        ));
+    }
+
+    if new_password != confirm_password {
+        log::info!("fn: change_password() - passwords do not match");
+        return Err(ServerFnError::ServerError(
+            "Passwords do not match".to_string(),
+        ));
+    }
+
+    app_state()?.password_policy.check(&new_password).map_err(|error| {
+        log::info!("fn: change_password() - new password does not follow the policy");
+        error
+    })?;
+
+    if new_password == current_password {
+        log::info!("fn: change_password() - new password is the same as the current one");
+        return Err(ServerFnError::ServerError(
+            "New password must be different from the current one".to_string(),
+        ));
+    }
+
+    let hashed_password = app_state()?.password_hasher.hash(&new_password)?;
+
+    log::info!("fn: change_password() - updating the password on the database");
+    sqlx::query("UPDATE user SET password = ? WHERE id = ?")
+        .bind(&hashed_password)
+        .bind(user.id)
+        .execute(&pool)
+        .await?;
+
+    return Ok(());
+}
+
+#[server(DeactivateAccount, "/api")]
+pub async fn deactivate_account(password: String) -> Result<(), ServerFnError> {
+    log::info!("fn: deactivate_account()");
+
+    check_max_length("Password", &password, PASSWORD_MAX_LENGTH)?;
+
+    let pool = pool()?;
+    let auth = auth()?;
+
+    let user = auth.current_user.clone().ok_or(AppError::Unauthorized)?;
+
+    if !verify_password(&password, &user.password)? {
+        log::info!("fn: deactivate_account() - password is incorrect");
+        return Err(AppError::Validation("Password is incorrect".to_string()).into());
+    }
+
+    // the expenses and the splits are kept, the balances of the groups
+    // must not change when a member goes away
+    log::info!("fn: deactivate_account() - deactivating the account of user {}", user.id);
+    sqlx::query("UPDATE user SET active = 0 WHERE id = ?")
+        .bind(user.id)
+        .execute(&pool)
+        .await
+        .map_err(AppError::from)?;
+
+    destroy_user_sessions(user.id).await?;
+
+    log::info!("fn: deactivate_account() - logging out user");
+    auth.logout_user();
+
+    log::info!("fn: deactivate_account() - redirecting to \"/\"");
+    leptos_axum::redirect("/");
+
+    return Ok(());
+}
+
+#[server(UpdateProfile, "/api")]
+pub async fn update_profile(
+    display_name: Option<String>,
+    avatar_url: Option<String>,
+) -> Result<(), ServerFnError> {
+    log::info!("fn: update_profile()");
+
+    let pool = pool()?;
+    let auth = auth()?;
+
+    let user = auth.current_user.ok_or_else(|| {
+        log::info!("fn: update_profile() - user not logged in");
+        return ServerFnError::ServerError("User not logged in".to_string());
+    })?;
+
+    let display_name = display_name
+        .map(|x| x.trim().to_string())
+        .filter(|x| !x.is_empty());
+    let avatar_url = avatar_url
+        .map(|x| x.trim().to_string())
+        .filter(|x| !x.is_empty());
+
+    if let Some(avatar_url) = &avatar_url {
+        if !avatar_url.starts_with("http://") && !avatar_url.starts_with("https://") {
+            log::info!("fn: update_profile() - invalid avatar url");
+            return Err(ServerFnError::ServerError(
+                "Avatar url must start with http:// or https://".to_string(),
+            ));
+        }
+    }
+
+    log::info!("fn: update_profile() - updating the profile on the database");
+    sqlx::query("UPDATE user SET display_name = ?, avatar_url = ? WHERE id = ?")
+        .bind(&display_name)
+        .bind(&avatar_url)
+        .bind(user.id)
+        .execute(&pool)
+        .await?;
+
+    return Ok(());
+}
+
+#[server(UpdatePreferences, "/api")]
+pub async fn update_preferences(preferred_currency: String, locale: String) -> Result<(), ServerFnError> {
+    use crate::i18n::Lang;
+    use crate::utils::money::Currency;
+
+    log::info!("fn: update_preferences()");
+
+    let pool = pool()?;
+    let auth = auth()?;
+
+    let user = auth.current_user.ok_or_else(|| {
+        log::info!("fn: update_preferences() - user not logged in");
+        return ServerFnError::ServerError("User not logged in".to_string());
+    })?;
+
+    let currency = preferred_currency
+        .parse::<Currency>()
+        .map_err(|_| ServerFnError::ServerError("Unknown currency".to_string()))?;
+    let lang = Lang::from_code(&locale)
+        .ok_or_else(|| ServerFnError::ServerError("Unknown locale".to_string()))?;
+
+    log::info!("fn: update_preferences() - updating the preferences on the database");
+    sqlx::query("UPDATE user SET preferred_currency = ?, locale = ? WHERE id = ?")
+        .bind(currency.as_str())
+        .bind(lang.as_str())
+        .bind(user.id)
+        .execute(&pool)
+        .await?;
+
+    return Ok(());
+}
+
+#[server(RequestPasswordReset, "/api")]
+pub async fn request_password_reset(email: String) -> Result<(), ServerFnError> {
+    log::info!("fn: request_password_reset()");
+
+    let pool = pool()?;
+
+    // the response is the same whether the email exists or not
+    let Some(user) = User::get_user_from_email(email, &pool).await else {
+        log::info!("fn: request_password_reset() - no user with this email");
+        return Ok(());
+    };
+
+    let token = uuid::Uuid::new_v4().to_string();
+
+    log::info!("fn: request_password_reset() - saving the reset token");
+    sqlx::query(
+        "INSERT INTO password_reset (user_id, token, expires_at) VALUES (?, ?, datetime('now', '+1 hour'))",
+    )
+    .bind(user.id)
+    .bind(&token)
+    .execute(&pool)
+    .await?;
+
+    // TODO: send the link by email
+    log::info!("fn: request_password_reset() - reset link: /reset-password?token={}", token);
+
+    return Ok(());
+}
+
+#[server(ResetPassword, "/api")]
+pub async fn reset_password(
+    token: String,
+    new_password: String,
+    confirm_password: String,
+) -> Result<(), ServerFnError> {
+    log::info!("fn: reset_password()");
+
+    check_max_length("Password", &new_password, PASSWORD_MAX_LENGTH)?;
+
+    let pool = pool()?;
+
+    if new_password != confirm_password {
+        log::info!("fn: reset_password() - passwords do not match");
+        return Err(ServerFnError::ServerError(
+            "Passwords do not match".to_string(),
+        ));
+    }
+
+    app_state()?.password_policy.check(&new_password).map_err(|error| {
+        log::info!("fn: reset_password() - new password does not follow the policy");
+        error
+    })?;
+
+    let mut tx = pool.begin().await?;
+
+    let reset: Option<(i64, bool)> = sqlx::query_as(
+        "SELECT user_id, expires_at > datetime('now') FROM password_reset WHERE token = ?",
+    )
+    .bind(&token)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let Some((user_id, is_valid)) = reset else {
+        log::info!("fn: reset_password() - unknown token");
+        return Err(ServerFnError::ServerError(
+            "Invalid reset token".to_string(),
+        ));
+    };
+
+    // the token can be used only once
+    sqlx::query("DELETE FROM password_reset WHERE token = ?")
+        .bind(&token)
+        .execute(&mut *tx)
+        .await?;
+
+    if !is_valid {
+        log::info!("fn: reset_password() - token expired");
+        tx.commit().await?;
+        return Err(ServerFnError::ServerError(
+            "Reset token expired".to_string(),
+        ));
+    }
+
+    let hashed_password = app_state()?.password_hasher.hash(&new_password)?;
+
+    log::info!("fn: reset_password() - updating the password on the database");
+    sqlx::query("UPDATE user SET password = ? WHERE id = ?")
+        .bind(&hashed_password)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    return Ok(());
+}
+
 #[component]
 pub fn LoginPage() -> impl IntoView {
     let action = create_server_action::<Login>();
 
     let value = action.value();
-    let has_error = move || value.with(|val| matches!(val, Some(Err(_))));
+    let notify = use_notifications();
 
     let (username, set_username) = create_signal(String::new());
     let (password, set_password) = create_signal(String::new());
+    let (remember, set_remember) = create_signal(false);
+    let (reactivate, set_reactivate) = create_signal(false);
+    let (is_deactivated, set_is_deactivated) = create_signal(false);
+    let lang = use_lang();
 
     let is_form_valid = move || {
         !username.with(String::is_empty) && !password.with(String::is_empty)
     };
 
     let username_params = InputParams {
-        label: "Username".to_string(),
+        label: t("field.username", lang.get_untracked()).to_string(),
         placeholder: "username".to_string(),
         name: "username".to_string(),
         input_type: InputType::Text,
@@ -139,55 +623,121 @@ pub fn LoginPage() -> impl IntoView {
     };
 
     let password_params = InputParams {
-        label: "Password".to_string(),
+        label: t("field.password", lang.get_untracked()).to_string(),
         placeholder: "******".to_string(),
         name: "password".to_string(),
         input_type: InputType::Password,
         value: (password, set_password),
     };
 
-    let get_notification_params = move || {
-        let server_message = value().unwrap().unwrap_err().to_string();
-        let client_message = server_message.replace("error running server function: ", "");
+    let username_ref = create_node_ref::<html::Input>();
+    let password_ref = create_node_ref::<html::Input>();
 
-        NotificationParams {
-            message: client_message,
-            notification_type: NotificationType::Error,
+    // runs again once the input is mounted
+    create_effect(move |_| {
+        if let Some(input) = username_ref.get() {
+            let _ = input.focus();
+        }
+    });
+
+    // after a wrong password the username is usually right
+    let focus_first_invalid = move || {
+        let input = if username.with(String::is_empty) { username_ref } else { password_ref };
+        if let Some(input) = input.get_untracked() {
+            let _ = input.focus();
         }
     };
 
+    // Enter submits the form by itself, unless the disabled button blocks it
+    let on_keydown = move |ev: ev::KeyboardEvent| {
+        if ev.key() == "Enter" && !is_form_valid() {
+            focus_first_invalid();
+        }
+    };
+
+    create_effect(move |_| {
+        if let Some(Err(err)) = value.get() {
+            focus_first_invalid();
+
+            let error = AppError::from_server_fn_error(&err);
+
+            // the account can be reactivated only after confirming it
+            if let AppError::AccountDeactivated = error {
+                set_is_deactivated(true);
+            }
+
+            // the user can fix the validation errors, the others are unexpected
+            let notification_type = match error {
+                AppError::Validation(_) | AppError::InvalidCredentials | AppError::AccountDeactivated => {
+                    NotificationType::Warning
+                }
+                _ => NotificationType::Error,
+            };
+
+            notify(NotificationParams {
+                message: error.to_string(),
+                notification_type,
+                auto_dismiss_ms: None,
+            });
+        }
+    });
+
     view! {
-        <div class="flex h-screen justify-center items-center">
+        <div class="flex h-screen justify-center items-center" on:keydown=on_keydown>
             <ActionForm action=action class="space-y-3 w-80">
-                <p class="text-3xl font-bold mb-6">"Log In"</p>
+                <p class="text-3xl font-bold mb-6">{move || t("login.title", lang.get())}</p>
+
+                <InputComponent params=username_params node_ref=username_ref/>
+                <InputComponent params=password_params node_ref=password_ref/>
+
+                <div class="form-control">
+                    <label class="label cursor-pointer">
+                        <span class="label-text">{move || t("login.remember_me", lang.get())}</span>
+                        <input
+                            type="checkbox"
+                            class="checkbox checkbox-primary"
+                            prop:checked=remember
+                            on:change=move |ev| set_remember(event_target_checked(&ev))
+                        />
+                    </label>
+                    // an unchecked checkbox isn't submitted, so the value is sent explicitly
+                    <input type="hidden" name="remember" value=move || remember().to_string()/>
+                </div>
 
-                <InputComponent params=username_params/>
-                <InputComponent params=password_params/>
+                <Show when=is_deactivated fallback=|| ()>
+                    <div class="form-control">
+                        <label class="label cursor-pointer">
+                            <span class="label-text">{move || t("login.reactivate", lang.get())}</span>
+                            <input
+                                type="checkbox"
+                                class="checkbox checkbox-warning"
+                                prop:checked=reactivate
+                                on:change=move |ev| set_reactivate(event_target_checked(&ev))
+                            />
+                        </label>
+                    </div>
+                </Show>
+                <input type="hidden" name="reactivate" value=move || reactivate().to_string()/>
 
                 <button
                     class="btn btn-primary btn-lg w-full"
                     type="submit"
                     prop:disabled=move || !is_form_valid()
                 >
-                    <b>LOGIN</b>
+                    <b>{move || t("login.submit", lang.get())}</b>
                 </button>
 
                 <div class="w-full">
                     <p class="text-center">
-                        "Don't have an account? " <A href="/register">
+                        {move || t("login.no_account", lang.get())} <A href="/register">
                             <b>
-                                <u>"Register now!"</u>
+                                <u>{move || t("login.register_now", lang.get())}</u>
                             </b>
                         </A>
                     </p>
                 </div>
 
             </ActionForm>
-
-            <Show when=has_error fallback=|| ()>
-                <NotificationComponent params=get_notification_params()/>
-            </Show>
-
         </div>
     }
 }
@@ -196,44 +746,87 @@ pub fn LoginPage() -> impl IntoView {
 pub fn RegisterPage() -> impl IntoView {
     let action = create_server_action::<Register>();
 
+    let value = action.value();
+    let has_error = move || value.with(|val| matches!(val, Some(Err(_))));
+
     let (username, set_username) = create_signal(String::new());
+    let (email, set_email) = create_signal(String::new());
     let (password, set_password) = create_signal(String::new());
     let (confirm_password, set_confirm_password) = create_signal(String::new());
-
-    const USERNAME_MIN_LENGTH: usize = 5;
-    const PASSWORD_MIN_LENGTH: usize = 8;
+    let lang = use_lang();
+
+    // the inputs update instantly, the validation follows the debounced values
+    let debounced_username = create_debounced_signal(username, VALIDATION_DEBOUNCE_MS);
+    let debounced_email = create_debounced_signal(email, VALIDATION_DEBOUNCE_MS);
+    let debounced_password = create_debounced_signal(password, VALIDATION_DEBOUNCE_MS);
+    let debounced_confirm_password =
+        create_debounced_signal(confirm_password, VALIDATION_DEBOUNCE_MS);
+
+    let username_available = create_resource(
+        move || debounced_username.get(),
+        |username| async move {
+            // too short usernames are rejected anyway, no need to ask the server
+            if username.len() < USERNAME_MIN_LENGTH {
+                return Ok(true);
+            }
+            is_username_available(username).await
+        },
+    );
 
     let username_error = move || {
-        if username.with(String::is_empty) {
-            Some("Username cannot be empty".to_string())
-        } else if username.with(|x| x.len() < USERNAME_MIN_LENGTH) {
-            return Some(format!(
-                "Username must be at least {} characters long",
-                USERNAME_MIN_LENGTH
-            ));
+        if debounced_username.with(String::is_empty) {
+            Some(t("error.username_empty", lang.get()).to_string())
+        } else if debounced_username.with(|x| x.len() < USERNAME_MIN_LENGTH) {
+            return Some(
+                t("error.username_too_short", lang.get())
+                    .replace("{min}", &USERNAME_MIN_LENGTH.to_string()),
+            );
+        } else if let Some(Ok(false)) = username_available.get() {
+            return Some(t("error.username_taken", lang.get()).to_string());
         } else {
             return None;
         }
     };
 
-    let password_error = move || {
-        if password.with(String::is_empty) {
-            Some("Password cannot be empty".to_string())
-        } else if password.with(|x| x.len() < PASSWORD_MIN_LENGTH) {
-            return Some(format!(
-                "Password must be at least {} characters long",
-                PASSWORD_MIN_LENGTH
-            ));
+    let email_error = move || {
+        if debounced_email.with(String::is_empty) {
+            Some(t("error.email_empty", lang.get()).to_string())
+        } else if debounced_email.with(|x| !x.contains('@')) {
+            return Some(t("error.email_invalid", lang.get()).to_string());
         } else {
             return None;
         }
     };
 
+    // the default policy is used until the server sends the configured one
+    let password_policy = create_resource(|| (), |_| get_password_policy());
+    let password_rule_error = move |rule: PasswordRule| match rule {
+        PasswordRule::MinLength(min) => t("error.password_too_short", lang.get()).replace("{min}", &min.to_string()),
+        PasswordRule::Digit => t("error.password_needs_digit", lang.get()).to_string(),
+        PasswordRule::Symbol => t("error.password_needs_symbol", lang.get()).to_string(),
+    };
+
+    let password_error = move || {
+        let policy = password_policy.get().and_then(Result::ok).unwrap_or_default();
+
+        if debounced_password.with(String::is_empty) {
+            Some(t("error.password_empty", lang.get()).to_string())
+        } else {
+            let errors: Vec<String> = debounced_password
+                .with(|x| policy.unmet_rules(x))
+                .into_iter()
+                .map(password_rule_error)
+                .collect();
+
+            return Some(join_password_errors(&errors)).filter(|errors| !errors.is_empty());
+        }
+    };
+
     let confirm_password_error = move || {
-        if confirm_password.with(String::is_empty) {
-            Some("Password cannot be empty".to_string())
-        } else if confirm_password.with(|x| *x != password.get()) {
-            return Some("Passwords do not match".to_string());
+        if debounced_confirm_password.with(String::is_empty) {
+            Some(t("error.password_empty", lang.get()).to_string())
+        } else if debounced_confirm_password.with(|x| *x != debounced_password.get()) {
+            return Some(t("error.passwords_do_not_match", lang.get()).to_string());
         } else {
             return None;
         }
@@ -241,21 +834,32 @@ pub fn RegisterPage() -> impl IntoView {
 
     let is_form_valid = move || {
         username_error().is_none()
+            && email_error().is_none()
             && password_error().is_none()
             && confirm_password_error().is_none()
+            && password.with(|x| password_strength(x)) >= PasswordStrength::Fair
     };
 
     let username_params = InputWithControlsParams {
-        label: "Username".to_string(),
-        placeholder: "Username".to_string(),
+        label: t("field.username", lang.get_untracked()).to_string(),
+        placeholder: t("field.username", lang.get_untracked()).to_string(),
         name: "username".to_string(),
         input_type: InputType::Text,
         value: (username, set_username),
         value_error: username_error,
     };
 
+    let email_params = InputWithControlsParams {
+        label: t("field.email", lang.get_untracked()).to_string(),
+        placeholder: t("field.email", lang.get_untracked()).to_string(),
+        name: "email".to_string(),
+        input_type: InputType::Email,
+        value: (email, set_email),
+        value_error: email_error,
+    };
+
     let password_params = InputWithControlsParams {
-        label: "Password".to_string(),
+        label: t("field.password", lang.get_untracked()).to_string(),
         placeholder: "******".to_string(),
         name: "password".to_string(),
         input_type: InputType::Password,
@@ -264,7 +868,7 @@ pub fn RegisterPage() -> impl IntoView {
     };
 
     let confirm_password_params = InputWithControlsParams {
-        label: "Confirm password".to_string(),
+        label: t("field.confirm_password", lang.get_untracked()).to_string(),
         placeholder: "******".to_string(),
         name: "confirm_password".to_string(),
         input_type: InputType::Password,
@@ -272,13 +876,30 @@ pub fn RegisterPage() -> impl IntoView {
         value_error: confirm_password_error,
     };
 
+    let get_notification_params = move || {
+        let error = AppError::from_server_fn_error(&value().unwrap().unwrap_err());
+
+        let notification_type = match error {
+            AppError::Validation(_) => NotificationType::Warning,
+            _ => NotificationType::Error,
+        };
+
+        NotificationParams {
+            message: error.to_string(),
+            notification_type,
+            auto_dismiss_ms: None,
+        }
+    };
+
     view! {
         <div class="flex h-screen justify-center items-center">
             <ActionForm action=action class="space-y-3 w-80">
-                <p class="text-3xl font-bold mb-6">"Register"</p>
+                <p class="text-3xl font-bold mb-6">{move || t("register.title", lang.get())}</p>
 
                 <InputWithControlsComponent params=username_params/>
+                <InputWithControlsComponent params=email_params/>
                 <InputWithControlsComponent params=password_params/>
+                <PasswordStrengthComponent password=password/>
                 <InputWithControlsComponent params=confirm_password_params/>
 
                 <button
@@ -286,20 +907,25 @@ pub fn RegisterPage() -> impl IntoView {
                     type="submit"
                     prop:disabled=move || !is_form_valid()
                 >
-                    <b>REGISTER</b>
+                    <b>{move || t("register.submit", lang.get())}</b>
                 </button>
 
                 <div class="w-full">
                     <p class="text-center">
-                        "Already have an account? " <A href="/login">
+                        {move || t("register.has_account", lang.get())} <A href="/login">
                             <b>
-                                <u>"Login now!"</u>
+                                <u>{move || t("register.login_now", lang.get())}</u>
                             </b>
                         </A>
                     </p>
                 </div>
 
             </ActionForm>
+
+            <Show when=has_error fallback=|| ()>
+                <NotificationComponent params=get_notification_params()/>
+            </Show>
+
         </div>
     }
 }
@@ -307,7 +933,7 @@ pub fn RegisterPage() -> impl IntoView {
 #[component]
 pub fn LogoutPage() -> impl IntoView {
     let action = create_server_action::<Logout>();
-    action.dispatch(Logout {});
+    action.dispatch(Logout { all_devices: false });
 
     view! { <div></div> }
 }
@@ -11,10 +11,13 @@ pub async fn create_room(room_name: String) -> Result<(), ServerFnError> {
     use crate::models::room::Room;
     use crate::state::pool;
     use crate::state::auth;
+    use crate::utils::validation::{check_max_length, ROOM_NAME_MAX_LENGTH};
 
     let pool = pool()?;
     let auth = auth()?;
 
+    check_max_length("Room name", &room_name, ROOM_NAME_MAX_LENGTH)?;
+
     let user = auth.current_user.unwrap();
     let user_id = user.id;
 
@@ -41,25 +44,13 @@ pub async fn create_room(room_name: String) -> Result<(), ServerFnError> {
 
     log!("fn: create_room() - creating room: {:?}", room_name);
 
-    let random_uuid = uuid::Uuid::new_v4().to_string();
-    log!("fn: create_room() - generated a random uuid: {}", random_uuid);
-
-    // insert the room 
-    let res = sqlx::query_as!(
-        Room,
-        "INSERT INTO room (id, room_name, max_participants, owner) VALUES ($1, $2, 20, $3) RETURNING *",
-        random_uuid,
-        room_name,
-        user_id
-    )
-    .fetch_one(&pool)
-    .await;
+    // insert the room and add the owner to it, in the currency the owner prefers
+    let res = Room::create(room_name, user_id, &user.preferred_currency, &pool).await;
 
     match res {
         Ok(room) => {
             log::info!("fn: create_room() - created room: {:?}", room);
-            let id = room.id;
-            leptos_axum::redirect(&format!("/room/{}", id));
+            leptos_axum::redirect(&format!("/room/{}", room.slug));
             Ok(())
         }
         Err(e) => {
@@ -94,6 +85,7 @@ pub fn CreateRoomPage() -> impl IntoView {
         NotificationParams {
             message: client_message,
             notification_type: NotificationType::Error,
+            auto_dismiss_ms: None,
         }
     };
 
@@ -1,12 +1,23 @@
 use crate::components::{
     expenses_component::ExpensesComponent, user_in_room_component::UserInRoomComponent, add_expense_component::AddExpenseComponent,
 };
+use crate::models::{activity::Activity, expense::SplitMethodKind, room::Room};
 use leptos::*;
 use leptos_router::*;
+use serde::{Deserialize, Serialize};
 
 #[derive(Params, PartialEq, Eq, Clone)]
 struct DashboardPageParams {
-    id: String,
+    slug: String,
+}
+
+// The spending of the current month against the budget of the room.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BudgetStatus {
+    pub budget: i64,
+    pub spent_this_month: i64,
+    pub remaining: i64,
+    pub over_by: Option<i64>,
 }
 
 #[server(GetRoomId, "/api")]
@@ -28,29 +39,431 @@ pub async fn get_room_name(room_id: String) -> Result<String, ServerFnError> {
     Ok(room_name)
 }
 
+// Also accepts the id of the room, for the links made before the slugs.
+#[server(GetRoomBySlug, "/api")]
+pub async fn get_room_by_slug(slug: String) -> Result<Room, ServerFnError> {
+    use crate::state::{auth, pool};
+    use leptos::logging::log;
+
+    let pool = pool()?;
+    let auth = auth()?;
+
+    let user = auth
+        .current_user
+        .ok_or_else(|| ServerFnError::ServerError("User not logged in".to_string()))?;
+
+    let room = match Room::get_by_slug(&slug, &pool).await? {
+        Some(room) => room,
+        None => Room::get(&slug, &pool)
+            .await?
+            .ok_or_else(|| ServerFnError::ServerError("Room not found".to_string()))?,
+    };
+
+    if !Room::is_member(&room.id, user.id, &pool).await? {
+        log!("fn: get_room_by_slug() - user {} is not in room {}", user.id, room.id);
+        return Err(ServerFnError::ServerError(
+            "You are not a member of this room".to_string(),
+        ));
+    }
+
+    Ok(room)
+}
+
+#[server(RenameGroup, "/api")]
+pub async fn rename_group(room_id: String, room_name: String) -> Result<(), ServerFnError> {
+    use crate::models::room::Role;
+    use crate::state::{auth, pool};
+    use crate::utils::validation::{check_max_length, ROOM_NAME_MAX_LENGTH};
+    use leptos::logging::log;
+
+    let pool = pool()?;
+    let auth = auth()?;
+
+    let user = auth
+        .current_user
+        .ok_or_else(|| ServerFnError::ServerError("User not logged in".to_string()))?;
+
+    if Room::user_role(&room_id, user.id, &pool).await? < Some(Role::Admin) {
+        log!("fn: rename_group() - user {} is not an admin of room {}", user.id, room_id);
+        return Err(ServerFnError::ServerError(
+            "Only the admins can rename the room".to_string(),
+        ));
+    }
+
+    let room_name = room_name.trim();
+    if room_name.is_empty() {
+        return Err(ServerFnError::ServerError(
+            "The room name cannot be empty".to_string(),
+        ));
+    }
+
+    check_max_length("Room name", room_name, ROOM_NAME_MAX_LENGTH)?;
+
+    let room = Room::rename(&room_id, room_name, &pool).await?;
+
+    log!("fn: rename_group() - redirecting to /room/{}", room.slug);
+    leptos_axum::redirect(&format!("/room/{}", room.slug));
+
+    Ok(())
+}
+
+#[server(GetActivity, "/api")]
+pub async fn get_activity(room_id: String, limit: i64) -> Result<Vec<Activity>, ServerFnError> {
+    use crate::models::room::Room;
+    use crate::state::{auth, pool};
+    use leptos::logging::log;
+
+    const MAX_ACTIVITY_LIMIT: i64 = 100;
+
+    let pool = pool()?;
+    let auth = auth()?;
+
+    let user = auth
+        .current_user
+        .ok_or_else(|| ServerFnError::ServerError("User not logged in".to_string()))?;
+
+    if !Room::is_member(&room_id, user.id, &pool).await? {
+        log!("fn: get_activity() - user {} is not in room {}", user.id, room_id);
+        return Err(ServerFnError::ServerError(
+            "You are not a member of this room".to_string(),
+        ));
+    }
+
+    let activities = Activity::list_for_room(&room_id, limit.clamp(1, MAX_ACTIVITY_LIMIT), &pool).await?;
+
+    Ok(activities)
+}
+
+// Without `force`, the rooms with debts still to settle are kept.
+#[server(DeleteGroup, "/api")]
+pub async fn delete_group(room_id: String, force: bool) -> Result<(), ServerFnError> {
+    use crate::i18n::Lang;
+    use crate::models::room::{Role, Room};
+    use crate::services::balance::room_balances;
+    use crate::state::{auth, pool};
+    use crate::utils::money::{format_cents, Currency};
+    use leptos::logging::log;
+
+    let pool = pool()?;
+    let auth = auth()?;
+
+    let user = auth
+        .current_user
+        .ok_or_else(|| ServerFnError::ServerError("User not logged in".to_string()))?;
+
+    if Room::user_role(&room_id, user.id, &pool).await? != Some(Role::Owner) {
+        log!("fn: delete_group() - user {} is not an owner of room {}", user.id, room_id);
+        return Err(ServerFnError::ServerError(
+            "Only the owners can delete the room".to_string(),
+        ));
+    }
+
+    if !force {
+        let outstanding: i64 = room_balances(&room_id, &pool)
+            .await?
+            .values()
+            .filter(|balance| **balance > 0)
+            .sum();
+
+        if outstanding > 0 {
+            let base_currency: String = sqlx::query_scalar("SELECT base_currency FROM room WHERE id = ?")
+                .bind(&room_id)
+                .fetch_one(&pool)
+                .await?;
+
+            let outstanding = format_cents(
+                outstanding,
+                base_currency.parse().unwrap_or(Currency::USD),
+                Lang::from_code(&user.locale).unwrap_or_default(),
+            );
+
+            log!("fn: delete_group() - room {} has {} to settle", room_id, outstanding);
+            return Err(ServerFnError::ServerError(format!(
+                "The room still has {} to settle, settle up first or force the deletion",
+                outstanding
+            )));
+        }
+    }
+
+    Room::delete(&room_id, &pool).await?;
+
+    log!("fn: delete_group() - redirecting to \"/\"");
+    leptos_axum::redirect("/");
+
+    Ok(())
+}
+
+// Only the settings that are sent are changed, the others keep their value.
+// A budget of 0 removes it.
+#[server(UpdateGroupSettings, "/api")]
+pub async fn update_group_settings(
+    room_id: String,
+    default_split_method: Option<SplitMethodKind>,
+    monthly_budget_cents: Option<i64>,
+    approval_required: Option<bool>,
+) -> Result<(), ServerFnError> {
+    use crate::models::room::{Role, Room};
+    use crate::services::authz::require_role;
+    use crate::state::{auth, pool};
+    use leptos::logging::log;
+
+    let pool = pool()?;
+    require_role(&room_id, Role::Admin, &pool, &auth()?).await?;
+
+    if monthly_budget_cents.is_some_and(|budget| budget < 0) {
+        return Err(ServerFnError::ServerError(
+            "The budget must be positive".to_string(),
+        ));
+    }
+
+    let room = Room::get(&room_id, &pool)
+        .await?
+        .ok_or_else(|| ServerFnError::ServerError("Room not found".to_string()))?;
+
+    let default_split_method = match default_split_method {
+        Some(default_split_method) => default_split_method.as_str().to_string(),
+        None => room.default_split_method,
+    };
+    let monthly_budget_cents = match monthly_budget_cents {
+        Some(0) => None,
+        Some(budget) => Some(budget),
+        None => room.monthly_budget_cents,
+    };
+    let approval_required = approval_required.unwrap_or(room.approval_required);
+
+    log!("fn: update_group_settings() - room {} splits by {:?}", room_id, default_split_method);
+    sqlx::query("UPDATE room SET default_split_method = ?, monthly_budget_cents = ?, approval_required = ? WHERE id = ?")
+        .bind(default_split_method)
+        .bind(monthly_budget_cents)
+        .bind(approval_required)
+        .bind(&room_id)
+        .execute(&pool)
+        .await?;
+
+    Ok(())
+}
+
+// None if the room has no budget.
+#[server(GetBudgetStatus, "/api")]
+pub async fn get_budget_status(room_id: String) -> Result<Option<BudgetStatus>, ServerFnError> {
+    use crate::models::{expense::Expense, room::Room};
+    use crate::state::{auth, pool};
+    use crate::utils::time::month_bounds;
+    use chrono::Datelike;
+    use leptos::logging::log;
+
+    let pool = pool()?;
+    let auth = auth()?;
+
+    let user = auth
+        .current_user
+        .ok_or_else(|| ServerFnError::ServerError("User not logged in".to_string()))?;
+
+    if !Room::is_member(&room_id, user.id, &pool).await? {
+        log!("fn: get_budget_status() - user {} is not in room {}", user.id, room_id);
+        return Err(ServerFnError::ServerError(
+            "You are not a member of this room".to_string(),
+        ));
+    }
+
+    let budget: Option<i64> = sqlx::query_scalar("SELECT monthly_budget_cents FROM room WHERE id = ?")
+        .bind(&room_id)
+        .fetch_one(&pool)
+        .await?;
+
+    let Some(budget) = budget else {
+        return Ok(None);
+    };
+
+    let today = chrono::Utc::now().date_naive();
+    let (from, to) = month_bounds(today.year(), today.month())
+        .ok_or_else(|| ServerFnError::ServerError("Invalid month".to_string()))?;
+
+    let spent_this_month: i64 = Expense::totals_paid_between(&room_id, from.date(), to.date(), &pool)
+        .await?
+        .iter()
+        .map(|(_, total)| total)
+        .sum();
+
+    log!("fn: get_budget_status() - room {} spent {} of {}", room_id, spent_this_month, budget);
+
+    Ok(Some(BudgetStatus {
+        budget,
+        spent_this_month,
+        remaining: (budget - spent_this_month).max(0),
+        over_by: Some(spent_this_month - budget).filter(|over_by| *over_by > 0),
+    }))
+}
+
+// Reverts the last expense created, edited or deleted by the user in the room,
+// if it happened less than 5 minutes ago.
+#[server(UndoLastAction, "/api")]
+pub async fn undo_last_action(room_id: String) -> Result<(), ServerFnError> {
+    use crate::models::activity::{ActivityAction, ExpenseSnapshot};
+    use crate::models::room::Room;
+    use crate::state::{auth, pool, publish_room_event, room_events::RoomEvent};
+    use leptos::logging::log;
+
+    // for SQLite datetime()
+    const UNDO_WINDOW: &str = "-5 minutes";
+
+    let pool = pool()?;
+    let auth = auth()?;
+
+    let user = auth
+        .current_user
+        .ok_or_else(|| ServerFnError::ServerError("User not logged in".to_string()))?;
+
+    if !Room::is_member(&room_id, user.id, &pool).await? {
+        log!("fn: undo_last_action() - user {} is not in room {}", user.id, room_id);
+        return Err(ServerFnError::ServerError(
+            "You are not a member of this room".to_string(),
+        ));
+    }
+
+    let Some((activity, details)) = Activity::last_undoable(&room_id, user.id, UNDO_WINDOW, &pool).await? else {
+        log!("fn: undo_last_action() - nothing to undo for user {} in room {}", user.id, room_id);
+        return Err(ServerFnError::ServerError("Nothing to undo".to_string()));
+    };
+
+    let expense_id = activity.entity_id;
+    let mut tx = pool.begin().await?;
+
+    let event = match activity.action {
+        ActivityAction::ExpenseCreated | ActivityAction::ExpenseRestored => {
+            sqlx::query("UPDATE expense SET deleted_at = CURRENT_TIMESTAMP WHERE id = ? AND deleted_at IS NULL")
+                .bind(expense_id)
+                .execute(&mut *tx)
+                .await?;
+
+            Some(RoomEvent::ExpenseDeleted { expense_id })
+        }
+        // deleted expenses are kept for 30 days, far longer than the undo window
+        ActivityAction::ExpenseDeleted => {
+            sqlx::query("UPDATE expense SET deleted_at = NULL WHERE id = ?")
+                .bind(expense_id)
+                .execute(&mut *tx)
+                .await?;
+
+            Some(RoomEvent::ExpenseCreated { expense_id })
+        }
+        ActivityAction::ExpenseEdited => {
+            let snapshot: ExpenseSnapshot = details
+                .and_then(|details| serde_json::from_str(&details).ok())
+                .ok_or_else(|| ServerFnError::ServerError("This edit cannot be undone".to_string()))?;
+
+            sqlx::query("UPDATE expense SET title = ?, description = ?, amount_cents = ?, occurred_on = COALESCE(?, occurred_on) WHERE id = ?")
+                .bind(&snapshot.title)
+                .bind(&snapshot.description)
+                .bind(snapshot.amount_cents)
+                .bind(snapshot.occurred_on.map(|occurred_on| occurred_on.to_string()))
+                .bind(expense_id)
+                .execute(&mut *tx)
+                .await?;
+
+            for (user_id, amount) in snapshot.split_amounts {
+                sqlx::query("UPDATE expense_split SET amount_cents = ? WHERE expense_id = ? AND user_id = ?")
+                    .bind(amount)
+                    .bind(expense_id)
+                    .bind(user_id)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+
+            None
+        }
+        ActivityAction::SettlementRecorded => {
+            return Err(ServerFnError::ServerError(
+                "Settlements cannot be undone".to_string(),
+            ));
+        }
+    };
+
+    sqlx::query("UPDATE activity SET undone_at = CURRENT_TIMESTAMP WHERE id = ?")
+        .bind(activity.id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    log!("fn: undo_last_action() - undid {} of expense {}", activity.action.as_str(), expense_id);
+
+    if let Some(event) = event {
+        publish_room_event(&room_id, event);
+    }
+
+    Ok(())
+}
+
+// Remembers the room, so that the next login goes straight to it.
+#[server(SetLastGroup, "/api")]
+pub async fn set_last_group(room_id: String) -> Result<(), ServerFnError> {
+    use crate::models::{room::Room, user::User};
+    use crate::state::{auth, pool};
+    use leptos::logging::log;
+
+    let pool = pool()?;
+    let auth = auth()?;
+
+    let user = auth
+        .current_user
+        .ok_or_else(|| ServerFnError::ServerError("User not logged in".to_string()))?;
+
+    if !Room::is_member(&room_id, user.id, &pool).await? {
+        log!("fn: set_last_group() - user {} is not in room {}", user.id, room_id);
+        return Err(ServerFnError::ServerError(
+            "You are not a member of this room".to_string(),
+        ));
+    }
+
+    User::set_last_group(user.id, &room_id, &pool).await?;
+
+    Ok(())
+}
+
 #[component]
 pub fn DashboardPage() -> impl IntoView {
     let params = use_params::<DashboardPageParams>();
 
-    let id = move || params.with(|p| p.clone().map(|p| p.id).unwrap_or_default());
-    let room_name = create_resource(move || (), move |_| get_room_name(id()));
+    let slug = move || params.with(|p| p.clone().map(|p| p.slug).unwrap_or_default());
+    let room = create_resource(slug, get_room_by_slug);
+
+    let set_last_group = create_server_action::<SetLastGroup>();
+    create_effect(move |_| {
+        if let Some(Ok(room)) = room.get() {
+            set_last_group.dispatch(SetLastGroup { room_id: room.id });
+        }
+    });
+
+    // old slugs and ids are redirected to the current slug
+    let navigate = use_navigate();
+    create_effect(move |_| {
+        if let Some(Ok(room)) = room.get() {
+            if room.slug != slug() {
+                navigate(&format!("/room/{}", room.slug), NavigateOptions { replace: true, ..Default::default() });
+            }
+        }
+    });
 
     view! {
         <div class="flex flex-col h-screen justify-center items-center">
             <Transition fallback=move || view!{<p>"Loading..."</p>}>
-                {move || 
-                    view!{
-                        <p class="text-2xl font-bold mb-4">{room_name.get()}</p>
-                    }.into_view()
-                }
-            </Transition>
+                {move || room.get().map(|room| match room {
+                    Ok(room) => view!{
+                        <p class="text-2xl font-bold mb-4">{room.room_name.clone()}</p>
 
-            <div class="grid grid-cols-2 gap-4">
-                <ExpensesComponent room_id=id() />
-                <UserInRoomComponent room_id=id() />
-            </div>
+                        <div class="grid grid-cols-2 gap-4">
+                            <ExpensesComponent room_id=room.id.clone() />
+                            <UserInRoomComponent room_id=room.id.clone() />
+                        </div>
 
-            <AddExpenseComponent room_id=id() />
+                        <AddExpenseComponent room_id=room.id />
+                    }.into_view(),
+                    Err(e) => view!{
+                        <p class="text-error">{e.to_string()}</p>
+                    }.into_view(),
+                })}
+            </Transition>
         </div>
     }
 }
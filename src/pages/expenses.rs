@@ -0,0 +1,630 @@
+use chrono::NaiveDate;
+use leptos::*;
+
+use crate::models::{
+    comment::Comment,
+    expense::{Bucket, CurrencyBreakdown, Expense, NewExpense},
+    recurring_expense::Interval,
+    tag::Tag,
+};
+
+#[server(EditExpense, "/api")]
+pub async fn edit_expense(
+    expense_id: i64,
+    title: String,
+    description: Option<String>,
+    amount_cents: i64,
+    occurred_on: NaiveDate,
+) -> Result<Expense, ServerFnError> {
+    use crate::models::activity::{log_activity_with_details, ActivityAction, ExpenseSnapshot};
+    use crate::models::expense::SplitMethod;
+    use crate::models::room::Role;
+    use crate::services::authz::require_author_or_role;
+    use crate::services::balance::distribute_remainder;
+    use crate::state::{auth, pool, time_server_fn};
+    use crate::utils::money::validate_amount;
+    use crate::utils::validation::{check_max_length, check_not_in_future, DESCRIPTION_MAX_LENGTH, TITLE_MAX_LENGTH};
+    use leptos::logging::log;
+
+    let _timer = time_server_fn("edit_expense");
+
+    let pool = pool()?;
+    let auth = auth()?;
+
+    validate_amount(amount_cents).map_err(|error| {
+        log!("fn: edit_expense() - invalid amount: {}", amount_cents);
+        error
+    })?;
+
+    check_max_length("Title", &title, TITLE_MAX_LENGTH)?;
+    check_max_length("Description", description.as_deref().unwrap_or_default(), DESCRIPTION_MAX_LENGTH)?;
+
+    // a day of margin for the users ahead of the server timezone
+    let tomorrow = chrono::Local::now().date_naive().succ_opt().unwrap_or(NaiveDate::MAX);
+    check_not_in_future("Date", occurred_on, tomorrow)?;
+
+    let expense = Expense::get(expense_id, &pool).await.ok_or_else(|| {
+        log!("fn: edit_expense() - expense {} not found", expense_id);
+        ServerFnError::ServerError("Expense not found".to_string())
+    })?;
+
+    // members can only manage their own expenses, admins and owners all of them
+    let user = require_author_or_role(&expense.room_id, expense.paid_by, Role::Admin, &pool, &auth).await?;
+
+    // the items must keep summing to the total
+    if let SplitMethod::Itemized(_) = &expense.split_method {
+        if amount_cents != expense.amount_cents {
+            log!("fn: edit_expense() - cannot change the amount of itemized expense {}", expense_id);
+            return Err(ServerFnError::ServerError(
+                "The amount of an itemized expense cannot be changed".to_string(),
+            ));
+        }
+    }
+
+    log!("fn: edit_expense() - updating expense {}", expense_id);
+
+    // the values before the edit, to undo it
+    let snapshot = ExpenseSnapshot {
+        title: expense.title.clone(),
+        description: expense.description.clone(),
+        amount_cents: expense.amount_cents,
+        split_amounts: match &expense.split_method {
+            SplitMethod::ExactCents(amounts) => amounts.clone(),
+            _ => vec![],
+        },
+        occurred_on: Some(expense.occurred_on),
+    };
+
+    let mut tx = pool.begin().await?;
+
+    sqlx::query("UPDATE expense SET title = ?, description = ?, amount_cents = ?, occurred_on = ? WHERE id = ?")
+        .bind(&title)
+        .bind(&description)
+        .bind(amount_cents)
+        .bind(occurred_on.to_string())
+        .bind(expense_id)
+        .execute(&mut *tx)
+        .await?;
+
+    // exact amounts are scaled to the new total, the other methods don't store cents
+    if let SplitMethod::ExactCents(amounts) = &expense.split_method {
+        if amount_cents != expense.amount_cents && expense.amount_cents > 0 {
+            let mut shares: Vec<(i64, i64)> = amounts
+                .iter()
+                .map(|(user_id, amount)| (*user_id, amount * amount_cents / expense.amount_cents))
+                .collect();
+            shares.sort_by(|a, b| a.0.cmp(&b.0));
+            distribute_remainder(&mut shares, amount_cents);
+
+            for (user_id, amount) in shares {
+                sqlx::query("UPDATE expense_split SET amount_cents = ? WHERE expense_id = ? AND user_id = ?")
+                    .bind(amount)
+                    .bind(expense_id)
+                    .bind(user_id)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+        }
+    }
+
+    tx.commit().await?;
+
+    let expense = Expense::get(expense_id, &pool)
+        .await
+        .ok_or_else(|| ServerFnError::ServerError("Expense not found".to_string()))?;
+
+    log!("fn: edit_expense() - updated expense: {:?}", expense);
+
+    log_activity_with_details(
+        &pool,
+        &expense.room_id,
+        user.id,
+        ActivityAction::ExpenseEdited,
+        expense_id,
+        serde_json::to_string(&snapshot).ok(),
+    )
+    .await?;
+
+    Ok(expense)
+}
+
+// Imports rows in the format `date,title,amount,paid_by` where the date is
+// YYYY-MM-DD, the amount is a decimal like 12.34 and paid_by is a username.
+#[server(ImportExpensesCsv, "/api")]
+pub async fn import_expenses_csv(room_id: String, csv: String) -> Result<usize, ServerFnError> {
+    use crate::state::{auth, pool};
+    use crate::utils::money::{parse_cents, validate_amount};
+    use chrono::NaiveDate;
+    use leptos::logging::log;
+    use std::collections::HashMap;
+
+    let pool = pool()?;
+    let auth = auth()?;
+
+    let user = auth
+        .current_user
+        .ok_or_else(|| ServerFnError::ServerError("User not logged in".to_string()))?;
+
+    let members: HashMap<String, i64> = sqlx::query_as::<_, (String, i64)>(
+        "SELECT lower(user.username), user.id FROM user_room JOIN user ON user_room.user_id = user.id WHERE user_room.room_id = ?",
+    )
+    .bind(&room_id)
+    .fetch_all(&pool)
+    .await?
+    .into_iter()
+    .collect();
+
+    if !members.values().any(|id| *id == user.id) {
+        log!("fn: import_expenses_csv() - user {} is not in room {}", user.id, room_id);
+        return Err(ServerFnError::ServerError(
+            "You are not a member of this room".to_string(),
+        ));
+    }
+
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .trim(csv::Trim::All)
+        .from_reader(csv.as_bytes());
+
+    let mut tx = pool.begin().await?;
+    let mut imported = 0;
+
+    for (i, record) in reader.records().enumerate() {
+        let line = i + 1;
+        let row_error = |message: &str| {
+            log!("fn: import_expenses_csv() - line {}: {}", line, message);
+            ServerFnError::ServerError(format!("Line {}: {}", line, message))
+        };
+
+        let record = record.map_err(|_| row_error("malformed row"))?;
+
+        // the header row is optional
+        if line == 1 && record.get(0).is_some_and(|x| x.eq_ignore_ascii_case("date")) {
+            continue;
+        }
+
+        if record.len() != 4 {
+            return Err(row_error("expected 4 columns: date,title,amount,paid_by"));
+        }
+
+        let date = NaiveDate::parse_from_str(&record[0], "%Y-%m-%d")
+            .map_err(|_| row_error("invalid date"))?;
+
+        let title = record[1].to_string();
+        if title.is_empty() {
+            return Err(row_error("title is required"));
+        }
+
+        let amount_cents = parse_cents(&record[2])
+            .filter(|amount| validate_amount(*amount).is_ok())
+            .ok_or_else(|| row_error("amount must be a positive number, not too large"))?;
+
+        let paid_by = *members
+            .get(&record[3].to_lowercase())
+            .ok_or_else(|| row_error("unknown user or not a member of the room"))?;
+
+        sqlx::query(
+            "INSERT INTO expense (paid_by, amount_cents, title, room_id, created_at, occurred_on) VALUES (?, ?, ?, ?, ?, ?)",
+        )
+        .bind(paid_by)
+        .bind(amount_cents)
+        .bind(&title)
+        .bind(&room_id)
+        .bind(date.and_hms_opt(0, 0, 0))
+        .bind(date.to_string())
+        .execute(&mut *tx)
+        .await?;
+
+        imported += 1;
+    }
+
+    tx.commit().await?;
+
+    log!("fn: import_expenses_csv() - imported {} expenses", imported);
+
+    Ok(imported)
+}
+
+// Every row is validated before anything is inserted, a single invalid row
+// rejects the whole batch. Returns the ids in the order of the rows.
+#[server(CreateExpensesBatch, "/api")]
+pub async fn create_expenses_batch(room_id: String, expenses: Vec<NewExpense>) -> Result<Vec<i64>, ServerFnError> {
+    use crate::error_template::AppError;
+    use crate::models::activity::{log_activity, ActivityAction};
+    use crate::models::room::Room;
+    use crate::services::authz::require_member;
+    use crate::state::{auth, pool, publish_room_event, room_events::RoomEvent, time_server_fn};
+    use crate::utils::money::validate_amount;
+    use crate::utils::validation::{check_max_length, DESCRIPTION_MAX_LENGTH, TITLE_MAX_LENGTH};
+    use leptos::logging::log;
+
+    const MAX_BATCH_SIZE: usize = 500;
+
+    let _timer = time_server_fn("create_expenses_batch");
+
+    let pool = pool()?;
+    let user = require_member(&room_id, &pool, &auth()?).await?;
+
+    let members = Room::member_ids(&room_id, &pool).await?;
+
+    if expenses.len() > MAX_BATCH_SIZE {
+        return Err(AppError::Validation(format!(
+            "At most {} expenses can be created at once",
+            MAX_BATCH_SIZE
+        ))
+        .into());
+    }
+
+    for (index, expense) in expenses.iter().enumerate() {
+        let row_error = |message: String| {
+            log!("fn: create_expenses_batch() - expense {}: {}", index, message);
+            AppError::Validation(format!("Expense {}: {}", index, message))
+        };
+
+        if expense.title.trim().is_empty() {
+            return Err(row_error("title is required".to_string()).into());
+        }
+
+        validate_amount(expense.amount_cents).map_err(|error| row_error(error.to_string()))?;
+
+        check_max_length("Title", expense.title.trim(), TITLE_MAX_LENGTH)
+            .map_err(|error| row_error(error.to_string()))?;
+
+        check_max_length("Description", expense.description.as_deref().unwrap_or_default(), DESCRIPTION_MAX_LENGTH)
+            .map_err(|error| row_error(error.to_string()))?;
+
+        let paid_by = expense.paid_by.unwrap_or(user.id);
+        if !members.contains(&paid_by) || expense.participants.iter().any(|user_id| !members.contains(user_id)) {
+            return Err(row_error("every user must be a member of the room".to_string()).into());
+        }
+
+        let mut participants = expense.participants.clone();
+        participants.sort_unstable();
+        participants.dedup();
+        if participants.len() != expense.participants.len() {
+            return Err(row_error("a participant is listed twice".to_string()).into());
+        }
+    }
+
+    let base_currency: String = sqlx::query_scalar("SELECT base_currency FROM room WHERE id = ?")
+        .bind(&room_id)
+        .fetch_one(&pool)
+        .await?;
+
+    let mut tx = pool.begin().await?;
+    let mut expense_ids = Vec::with_capacity(expenses.len());
+
+    for expense in &expenses {
+        let expense_id: i64 = sqlx::query_scalar(
+            "INSERT INTO expense (paid_by, amount_cents, title, description, room_id, currency, category) VALUES (?, ?, ?, ?, ?, ?, ?) RETURNING id",
+        )
+        .bind(expense.paid_by.unwrap_or(user.id))
+        .bind(expense.amount_cents)
+        .bind(expense.title.trim())
+        .bind(&expense.description)
+        .bind(&room_id)
+        .bind(&base_currency)
+        .bind(expense.category.to_string())
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let participants = if expense.participants.is_empty() {
+            &members
+        } else {
+            &expense.participants
+        };
+
+        for user_id in participants {
+            sqlx::query("INSERT INTO expense_split (expense_id, user_id) VALUES (?, ?)")
+                .bind(expense_id)
+                .bind(user_id)
+                .execute(&mut *tx)
+                .await?;
+
+            sqlx::query("INSERT INTO user_expense (user_id, expense_id) VALUES (?, ?)")
+                .bind(user_id)
+                .bind(expense_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        expense_ids.push(expense_id);
+    }
+
+    tx.commit().await?;
+
+    log!("fn: create_expenses_batch() - created {} expenses in room {}", expense_ids.len(), room_id);
+
+    for expense_id in &expense_ids {
+        log_activity(&pool, &room_id, user.id, ActivityAction::ExpenseCreated, *expense_id).await?;
+        publish_room_event(&room_id, RoomEvent::ExpenseCreated { expense_id: *expense_id });
+    }
+
+    Ok(expense_ids)
+}
+
+#[server(CreateRecurringExpense, "/api")]
+pub async fn create_recurring_expense(
+    room_id: String,
+    title: String,
+    amount_cents: i64,
+    interval: Interval,
+    first_due: NaiveDate,
+) -> Result<i64, ServerFnError> {
+    use crate::error_template::AppError;
+    use crate::services::authz::require_member;
+    use crate::state::{auth, pool};
+    use crate::utils::money::validate_amount;
+    use crate::utils::validation::{check_max_length, check_not_in_past, TITLE_MAX_LENGTH};
+    use chrono::Months;
+    use leptos::logging::log;
+
+    let pool = pool()?;
+    let user = require_member(&room_id, &pool, &auth()?).await?;
+
+    validate_amount(amount_cents).map_err(|error| {
+        log!("fn: create_recurring_expense() - invalid amount: {}", amount_cents);
+        error
+    })?;
+
+    check_max_length("Title", &title, TITLE_MAX_LENGTH)?;
+
+    let today = chrono::Local::now().date_naive();
+    check_not_in_past("First due date", first_due, today)?;
+
+    // every due date is computed from this one, it must be a real schedule
+    if today.checked_add_months(Months::new(12)).is_some_and(|limit| first_due > limit) {
+        log!("fn: create_recurring_expense() - first due date too far: {}", first_due);
+        return Err(AppError::Validation("First due date must be within a year".to_string()).into());
+    }
+
+    let id: i64 = sqlx::query_scalar(
+        "INSERT INTO recurring_expense (room_id, title, amount_cents, paid_by, interval, first_due, next_due) VALUES (?, ?, ?, ?, ?, ?, ?) RETURNING id",
+    )
+    .bind(&room_id)
+    .bind(&title)
+    .bind(amount_cents)
+    .bind(user.id)
+    .bind(interval.as_str())
+    .bind(first_due)
+    .bind(first_due)
+    .fetch_one(&pool)
+    .await?;
+
+    log!("fn: create_recurring_expense() - added recurring expense: {}", id);
+
+    Ok(id)
+}
+
+#[server(CancelRecurringExpense, "/api")]
+pub async fn cancel_recurring_expense(id: i64) -> Result<(), ServerFnError> {
+    use crate::models::room::Role;
+    use crate::services::authz::require_author_or_role;
+    use crate::state::{auth, pool};
+    use leptos::logging::log;
+
+    let pool = pool()?;
+    let auth = auth()?;
+
+    let recurring: Option<(String, i64)> =
+        sqlx::query_as("SELECT room_id, paid_by FROM recurring_expense WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&pool)
+            .await?;
+
+    let (room_id, paid_by) = recurring.ok_or_else(|| {
+        log!("fn: cancel_recurring_expense() - recurring expense {} not found", id);
+        ServerFnError::ServerError("Recurring expense not found".to_string())
+    })?;
+
+    // members can only manage their own expenses, admins and owners all of them
+    require_author_or_role(&room_id, paid_by, Role::Admin, &pool, &auth).await?;
+
+    sqlx::query("DELETE FROM recurring_expense WHERE id = ?")
+        .bind(id)
+        .execute(&pool)
+        .await?;
+
+    Ok(())
+}
+
+#[server(GetMonthlySummary, "/api")]
+pub async fn get_monthly_summary(
+    room_id: String,
+    year: i32,
+    month: u32,
+) -> Result<Vec<(i64, i64)>, ServerFnError> {
+    use crate::services::authz::require_member;
+    use crate::state::{auth, pool};
+    use crate::utils::time::month_bounds;
+    use leptos::logging::log;
+
+    let pool = pool()?;
+    require_member(&room_id, &pool, &auth()?).await?;
+
+    let (from, to) = month_bounds(year, month)
+        .ok_or_else(|| ServerFnError::ServerError("Invalid month".to_string()))?;
+
+    log!("fn: get_monthly_summary() - getting totals between {} and {}", from, to);
+
+    let totals = Expense::totals_paid_between(&room_id, from.date(), to.date(), &pool).await?;
+
+    Ok(totals)
+}
+
+// One point per bucket, the empty ones included, to be drawn as a sparkline.
+#[server(GetSpendTimeseries, "/api")]
+pub async fn get_spend_timeseries(room_id: String, bucket: Bucket) -> Result<Vec<(NaiveDate, i64)>, ServerFnError> {
+    use crate::services::authz::require_member;
+    use crate::state::{auth, pool};
+    use leptos::logging::log;
+
+    let pool = pool()?;
+    require_member(&room_id, &pool, &auth()?).await?;
+
+    let totals = Expense::spend_by_bucket(&room_id, bucket, &pool).await?;
+
+    log!("fn: get_spend_timeseries() - {} buckets with expenses", totals.len());
+
+    Ok(bucket.fill_gaps(totals))
+}
+
+#[server(GetCurrencyBreakdown, "/api")]
+pub async fn get_currency_breakdown(room_id: String) -> Result<CurrencyBreakdown, ServerFnError> {
+    use crate::models::room::Room;
+    use crate::services::authz::require_member;
+    use crate::state::{auth, pool};
+    use crate::utils::money::Currency;
+    use leptos::logging::log;
+
+    let pool = pool()?;
+    require_member(&room_id, &pool, &auth()?).await?;
+
+    let room = Room::get(&room_id, &pool)
+        .await?
+        .ok_or_else(|| ServerFnError::ServerError("Room not found".to_string()))?;
+
+    let totals = Expense::totals_by_currency(&room_id, &pool).await?;
+
+    log!("fn: get_currency_breakdown() - {} currencies in room {}", totals.len(), room_id);
+
+    Ok(CurrencyBreakdown {
+        base_total_cents: totals.iter().map(|(_, _, base_total)| base_total).sum(),
+        by_currency: totals.into_iter().map(|(currency, total, _)| (currency, total)).collect(),
+        base_currency: room.base_currency.parse().unwrap_or(Currency::USD),
+    })
+}
+
+// The final accounting of the room, to be shared as a file.
+#[server(ExportGroupPdf, "/api")]
+pub async fn export_group_pdf(room_id: String) -> Result<Vec<u8>, ServerFnError> {
+    use crate::services::authz::require_member;
+    use crate::services::report::room_report_pdf;
+    use crate::state::{auth, pool};
+
+    let pool = pool()?;
+    require_member(&room_id, &pool, &auth()?).await?;
+
+    room_report_pdf(&room_id, &pool).await
+}
+
+#[server(AddComment, "/api")]
+pub async fn add_comment(expense_id: i64, body: String) -> Result<i64, ServerFnError> {
+    use crate::services::authz::require_member;
+    use crate::state::{auth, pool};
+    use crate::utils::validation::{check_max_length, COMMENT_MAX_LENGTH};
+    use leptos::logging::log;
+
+    let pool = pool()?;
+    let auth = auth()?;
+
+    let body = body.trim();
+    if body.is_empty() {
+        return Err(ServerFnError::ServerError(
+            "Comment cannot be empty".to_string(),
+        ));
+    }
+
+    check_max_length("Comment", body, COMMENT_MAX_LENGTH).map_err(|error| {
+        log!("fn: add_comment() - comment too long");
+        error
+    })?;
+
+    let expense = Expense::get(expense_id, &pool)
+        .await
+        .ok_or_else(|| ServerFnError::ServerError("Expense not found".to_string()))?;
+
+    let user = require_member(&expense.room_id, &pool, &auth).await?;
+
+    let comment_id = Comment::create(expense_id, user.id, body, &pool).await?;
+
+    log!("fn: add_comment() - added comment: {}", comment_id);
+
+    Ok(comment_id)
+}
+
+#[server(GetComments, "/api")]
+pub async fn get_comments(expense_id: i64) -> Result<Vec<Comment>, ServerFnError> {
+    use crate::services::authz::require_member;
+    use crate::state::{auth, pool};
+
+    let pool = pool()?;
+    let auth = auth()?;
+
+    let expense = Expense::get(expense_id, &pool)
+        .await
+        .ok_or_else(|| ServerFnError::ServerError("Expense not found".to_string()))?;
+
+    require_member(&expense.room_id, &pool, &auth).await?;
+
+    let comments = Comment::list_for_expense(expense_id, &pool).await?;
+
+    Ok(comments)
+}
+
+#[server(AddTag, "/api")]
+pub async fn add_tag(expense_id: i64, tag: String) -> Result<(), ServerFnError> {
+    use crate::services::authz::require_member;
+    use crate::state::{auth, pool};
+    use crate::utils::validation::{check_max_length, TAG_MAX_LENGTH};
+    use leptos::logging::log;
+
+    let pool = pool()?;
+    let auth = auth()?;
+
+    let tag = Tag::normalize(&tag);
+    if tag.is_empty() {
+        return Err(ServerFnError::ServerError(
+            "Tag cannot be empty".to_string(),
+        ));
+    }
+
+    check_max_length("Tag", &tag, TAG_MAX_LENGTH)?;
+
+    let expense = Expense::get(expense_id, &pool)
+        .await
+        .ok_or_else(|| ServerFnError::ServerError("Expense not found".to_string()))?;
+
+    require_member(&expense.room_id, &pool, &auth).await?;
+
+    Tag::add_to_expense(expense_id, &tag, &pool).await?;
+
+    log!("fn: add_tag() - tagged expense {} with {:?}", expense_id, tag);
+
+    Ok(())
+}
+
+#[server(RemoveTag, "/api")]
+pub async fn remove_tag(expense_id: i64, tag: String) -> Result<(), ServerFnError> {
+    use crate::services::authz::require_member;
+    use crate::state::{auth, pool};
+    use leptos::logging::log;
+
+    let pool = pool()?;
+    let auth = auth()?;
+
+    let expense = Expense::get(expense_id, &pool)
+        .await
+        .ok_or_else(|| ServerFnError::ServerError("Expense not found".to_string()))?;
+
+    require_member(&expense.room_id, &pool, &auth).await?;
+
+    if !Tag::remove_from_expense(expense_id, &Tag::normalize(&tag), &pool).await? {
+        log!("fn: remove_tag() - expense {} is not tagged with {:?}", expense_id, tag);
+    }
+
+    Ok(())
+}
+
+#[server(GetExpensesByTag, "/api")]
+pub async fn get_expenses_by_tag(room_id: String, tag: String) -> Result<Vec<Expense>, ServerFnError> {
+    use crate::services::authz::require_member;
+    use crate::state::{auth, pool};
+
+    let pool = pool()?;
+    require_member(&room_id, &pool, &auth()?).await?;
+
+    let expenses = Expense::list_by_tag(&room_id, &Tag::normalize(&tag), &pool).await?;
+
+    Ok(expenses)
+}
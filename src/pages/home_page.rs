@@ -1,11 +1,119 @@
 use leptos::*;
 use leptos_router::*;
+use serde::{Deserialize, Serialize};
+
+use crate::i18n::use_lang;
+use crate::models::{expense::Expense, reminder::Reminder};
+use crate::utils::money::{format_cents, Currency};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DashboardData {
+    pub total_owed_to_me: i64,
+    pub total_i_owe: i64,
+    pub group_count: i64,
+    pub recent_expenses: Vec<Expense>,
+}
+
+// Everything the home page needs about the user, across all their rooms.
+#[server(GetDashboard, "/api")]
+pub async fn get_dashboard() -> Result<DashboardData, ServerFnError> {
+    use crate::models::room::Room;
+    use crate::services::balance::room_balances;
+    use crate::state::{auth, pool};
+    use leptos::logging::log;
+
+    const RECENT_EXPENSES: i64 = 10;
+
+    let pool = pool()?;
+    let auth = auth()?;
+
+    let user = auth
+        .current_user
+        .ok_or_else(|| ServerFnError::ServerError("User not logged in".to_string()))?;
+
+    let rooms = Room::list_for_user(user.id, &pool).await?;
+
+    let mut total_owed_to_me = 0;
+    let mut total_i_owe = 0;
+
+    for room in &rooms {
+        let balance = room_balances(&room.id, &pool)
+            .await?
+            .get(&user.id)
+            .copied()
+            .unwrap_or_default();
+
+        if balance > 0 {
+            total_owed_to_me += balance;
+        } else {
+            total_i_owe -= balance;
+        }
+    }
+
+    let recent_expenses = Expense::list_recent_for_user(user.id, RECENT_EXPENSES, &pool).await?;
+
+    log!("fn: get_dashboard() - {} rooms for user {}", rooms.len(), user.id);
+
+    Ok(DashboardData {
+        total_owed_to_me,
+        total_i_owe,
+        group_count: rooms.len() as i64,
+        recent_expenses,
+    })
+}
+
+#[server(GetReminders, "/api")]
+pub async fn get_reminders() -> Result<Vec<Reminder>, ServerFnError> {
+    use crate::state::{auth, pool};
+    use leptos::logging::log;
+
+    let pool = pool()?;
+    let auth = auth()?;
+
+    let user = auth
+        .current_user
+        .ok_or_else(|| ServerFnError::ServerError("User not logged in".to_string()))?;
+
+    let reminders = Reminder::list_for_user(user.id, &pool).await?;
+
+    log!("fn: get_reminders() - {} reminders for user {}", reminders.len(), user.id);
+
+    Ok(reminders)
+}
 
 #[component]
 pub fn HomePage() -> impl IntoView {
+    let lang = use_lang();
+    let reminders = create_resource(|| (), |_| get_reminders());
+
+    let reminders_view = move || {
+        reminders.get().and_then(Result::ok).map(|reminders| {
+            reminders
+                .into_iter()
+                .map(|reminder| {
+                    let amount = format_cents(
+                        reminder.amount_cents,
+                        reminder.base_currency.parse().unwrap_or(Currency::USD),
+                        lang.get(),
+                    );
+
+                    view! {
+                        <div class="alert alert-warning">
+                            <span>"You owe " {amount} " in " {reminder.room_name} " for over a week"</span>
+                            <A href=format!("room/{}", reminder.room_id)>
+                                <button class="btn btn-sm">"Settle up"</button>
+                            </A>
+                        </div>
+                    }
+                })
+                .collect_view()
+        })
+    };
+
     view! {
         <div class="flex h-screen justify-center items-center">
             <div class="flex flex-col space-y-6 w-80">
+                <Transition fallback=|| ()>{reminders_view}</Transition>
                 <A href="join">
                     <button class="btn btn-primary btn-lg w-full">
                         <b>ENTER</b>
@@ -19,6 +127,10 @@ pub fn HomePage() -> impl IntoView {
                         a new room
                     </button>
                 </A>
+
+                <A href="settings">
+                    <button class="btn btn-ghost w-full">"Settings"</button>
+                </A>
             </div>
         </div>
     }
@@ -5,6 +5,7 @@ use crate::components::{
     input_component::{InputComponent, InputParams, InputType},
     notification_component::{NotificationComponent, NotificationParams, NotificationType},
 };
+use crate::models::room::PendingMember;
 
 use cfg_if::cfg_if;
 
@@ -29,10 +30,18 @@ pub async fn join_room(room_name: String) -> Result<(), ServerFnError> {
 
             // TODO: check max_participants
 
+            if room.approval_required && !Room::is_member(&room.id, user_id, &pool).await? {
+                log!("fn: join_room() - waiting for the approval of an admin");
+                Room::add_pending_member(&room.id, user_id, &pool).await?;
+
+                leptos_axum::redirect("/");
+                return Ok(());
+            }
+
             join_room_sql(user_id, room.id.clone(), &pool).await?;
 
-            log!("fn: join_room() - redirecting to /room/{}", room.id);
-            leptos_axum::redirect(&format!("/room/{}", room.id));
+            log!("fn: join_room() - redirecting to /room/{}", room.slug);
+            leptos_axum::redirect(&format!("/room/{}", room.slug));
             Ok(())
         }
         None => {
@@ -42,8 +51,161 @@ pub async fn join_room(room_name: String) -> Result<(), ServerFnError> {
     }
 }
 
+#[server(CreateInvite, "/api")]
+pub async fn create_invite(room_id: String) -> Result<String, ServerFnError> {
+    const INVITE_MAX_USES: i64 = 10;
+
+    let pool = pool()?;
+    let auth = auth()?;
+
+    let user = auth
+        .current_user
+        .ok_or_else(|| ServerFnError::ServerError("User not logged in".to_string()))?;
+
+    let room_owner: Option<i64> = sqlx::query_scalar("SELECT owner FROM room WHERE id = ?")
+        .bind(&room_id)
+        .fetch_optional(&pool)
+        .await?;
+
+    if room_owner != Some(user.id) {
+        log!("fn: create_invite() - user {} is not the owner of room {}", user.id, room_id);
+        return Err(ServerFnError::ServerError(
+            "Only the room owner can create invites".to_string(),
+        ));
+    }
+
+    let token = uuid::Uuid::new_v4().to_string();
+
+    log!("fn: create_invite() - creating invite for room {}", room_id);
+    sqlx::query(
+        "INSERT INTO room_invite (token, room_id, created_by, expires_at, max_uses) VALUES (?, ?, ?, datetime('now', '+7 days'), ?)",
+    )
+    .bind(&token)
+    .bind(&room_id)
+    .bind(user.id)
+    .bind(INVITE_MAX_USES)
+    .execute(&pool)
+    .await?;
+
+    Ok(token)
+}
+
+#[server(AcceptInvite, "/api")]
+pub async fn accept_invite(token: String) -> Result<(), ServerFnError> {
+    let pool = pool()?;
+    let auth = auth()?;
+
+    let user = auth
+        .current_user
+        .ok_or_else(|| ServerFnError::ServerError("User not logged in".to_string()))?;
+
+    let mut tx = pool.begin().await?;
+
+    let invite: Option<(String, bool, bool, bool)> = sqlx::query_as(
+        "SELECT room_invite.room_id, room_invite.expires_at > datetime('now'), room_invite.uses < room_invite.max_uses, room.approval_required FROM room_invite JOIN room ON room.id = room_invite.room_id WHERE room_invite.token = ?",
+    )
+    .bind(&token)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let Some((room_id, is_not_expired, has_uses_left, approval_required)) = invite else {
+        log!("fn: accept_invite() - unknown invite");
+        return Err(ServerFnError::ServerError("Invalid invite".to_string()));
+    };
+
+    let is_member = sqlx::query("SELECT 1 FROM user_room WHERE room_id = ? AND user_id = ?")
+        .bind(&room_id)
+        .bind(user.id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .is_some();
+
+    // accepting an invite twice doesn't use it again
+    if !is_member {
+        if !is_not_expired {
+            log!("fn: accept_invite() - invite expired");
+            return Err(ServerFnError::ServerError("Invite expired".to_string()));
+        }
+
+        if !has_uses_left {
+            log!("fn: accept_invite() - invite has no uses left");
+            return Err(ServerFnError::ServerError(
+                "Invite has already been used too many times".to_string(),
+            ));
+        }
+
+        // a request already waiting doesn't use the invite again either
+        let is_added = if approval_required {
+            log!("fn: accept_invite() - user {} waits to be approved in room {}", user.id, room_id);
+            sqlx::query("INSERT OR IGNORE INTO pending_member (room_id, user_id) VALUES (?, ?)")
+                .bind(&room_id)
+                .bind(user.id)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected()
+                > 0
+        } else {
+            log!("fn: accept_invite() - adding user {} to room {}", user.id, room_id);
+            sqlx::query("INSERT INTO user_room (room_id, user_id) VALUES (?, ?)")
+                .bind(&room_id)
+                .bind(user.id)
+                .execute(&mut *tx)
+                .await?;
+
+            true
+        };
+
+        if is_added {
+            sqlx::query("UPDATE room_invite SET uses = uses + 1 WHERE token = ?")
+                .bind(&token)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        if approval_required {
+            tx.commit().await?;
+
+            log!("fn: accept_invite() - redirecting to \"/\"");
+            leptos_axum::redirect("/");
+            return Ok(());
+        }
+    }
+
+    tx.commit().await?;
+
+    log!("fn: accept_invite() - redirecting to /room/{}", room_id);
+    leptos_axum::redirect(&format!("/room/{}", room_id));
+    Ok(())
+}
+
+// Only the admins and the owners see who is waiting.
+#[server(GetPendingMembers, "/api")]
+pub async fn get_pending_members(room_id: String) -> Result<Vec<PendingMember>, ServerFnError> {
+    use crate::models::room::Role;
+    use crate::services::authz::require_role;
+
+    let pool = pool()?;
+    require_role(&room_id, Role::Admin, &pool, &auth()?).await?;
+
+    let pending_members = Room::pending_members(&room_id, &pool).await?;
+
+    log!("fn: get_pending_members() - {} users waiting in room {}", pending_members.len(), room_id);
+    Ok(pending_members)
+}
+
+#[server(ApproveMember, "/api")]
+pub async fn approve_member(room_id: String, user_id: i64) -> Result<(), ServerFnError> {
+    resolve_pending_member(room_id, user_id, true).await
+}
+
+#[server(RejectMember, "/api")]
+pub async fn reject_member(room_id: String, user_id: i64) -> Result<(), ServerFnError> {
+    resolve_pending_member(room_id, user_id, false).await
+}
+
 cfg_if! {
     if #[cfg(feature = "ssr")] {
+        use crate::error_template::AppError;
         use crate::models::room::Room;
         use crate::state::{auth, pool};
         use leptos::logging::log;
@@ -52,29 +214,22 @@ cfg_if! {
         async fn join_room_sql(user_id: i64, room_id: String, pool: &SqlitePool) -> Result<(), ServerFnError> {
             // TODO: check max_participants
 
-            log!("fn: join_room() - checking if the user is already in the room");
-            let is_user_in_room = sqlx::query!(
-                "SELECT * FROM user_room WHERE room_id = $1 AND user_id = $2",
-                room_id,
-                user_id,
-            )
-            .fetch_optional(pool)
-            .await?;
+            log!("fn: join_room() - adding the user to the room");
+            Room::add_member(&room_id, user_id, pool).await?;
+
+            Ok(())
+        }
+
+        async fn resolve_pending_member(room_id: String, user_id: i64, approved: bool) -> Result<(), ServerFnError> {
+            use crate::models::room::Role;
+            use crate::services::authz::require_role;
+
+            let pool = pool()?;
+            require_role(&room_id, Role::Admin, &pool, &auth()?).await?;
 
-            match is_user_in_room {
-                Some(_) => {
-                    log!("fn: join_room() - user already in the room");
-                }
-                None => {
-                    log!("fn: join_room() - adding the user to the room");
-                    sqlx::query!(
-                        "INSERT INTO user_room (room_id, user_id) VALUES ($1, $2)",
-                        room_id,
-                        user_id,
-                    )
-                    .execute(pool)
-                    .await?;
-                }
+            if !Room::resolve_pending_member(&room_id, user_id, approved, &pool).await? {
+                log!("fn: resolve_pending_member() - user {} is not waiting in room {}", user_id, room_id);
+                return Err(AppError::Validation("This user is not waiting to join the room".to_string()).into());
             }
 
             Ok(())
@@ -105,6 +260,7 @@ pub fn JoinRoomPage() -> impl IntoView {
         NotificationParams {
             message: client_message,
             notification_type: NotificationType::Error,
+            auto_dismiss_ms: None,
         }
     };
 
@@ -1,5 +1,7 @@
 pub mod create_room_page;
 pub mod dashboard_page;
+pub mod expenses;
 pub mod home_page;
 pub mod join_room_page;
+pub mod settings_page;
 pub mod auth;
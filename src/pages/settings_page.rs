@@ -0,0 +1,155 @@
+use crate::{
+    components::notification_component::{use_notifications, NotificationParams, NotificationType},
+    i18n::{use_lang, Lang},
+    pages::auth::{get_user, DeactivateAccount, UpdatePreferences},
+    utils::money::Currency,
+};
+use leptos::*;
+use leptos_router::*;
+
+const CURRENCIES: [Currency; 3] = [Currency::USD, Currency::EUR, Currency::GBP];
+const LOCALES: [(Lang, &str); 2] = [(Lang::En, "English"), (Lang::It, "Italiano")];
+
+#[component]
+pub fn SettingsPage() -> impl IntoView {
+    let action = create_server_action::<UpdatePreferences>();
+    let value = action.value();
+    let notify = use_notifications();
+    let lang = use_lang();
+
+    let deactivate_action = create_server_action::<DeactivateAccount>();
+    let deactivate_value = deactivate_action.value();
+
+    let user = create_resource(move || action.version().get(), move |_| get_user());
+
+    let (preferred_currency, set_preferred_currency) = create_signal(Currency::default().as_str().to_string());
+    let (locale, set_locale) = create_signal(Lang::default().as_str().to_string());
+
+    create_effect(move |_| {
+        if let Some(Ok(Some(user))) = user.get() {
+            set_preferred_currency(user.preferred_currency.clone());
+            set_locale(user.locale.clone());
+        }
+    });
+
+    create_effect(move |_| match value.get() {
+        Some(Ok(_)) => {
+            // the new locale is used right away, without reloading the page
+            if let Some(new_lang) = Lang::from_code(&locale.get_untracked()) {
+                lang.set(new_lang);
+            }
+
+            notify(NotificationParams {
+                message: "Preferences saved".to_string(),
+                notification_type: NotificationType::Success,
+                auto_dismiss_ms: Some(3000),
+            });
+        }
+        Some(Err(err)) => {
+            let server_message = err.to_string();
+            let client_message = server_message.replace("error running server function: ", "");
+
+            notify(NotificationParams {
+                message: client_message,
+                notification_type: NotificationType::Error,
+                auto_dismiss_ms: None,
+            });
+        }
+        None => {}
+    });
+
+    create_effect(move |_| {
+        if let Some(Err(err)) = deactivate_value.get() {
+            let server_message = err.to_string();
+            let client_message = server_message.replace("error running server function: ", "");
+
+            notify(NotificationParams {
+                message: client_message,
+                notification_type: NotificationType::Error,
+                auto_dismiss_ms: None,
+            });
+        }
+    });
+
+    view! {
+        <div class="flex flex-col h-screen justify-center items-center gap-12">
+            <ActionForm action=action class="space-y-3 w-80">
+                <p class="text-3xl font-bold mb-6">"Preferences"</p>
+
+                <div class="form-control w-full">
+                    <label class="label">
+                        <span class="label-text">"Default currency"</span>
+                    </label>
+                    <select
+                        class="select select-bordered w-full"
+                        name="preferred_currency"
+                        on:change=move |ev| set_preferred_currency(event_target_value(&ev))
+                    >
+                        {CURRENCIES
+                            .into_iter()
+                            .map(|currency| {
+                                view! {
+                                    <option
+                                        value=currency.as_str()
+                                        selected=move || preferred_currency() == currency.as_str()
+                                    >
+                                        {format!("{} ({})", currency.as_str(), currency.symbol())}
+                                    </option>
+                                }
+                            })
+                            .collect_view()}
+                    </select>
+                </div>
+
+                <div class="form-control w-full">
+                    <label class="label">
+                        <span class="label-text">"Language and number format"</span>
+                    </label>
+                    <select
+                        class="select select-bordered w-full"
+                        name="locale"
+                        on:change=move |ev| set_locale(event_target_value(&ev))
+                    >
+                        {LOCALES
+                            .into_iter()
+                            .map(|(lang, name)| {
+                                view! {
+                                    <option value=lang.as_str() selected=move || locale() == lang.as_str()>
+                                        {name}
+                                    </option>
+                                }
+                            })
+                            .collect_view()}
+                    </select>
+                </div>
+
+                <button class="btn btn-primary btn-lg w-full" type="submit">
+                    <b>SAVE</b>
+                </button>
+
+                <A href="/">
+                    <button class="btn btn-ghost w-full" type="button">"Back"</button>
+                </A>
+            </ActionForm>
+
+            <ActionForm action=deactivate_action class="space-y-3 w-80">
+                <p class="text-xl font-bold">"Deactivate account"</p>
+                <p class="text-sm">
+                    "You won't be able to log in until you reactivate it. Your expenses stay in the groups."
+                </p>
+
+                <input
+                    class="input input-bordered w-full"
+                    type="password"
+                    name="password"
+                    placeholder="Password"
+                    required
+                />
+
+                <button class="btn btn-error btn-outline w-full" type="submit">
+                    "DEACTIVATE"
+                </button>
+            </ActionForm>
+        </div>
+    }
+}
@@ -0,0 +1,92 @@
+use cfg_if::cfg_if;
+
+cfg_if! {
+    if #[cfg(feature = "ssr")] {
+        use sqlx::sqlite::SqlitePool;
+
+        use crate::error_template::AppError;
+        use crate::models::room::{Role, Room};
+        use crate::models::user::User;
+        use crate::pages::auth::AuthSession;
+
+        // The current user, if they are a member of the room.
+        pub async fn require_member(room_id: &str, pool: &SqlitePool, auth: &AuthSession) -> Result<User, AppError> {
+            return require_role(room_id, Role::Member, pool, auth).await;
+        }
+
+        // The current user, if their role in the room is at least `min_role`.
+        pub async fn require_role(room_id: &str, min_role: Role, pool: &SqlitePool, auth: &AuthSession) -> Result<User, AppError> {
+            return check_role(room_id, auth.current_user.clone(), min_role, pool).await;
+        }
+
+        // `require_role` without the session, `user` is None for the anonymous requests.
+        pub async fn check_role(room_id: &str, user: Option<User>, min_role: Role, pool: &SqlitePool) -> Result<User, AppError> {
+            let user = user.ok_or(AppError::Unauthorized)?;
+
+            match Room::user_role(room_id, user.id, pool).await? {
+                Some(role) if role >= min_role => Ok(user),
+                role => {
+                    log::info!("fn: check_role() - user {} is {:?} in room {}, needs {:?}", user.id, role, room_id, min_role);
+                    Err(AppError::Unauthorized)
+                }
+            }
+        }
+
+        // The current user, if they are the author of the entity (e.g. who paid
+        // an expense) and still in the room, or their role is at least `min_role`.
+        pub async fn require_author_or_role(room_id: &str, author_id: i64, min_role: Role, pool: &SqlitePool, auth: &AuthSession) -> Result<User, AppError> {
+            let user = auth.current_user.clone().ok_or(AppError::Unauthorized)?;
+
+            match Room::user_role(room_id, user.id, pool).await? {
+                Some(role) if user.id == author_id || role >= min_role => Ok(user),
+                role => {
+                    log::info!("fn: require_author_or_role() - user {} is {:?} in room {} and not the author", user.id, role, room_id);
+                    Err(AppError::Unauthorized)
+                }
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "ssr"))]
+mod tests {
+    use super::*;
+    use crate::models::room::{Role, Room};
+    use crate::test_utils::{create_room, create_user, test_pool};
+
+    #[tokio::test]
+    async fn rejects_a_non_member() {
+        let pool = test_pool().await;
+        let owner = create_user("owner", &pool).await;
+        let stranger = create_user("stranger", &pool).await;
+        let room = create_room("Trip", &owner, &pool).await;
+
+        let result = check_role(&room.id, Some(stranger), Role::Member, &pool).await;
+        assert!(matches!(result, Err(AppError::Unauthorized)));
+    }
+
+    #[tokio::test]
+    async fn rejects_an_anonymous_user() {
+        let pool = test_pool().await;
+        let owner = create_user("owner", &pool).await;
+        let room = create_room("Trip", &owner, &pool).await;
+
+        let result = check_role(&room.id, None, Role::Member, &pool).await;
+        assert!(matches!(result, Err(AppError::Unauthorized)));
+    }
+
+    #[tokio::test]
+    async fn accepts_a_member_only_up_to_their_role() {
+        let pool = test_pool().await;
+        let owner = create_user("owner", &pool).await;
+        let member = create_user("member", &pool).await;
+        let room = create_room("Trip", &owner, &pool).await;
+        Room::add_member(&room.id, member.id, &pool).await.unwrap();
+
+        let result = check_role(&room.id, Some(member.clone()), Role::Member, &pool).await;
+        assert_eq!(result.unwrap().id, member.id);
+
+        let result = check_role(&room.id, Some(member), Role::Admin, &pool).await;
+        assert!(matches!(result, Err(AppError::Unauthorized)));
+    }
+}
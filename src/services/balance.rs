@@ -0,0 +1,638 @@
+use cfg_if::cfg_if;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::models::{
+    expense::{Expense, ExpenseItem, SplitMethod},
+    settlement,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Settlement {
+    pub from: i64,
+    pub to: i64,
+    pub amount_cents: i64,
+    // the names of the users, filled with `UserLabels` by the server functions
+    pub from_label: String,
+    pub to_label: String,
+}
+
+// The balance with another user, from the point of view of the current
+// user: positive means the other user owes them money.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UserBalance {
+    pub other_user: i64,
+    // the name of the other user, like the labels of `Settlement`
+    pub label: String,
+    pub net_cents: i64,
+}
+
+// What a member paid and owes in a room, in cents of the base currency. The
+// refunds count as negative amounts.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UserStats {
+    pub total_paid: i64,
+    pub total_share: i64,
+    // the expenses paid by the user
+    pub expense_count: i64,
+    pub avg_expense: i64,
+}
+
+// Net balance per user id, in cents: positive means the user is owed money,
+// negative means the user owes money. The values always sum to zero.
+// Recorded settlements are transfers that reduce the debt of who paid.
+pub fn compute_balances(
+    expenses: &[Expense],
+    settlements: &[settlement::Settlement],
+    members: &[i64],
+) -> HashMap<i64, i64> {
+    let mut balances: HashMap<i64, i64> = members.iter().map(|id| (*id, 0)).collect();
+
+    for settlement in settlements {
+        *balances.entry(settlement.from_user).or_insert(0) += settlement.amount_cents;
+        *balances.entry(settlement.to_user).or_insert(0) -= settlement.amount_cents;
+    }
+
+    let mut sorted_members = members.to_vec();
+    sorted_members.sort();
+    sorted_members.dedup();
+
+    if sorted_members.is_empty() {
+        return balances;
+    }
+
+    for expense in expenses {
+        // refunds are split like expenses so that the same amount cancels out exactly
+        let sign = expense.kind.sign();
+
+        *balances.entry(expense.paid_by).or_insert(0) += sign * expense.base_amount_cents();
+
+        for (user_id, share) in expense_shares(expense, &sorted_members) {
+            *balances.entry(user_id).or_insert(0) -= sign * share;
+        }
+    }
+
+    return balances;
+}
+
+// The part of the expense of every participant in the base currency, always
+// positive, also for the refunds. `sorted_members` share the equal splits
+// without participants.
+pub fn expense_shares(expense: &Expense, sorted_members: &[i64]) -> Vec<(i64, i64)> {
+    let amount_cents = expense.base_amount_cents();
+
+    match &expense.split_method {
+        SplitMethod::Equal if !expense.participants.is_empty() => {
+            let mut participants = expense.participants.clone();
+            participants.sort();
+            participants.dedup();
+            split_equally(amount_cents, &participants)
+        }
+        SplitMethod::Equal => split_equally(amount_cents, sorted_members),
+        SplitMethod::Percentage(percentages) => split_by_percentage(amount_cents, percentages),
+        SplitMethod::Shares(shares) => split_by_shares(amount_cents, shares),
+        SplitMethod::ExactCents(amounts) => convert_exact_amounts(amounts, expense.exchange_rate, amount_cents),
+        SplitMethod::Itemized(items) => {
+            let subtotals = sum_items(items);
+            let extras = distribute_proportional(&subtotals, expense.tax_cents + expense.tip_cents);
+
+            let amounts: Vec<(i64, i64)> = subtotals
+                .iter()
+                .map(|(user_id, subtotal)| (*user_id, subtotal + extras.get(user_id).copied().unwrap_or_default()))
+                .collect();
+
+            convert_exact_amounts(&amounts, expense.exchange_rate, amount_cents)
+        }
+    }
+}
+
+// Splits the amount in equal shares, the cents that can't be divided evenly
+// go one each to the first users of the (sorted) list.
+fn split_equally(amount_cents: i64, users: &[i64]) -> Vec<(i64, i64)> {
+    let count = users.len() as i64;
+    let share = amount_cents / count;
+    let remainder = amount_cents % count;
+
+    return users
+        .iter()
+        .enumerate()
+        .map(|(i, user_id)| {
+            if (i as i64) < remainder {
+                (*user_id, share + 1)
+            } else {
+                (*user_id, share)
+            }
+        })
+        .collect();
+}
+
+// Converts the exact amounts to the base currency, any cent lost in the
+// conversion goes to the users with the lowest ids so the shares still sum
+// to the converted total.
+fn convert_exact_amounts(amounts: &[(i64, i64)], exchange_rate: f64, total_cents: i64) -> Vec<(i64, i64)> {
+    let mut shares: Vec<(i64, i64)> = amounts
+        .iter()
+        .map(|(user_id, amount)| (*user_id, (*amount as f64 * exchange_rate).floor() as i64))
+        .collect();
+    shares.sort_by(|a, b| a.0.cmp(&b.0));
+
+    distribute_remainder(&mut shares, total_cents);
+    return shares;
+}
+
+// Total of the items assigned to every user.
+fn sum_items(items: &[ExpenseItem]) -> HashMap<i64, i64> {
+    let mut totals: HashMap<i64, i64> = HashMap::new();
+
+    for item in items {
+        *totals.entry(item.assigned_to).or_insert(0) += item.amount_cents;
+    }
+
+    return totals;
+}
+
+// Splits `extra_cents` (e.g. tax and tip) proportionally to the base amounts,
+// rounding down. The cents lost in the rounding go one each to the users with
+// the lowest ids, so the result always sums to `extra_cents`.
+fn distribute_proportional(base: &HashMap<i64, i64>, extra_cents: i64) -> HashMap<i64, i64> {
+    let total: i64 = base.values().sum();
+
+    let mut user_ids: Vec<i64> = base.keys().copied().collect();
+    user_ids.sort();
+
+    if total <= 0 {
+        return split_equally(extra_cents, &user_ids).into_iter().collect();
+    }
+
+    let mut shares: Vec<(i64, i64)> = user_ids
+        .iter()
+        .map(|user_id| (*user_id, extra_cents * base[user_id] / total))
+        .collect();
+
+    distribute_remainder(&mut shares, extra_cents);
+    return shares.into_iter().collect();
+}
+
+// Applies the percentages to the amount rounding down, the cents lost in the
+// rounding go one each to the users with the lowest ids.
+fn split_by_percentage(amount_cents: i64, percentages: &[(i64, f64)]) -> Vec<(i64, i64)> {
+    let mut sorted_percentages = percentages.to_vec();
+    sorted_percentages.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut shares: Vec<(i64, i64)> = sorted_percentages
+        .iter()
+        .map(|(user_id, percentage)| {
+            (*user_id, (amount_cents as f64 * percentage / 100.0).floor() as i64)
+        })
+        .collect();
+
+    distribute_remainder(&mut shares, amount_cents);
+    return shares;
+}
+
+// Gives everyone `amount_cents * share / total_shares` rounding down, the
+// cents lost in the rounding go one each to the users with the lowest ids.
+fn split_by_shares(amount_cents: i64, shares: &[(i64, u32)]) -> Vec<(i64, i64)> {
+    let total_shares: i64 = shares.iter().map(|(_, share)| *share as i64).sum();
+    if total_shares <= 0 {
+        return vec![];
+    }
+
+    let mut sorted_shares = shares.to_vec();
+    sorted_shares.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut amounts: Vec<(i64, i64)> = sorted_shares
+        .iter()
+        .map(|(user_id, share)| (*user_id, amount_cents * *share as i64 / total_shares))
+        .collect();
+
+    distribute_remainder(&mut amounts, amount_cents);
+    return amounts;
+}
+
+// Gives the cents missing to reach the total one each to the shares, in order.
+// The cents over the total (e.g. percentages summing to 100.01) are taken back
+// one each from the last shares.
+pub(crate) fn distribute_remainder(shares: &mut [(i64, i64)], total_cents: i64) {
+    if shares.is_empty() {
+        return;
+    }
+
+    let count = shares.len();
+    let mut remainder = total_cents - shares.iter().map(|(_, share)| share).sum::<i64>();
+    let mut i = 0;
+
+    while remainder > 0 {
+        shares[i % count].1 += 1;
+        remainder -= 1;
+        i += 1;
+    }
+
+    while remainder < 0 {
+        shares[count - 1 - i % count].1 -= 1;
+        remainder += 1;
+        i += 1;
+    }
+}
+
+// Greedy settle up: the biggest debtor always pays the biggest creditor, until
+// everybody is at zero. Ties are broken by user id to keep the result stable.
+pub fn simplify_debts(balances: &HashMap<i64, i64>) -> Vec<Settlement> {
+    let mut creditors: Vec<(i64, i64)> = balances
+        .iter()
+        .filter(|(_, amount)| **amount > 0)
+        .map(|(user_id, amount)| (*user_id, *amount))
+        .collect();
+
+    let mut debtors: Vec<(i64, i64)> = balances
+        .iter()
+        .filter(|(_, amount)| **amount < 0)
+        .map(|(user_id, amount)| (*user_id, -*amount))
+        .collect();
+
+    let mut settlements = vec![];
+
+    loop {
+        creditors.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        debtors.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+
+        let (Some(creditor), Some(debtor)) = (creditors.first_mut(), debtors.first_mut()) else {
+            break;
+        };
+
+        let amount_cents = creditor.1.min(debtor.1);
+
+        settlements.push(Settlement {
+            from: debtor.0,
+            to: creditor.0,
+            amount_cents,
+            from_label: String::new(),
+            to_label: String::new(),
+        });
+
+        creditor.1 -= amount_cents;
+        debtor.1 -= amount_cents;
+
+        creditors.retain(|(_, amount)| *amount > 0);
+        debtors.retain(|(_, amount)| *amount > 0);
+    }
+
+    return settlements;
+}
+
+// The settle up transfers that involve the user, framed from their point of view.
+pub fn balances_for_user(user_id: i64, settlements: &[Settlement]) -> Vec<UserBalance> {
+    let mut net: HashMap<i64, i64> = HashMap::new();
+
+    for settlement in settlements {
+        if settlement.to == user_id {
+            *net.entry(settlement.from).or_insert(0) += settlement.amount_cents;
+        } else if settlement.from == user_id {
+            *net.entry(settlement.to).or_insert(0) -= settlement.amount_cents;
+        }
+    }
+
+    let mut balances: Vec<UserBalance> = net
+        .into_iter()
+        .filter(|(_, net_cents)| *net_cents != 0)
+        .map(|(other_user, net_cents)| UserBalance {
+            other_user,
+            label: String::new(),
+            net_cents,
+        })
+        .collect();
+    balances.sort_by(|a, b| a.other_user.cmp(&b.other_user));
+
+    return balances;
+}
+
+cfg_if! {
+    if #[cfg(feature = "ssr")] {
+        use sqlx::sqlite::SqlitePool;
+
+        use crate::models::expense::SIGNED_BASE_AMOUNT_SQL;
+        use crate::models::room::Room;
+
+        // Loads everything needed from the database and computes the balances of a room.
+        pub async fn room_balances(room_id: &str, pool: &SqlitePool) -> Result<HashMap<i64, i64>, sqlx::Error> {
+            let expenses = Expense::list_for_room(room_id, pool).await?;
+            let settlements = settlement::Settlement::list_for_room(room_id, pool).await?;
+            let members = Room::member_ids(room_id, pool).await?;
+
+            return Ok(compute_balances(&expenses, &settlements, &members));
+        }
+
+        // The share goes through the same splits as the balances, so that the
+        // percentage and itemized expenses count like they do there.
+        pub async fn user_stats(room_id: &str, user_id: i64, pool: &SqlitePool) -> Result<UserStats, sqlx::Error> {
+            let (total_paid, expense_count, avg_expense): (i64, i64, i64) = sqlx::query_as(&format!(
+                "SELECT
+                    COALESCE(CAST(SUM({signed}) AS INTEGER), 0),
+                    COUNT(*),
+                    COALESCE(CAST(AVG({signed}) AS INTEGER), 0)
+                FROM expense
+                WHERE room_id = ? AND paid_by = ? AND deleted_at IS NULL",
+                signed = SIGNED_BASE_AMOUNT_SQL
+            ))
+            .bind(room_id)
+            .bind(user_id)
+            .fetch_one(pool)
+            .await?;
+
+            let expenses = Expense::list_for_room(room_id, pool).await?;
+            let members = Room::member_ids(room_id, pool).await?;
+
+            let total_share = expenses
+                .iter()
+                .map(|expense| {
+                    let share: i64 = expense_shares(expense, &members)
+                        .into_iter()
+                        .filter(|(id, _)| *id == user_id)
+                        .map(|(_, share)| share)
+                        .sum();
+
+                    expense.kind.sign() * share
+                })
+                .sum();
+
+            return Ok(UserStats {
+                total_paid,
+                total_share,
+                expense_count,
+                avg_expense,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::expense::ExpenseKind;
+
+    fn expense(paid_by: i64, amount_cents: i64, participants: Vec<i64>) -> Expense {
+        Expense {
+            paid_by,
+            amount_cents,
+            participants,
+            ..Expense::default()
+        }
+    }
+
+    #[test]
+    fn splits_among_all_the_members_without_participants() {
+        let balances = compute_balances(&[expense(1, 100, vec![])], &[], &[1, 2, 3]);
+
+        // the cent that can't be divided goes to the lowest id
+        assert_eq!(balances[&1], 66);
+        assert_eq!(balances[&2], -33);
+        assert_eq!(balances[&3], -33);
+        assert_eq!(balances.values().sum::<i64>(), 0);
+    }
+
+    #[test]
+    fn splits_ten_dollars_among_three() {
+        let expense = expense(1, 1000, vec![1, 2, 3]);
+
+        assert_eq!(expense_shares(&expense, &[1, 2, 3]), vec![(1, 334), (2, 333), (3, 333)]);
+    }
+
+    #[test]
+    fn splits_a_two_cents_remainder_among_three() {
+        let expense = expense(1, 1001, vec![1, 2, 3]);
+
+        // 1001 % 3 == 2, the two lowest ids get a cent each
+        assert_eq!(expense_shares(&expense, &[1, 2, 3]), vec![(1, 334), (2, 334), (3, 333)]);
+
+        let balances = compute_balances(&[expense], &[], &[1, 2, 3]);
+        assert_eq!(balances[&1], 667);
+        assert_eq!(balances[&2], -334);
+        assert_eq!(balances[&3], -333);
+    }
+
+    #[test]
+    fn splits_only_among_the_participants() {
+        let balances = compute_balances(&[expense(1, 100, vec![1, 2])], &[], &[1, 2, 3]);
+
+        assert_eq!(balances[&1], 50);
+        assert_eq!(balances[&2], -50);
+        assert_eq!(balances[&3], 0);
+    }
+
+    #[test]
+    fn settlements_reduce_the_debt() {
+        let settlement = settlement::Settlement {
+            id: 1,
+            room_id: String::new(),
+            from_user: 2,
+            to_user: 1,
+            amount_cents: 50,
+            #[cfg(feature = "ssr")]
+            created_at: None,
+        };
+
+        let balances = compute_balances(&[expense(1, 100, vec![1, 2])], &[settlement], &[1, 2]);
+
+        assert_eq!(balances[&1], 0);
+        assert_eq!(balances[&2], 0);
+    }
+
+    #[test]
+    fn simplifies_the_debts_to_the_fewest_transfers() {
+        let balances = HashMap::from([(1, 60), (2, -30), (3, -20), (4, -10)]);
+
+        let settlements = simplify_debts(&balances);
+
+        let transfers: Vec<(i64, i64, i64)> = settlements
+            .iter()
+            .map(|settlement| (settlement.from, settlement.to, settlement.amount_cents))
+            .collect();
+        assert_eq!(transfers, vec![(2, 1, 30), (3, 1, 20), (4, 1, 10)]);
+    }
+
+    #[test]
+    fn simplified_debts_settle_everybody() {
+        let balances = HashMap::from([(1, 45), (2, 25), (3, -50), (4, -20)]);
+
+        let mut remaining = balances.clone();
+        for settlement in simplify_debts(&balances) {
+            *remaining.get_mut(&settlement.from).unwrap() += settlement.amount_cents;
+            *remaining.get_mut(&settlement.to).unwrap() -= settlement.amount_cents;
+        }
+
+        assert!(remaining.values().all(|amount| *amount == 0));
+    }
+
+    // the transfers of the settle up, as (from, to, amount)
+    fn settle(expenses: &[Expense], members: &[i64]) -> Vec<(i64, i64, i64)> {
+        simplify_debts(&compute_balances(expenses, &[], members))
+            .iter()
+            .map(|settlement| (settlement.from, settlement.to, settlement.amount_cents))
+            .collect()
+    }
+
+    #[test]
+    fn collapses_a_chain_of_debts() {
+        // 1 owes 2 $5 and 2 owes 3 $5, so 1 pays 3 directly
+        let expenses = [expense(2, 500, vec![1]), expense(3, 500, vec![2])];
+
+        assert_eq!(settle(&expenses, &[1, 2, 3]), vec![(1, 3, 500)]);
+    }
+
+    #[test]
+    fn a_cycle_of_debts_cancels_out() {
+        // 1 owes 2, 2 owes 3 and 3 owes 1, $5 each
+        let expenses = [expense(2, 500, vec![1]), expense(3, 500, vec![2]), expense(1, 500, vec![3])];
+
+        assert!(settle(&expenses, &[1, 2, 3]).is_empty());
+    }
+
+    #[test]
+    fn the_transfers_move_half_the_total_debt() {
+        let expenses = [
+            expense(1, 900, vec![1, 2, 3]),
+            expense(2, 600, vec![2, 3]),
+            expense(3, 100, vec![1]),
+            expense(4, 250, vec![1, 4]),
+        ];
+        let members = [1, 2, 3, 4];
+
+        let balances = compute_balances(&expenses, &[], &members);
+        let total_debt: i64 = balances.values().map(|amount| amount.abs()).sum();
+        let transfers = settle(&expenses, &members);
+
+        assert_eq!(transfers.iter().map(|(_, _, amount)| amount).sum::<i64>(), total_debt / 2);
+        assert!(transfers.iter().all(|(_, _, amount)| *amount != 0));
+        assert_eq!(transfers, vec![(3, 1, 375), (3, 4, 125)]);
+    }
+
+    #[test]
+    fn nothing_to_settle_when_everybody_is_even() {
+        let balances = HashMap::from([(1, 0), (2, 0)]);
+
+        assert!(simplify_debts(&balances).is_empty());
+    }
+
+    #[test]
+    fn distributes_the_extras_proportionally() {
+        let base = HashMap::from([(1, 1000), (2, 2000), (3, 333)]);
+
+        let extras = distribute_proportional(&base, 101);
+
+        assert_eq!(extras.values().sum::<i64>(), 101);
+        // the cent lost in the rounding goes to the lowest id
+        assert_eq!(extras, HashMap::from([(1, 31), (2, 60), (3, 10)]));
+    }
+
+    #[test]
+    fn distributes_the_extras_equally_without_a_base() {
+        let base = HashMap::from([(1, 0), (2, 0)]);
+
+        let extras = distribute_proportional(&base, 5);
+
+        assert_eq!(extras, HashMap::from([(1, 3), (2, 2)]));
+    }
+
+    #[test]
+    fn itemized_shares_include_tax_and_tip() {
+        let items = vec![
+            ExpenseItem { description: "Pasta".to_string(), amount_cents: 1000, assigned_to: 1 },
+            ExpenseItem { description: "Steak".to_string(), amount_cents: 3000, assigned_to: 2 },
+        ];
+        let expense = Expense {
+            paid_by: 1,
+            amount_cents: 4400,
+            split_method: SplitMethod::Itemized(items),
+            tax_cents: 200,
+            tip_cents: 200,
+            ..Expense::default()
+        };
+
+        assert_eq!(expense_shares(&expense, &[1, 2]), vec![(1, 1100), (2, 3300)]);
+    }
+
+    #[test]
+    fn percentages_under_100_give_the_missing_cents() {
+        // 33.33% of 1000 is 333.3, three times 333 misses a cent
+        let shares = split_by_percentage(1000, &[(1, 33.33), (2, 33.33), (3, 33.33)]);
+
+        assert_eq!(shares, vec![(1, 334), (2, 333), (3, 333)]);
+    }
+
+    #[test]
+    fn percentages_over_100_take_back_the_extra_cents() {
+        let shares = split_by_percentage(10000, &[(1, 50.01), (2, 50.0)]);
+
+        assert_eq!(shares, vec![(1, 5001), (2, 4999)]);
+        assert_eq!(shares.iter().map(|(_, share)| share).sum::<i64>(), 10000);
+    }
+
+    #[test]
+    fn equal_shares_sum_to_the_amount() {
+        let amounts = split_by_shares(3, &[(1, 1), (2, 1), (3, 1)]);
+
+        assert_eq!(amounts, vec![(1, 1), (2, 1), (3, 1)]);
+        assert_eq!(amounts.iter().map(|(_, amount)| amount).sum::<i64>(), 3);
+    }
+
+    #[test]
+    fn splits_by_the_weights_of_the_shares() {
+        assert_eq!(split_by_shares(100, &[(3, 1), (1, 2), (2, 1)]), vec![(1, 50), (2, 25), (3, 25)]);
+        // 3.33 and 6.66, the cent lost in the rounding goes to the lowest id
+        assert_eq!(split_by_shares(10, &[(1, 1), (2, 2)]), vec![(1, 4), (2, 6)]);
+    }
+
+    #[test]
+    fn refunds_reduce_the_balances() {
+        let refund = Expense {
+            kind: ExpenseKind::Refund,
+            ..expense(1, 40, vec![1, 2])
+        };
+
+        let balances = compute_balances(&[expense(1, 100, vec![1, 2]), refund], &[], &[1, 2]);
+
+        assert_eq!(balances[&1], 30);
+        assert_eq!(balances[&2], -30);
+    }
+
+    #[cfg(feature = "ssr")]
+    #[tokio::test]
+    async fn refunds_reduce_the_user_stats() {
+        use crate::models::expense::Category;
+        use crate::services::expenses::{insert_expense, ValidatedExpense};
+        use crate::test_utils::{create_room, create_user, test_pool};
+        use crate::utils::money::Currency;
+
+        let pool = test_pool().await;
+        let alice = create_user("alice", &pool).await;
+        let bob = create_user("bob", &pool).await;
+        let room = create_room("Trip", &alice, &pool).await;
+        Room::add_member(&room.id, bob.id, &pool).await.unwrap();
+
+        for (amount_cents, kind) in [(100, ExpenseKind::Expense), (40, ExpenseKind::Refund)] {
+            let expense = ValidatedExpense {
+                room_id: room.id.clone(),
+                paid_by: alice.id,
+                title: "Hotel".to_string(),
+                description: None,
+                amount_cents,
+                participants: vec![alice.id, bob.id],
+                split_method: SplitMethod::Equal,
+                currency: Currency::USD,
+                exchange_rate: 1.0,
+                tax_cents: 0,
+                tip_cents: 0,
+                category: Category::Other,
+                kind,
+                occurred_on: chrono::NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+            };
+            insert_expense(&expense, None, &pool).await.unwrap();
+        }
+
+        let stats = user_stats(&room.id, alice.id, &pool).await.unwrap();
+
+        assert_eq!(stats.total_paid, 60);
+        assert_eq!(stats.total_share, 30);
+        assert_eq!(stats.expense_count, 2);
+    }
+}
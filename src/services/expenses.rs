@@ -0,0 +1,279 @@
+use cfg_if::cfg_if;
+
+cfg_if! {
+    if #[cfg(feature = "ssr")] {
+        use chrono::NaiveDate;
+        use sqlx::sqlite::SqlitePool;
+
+        use crate::error_template::AppError;
+        use crate::models::expense::{Category, ExpenseKind, SplitMethod};
+        use crate::utils::money::Currency;
+
+        // for SQLite datetime()
+        const IDEMPOTENCY_KEY_TTL: &str = "-1 day";
+
+        // An expense already validated by the server function, with the
+        // participants resolved (never empty).
+        #[derive(Debug, Clone)]
+        pub struct ValidatedExpense {
+            pub room_id: String,
+            pub paid_by: i64,
+            pub title: String,
+            pub description: Option<String>,
+            pub amount_cents: i64,
+            pub participants: Vec<i64>,
+            pub split_method: SplitMethod,
+            pub currency: Currency,
+            pub exchange_rate: f64,
+            pub tax_cents: i64,
+            pub tip_cents: i64,
+            pub category: Category,
+            pub kind: ExpenseKind,
+            pub occurred_on: NaiveDate,
+        }
+
+        // Stores the expense with its splits and participants. A retried request
+        // with the same idempotency key returns the expense created the first time.
+        pub async fn insert_expense(expense: &ValidatedExpense, idempotency_key: Option<&str>, pool: &SqlitePool) -> Result<i64, AppError> {
+            let mut tx = pool.begin().await?;
+
+            // the delete is the first statement, so the transaction holds the
+            // write lock before the lookup and two requests with the same key
+            // can't both miss it
+            if let Some(key) = idempotency_key {
+                sqlx::query("DELETE FROM idempotency WHERE created_at <= datetime('now', ?)")
+                    .bind(IDEMPOTENCY_KEY_TTL)
+                    .execute(&mut *tx)
+                    .await?;
+
+                let expense_id: Option<i64> = sqlx::query_scalar("SELECT expense_id FROM idempotency WHERE user_id = ? AND key = ?")
+                    .bind(expense.paid_by)
+                    .bind(key)
+                    .fetch_optional(&mut *tx)
+                    .await?;
+
+                if let Some(expense_id) = expense_id {
+                    log::info!("fn: insert_expense() - key {} already used for expense {}", key, expense_id);
+                    return Ok(expense_id);
+                }
+            }
+
+            log::info!("fn: insert_expense() - adding expense: {:?} in room {}", expense.title, expense.room_id);
+
+            // add expense, only if the payer is still in the room: they could have
+            // been removed after the checks, the insert and the check must be atomic
+            let expense_id: Option<i64> = sqlx::query_scalar(
+                "INSERT INTO expense (paid_by, amount_cents, title, description, room_id, split_method, currency, exchange_rate, tax_cents, tip_cents, category, kind, occurred_on) SELECT ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ? WHERE EXISTS (SELECT 1 FROM user_room WHERE user_room.user_id = ? AND user_room.room_id = ?) RETURNING id",
+            )
+            .bind(expense.paid_by)
+            .bind(expense.amount_cents)
+            .bind(&expense.title)
+            .bind(&expense.description)
+            .bind(&expense.room_id)
+            .bind(expense.split_method.as_str())
+            .bind(expense.currency.as_str())
+            .bind(expense.exchange_rate)
+            .bind(expense.tax_cents)
+            .bind(expense.tip_cents)
+            .bind(expense.category.to_string())
+            .bind(expense.kind.as_str())
+            .bind(expense.occurred_on.to_string())
+            .bind(expense.paid_by)
+            .bind(&expense.room_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+            let Some(expense_id) = expense_id else {
+                log::info!("fn: insert_expense() - user {} left room {} before the insert", expense.paid_by, expense.room_id);
+                return Err(AppError::Unauthorized);
+            };
+
+            // add values to expense_split
+            match &expense.split_method {
+                SplitMethod::Equal => {
+                    for user_id in &expense.participants {
+                        sqlx::query("INSERT INTO expense_split (expense_id, user_id) VALUES (?, ?)")
+                            .bind(expense_id)
+                            .bind(user_id)
+                            .execute(&mut *tx)
+                            .await?;
+                    }
+                }
+                SplitMethod::Percentage(percentages) => {
+                    for (user_id, percentage) in percentages {
+                        sqlx::query("INSERT INTO expense_split (expense_id, user_id, percentage) VALUES (?, ?, ?)")
+                            .bind(expense_id)
+                            .bind(user_id)
+                            .bind(percentage)
+                            .execute(&mut *tx)
+                            .await?;
+                    }
+                }
+                SplitMethod::ExactCents(amounts) => {
+                    for (user_id, amount) in amounts {
+                        sqlx::query("INSERT INTO expense_split (expense_id, user_id, amount_cents) VALUES (?, ?, ?)")
+                            .bind(expense_id)
+                            .bind(user_id)
+                            .bind(amount)
+                            .execute(&mut *tx)
+                            .await?;
+                    }
+                }
+                SplitMethod::Shares(shares) => {
+                    for (user_id, share) in shares {
+                        sqlx::query("INSERT INTO expense_split (expense_id, user_id, shares) VALUES (?, ?, ?)")
+                            .bind(expense_id)
+                            .bind(user_id)
+                            .bind(share)
+                            .execute(&mut *tx)
+                            .await?;
+                    }
+                }
+                SplitMethod::Itemized(items) => {
+                    for item in items {
+                        sqlx::query("INSERT INTO expense_item (expense_id, description, amount_cents, assigned_to) VALUES (?, ?, ?, ?)")
+                            .bind(expense_id)
+                            .bind(&item.description)
+                            .bind(item.amount_cents)
+                            .bind(item.assigned_to)
+                            .execute(&mut *tx)
+                            .await?;
+                    }
+                }
+            }
+
+            log::info!("fn: insert_expense() - adding participants: {:?}", expense.participants);
+
+            // add values to user_expense
+            for id_participant in &expense.participants {
+                sqlx::query("INSERT INTO user_expense (user_id, expense_id) VALUES (?, ?)")
+                    .bind(id_participant)
+                    .bind(expense_id)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+
+            if let Some(key) = idempotency_key {
+                let claimed = sqlx::query("INSERT INTO idempotency (user_id, key, expense_id) VALUES (?, ?, ?) ON CONFLICT (user_id, key) DO NOTHING")
+                    .bind(expense.paid_by)
+                    .bind(key)
+                    .bind(expense_id)
+                    .execute(&mut *tx)
+                    .await?
+                    .rows_affected()
+                    > 0;
+
+                // another request stored the key first, its expense is the one to keep
+                if !claimed {
+                    tx.rollback().await?;
+
+                    let original_id: i64 = sqlx::query_scalar("SELECT expense_id FROM idempotency WHERE user_id = ? AND key = ?")
+                        .bind(expense.paid_by)
+                        .bind(key)
+                        .fetch_one(pool)
+                        .await?;
+
+                    log::info!("fn: insert_expense() - key {} stored meanwhile for expense {}", key, original_id);
+                    return Ok(original_id);
+                }
+            }
+
+            tx.commit().await?;
+
+            log::info!("fn: insert_expense() - added expense: {}", expense_id);
+            return Ok(expense_id);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "ssr"))]
+mod tests {
+    use super::*;
+    use crate::models::room::Room;
+    use crate::test_utils::{create_room, create_user, test_pool};
+
+    fn lunch(room_id: &str, paid_by: i64, participants: Vec<i64>) -> ValidatedExpense {
+        ValidatedExpense {
+            room_id: room_id.to_string(),
+            paid_by,
+            title: "Lunch".to_string(),
+            description: None,
+            amount_cents: 30_00,
+            participants,
+            split_method: SplitMethod::Equal,
+            currency: Currency::USD,
+            exchange_rate: 1.0,
+            tax_cents: 0,
+            tip_cents: 0,
+            category: Category::Food,
+            kind: ExpenseKind::Expense,
+            occurred_on: NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+        }
+    }
+
+    #[tokio::test]
+    async fn inserts_an_expense_paid_by_a_member() {
+        let pool = test_pool().await;
+        let alice = create_user("alice", &pool).await;
+        let room = create_room("Trip", &alice, &pool).await;
+
+        let expense_id = insert_expense(&lunch(&room.id, alice.id, vec![alice.id]), None, &pool).await.unwrap();
+
+        let participants: Vec<i64> = sqlx::query_scalar("SELECT user_id FROM user_expense WHERE expense_id = ?")
+            .bind(expense_id)
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(participants, vec![alice.id]);
+    }
+
+    #[tokio::test]
+    async fn rejects_a_payer_removed_from_the_room() {
+        let pool = test_pool().await;
+        let alice = create_user("alice", &pool).await;
+        let bob = create_user("bob", &pool).await;
+        let room = create_room("Trip", &alice, &pool).await;
+
+        Room::add_member(&room.id, bob.id, &pool).await.unwrap();
+
+        // bob passed the checks of the server function, then was removed
+        sqlx::query("DELETE FROM user_room WHERE room_id = ? AND user_id = ?")
+            .bind(&room.id)
+            .bind(bob.id)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let result = insert_expense(&lunch(&room.id, bob.id, vec![alice.id]), None, &pool).await;
+        assert!(matches!(result, Err(AppError::Unauthorized)));
+
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM expense WHERE room_id = ?")
+            .bind(&room.id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(count, 0);
+    }
+
+    #[tokio::test]
+    async fn a_retried_request_returns_the_first_expense() {
+        let pool = test_pool().await;
+        let alice = create_user("alice", &pool).await;
+        let room = create_room("Trip", &alice, &pool).await;
+        let expense = lunch(&room.id, alice.id, vec![alice.id]);
+
+        let first_id = insert_expense(&expense, Some("key-1"), &pool).await.unwrap();
+        let retried_id = insert_expense(&expense, Some("key-1"), &pool).await.unwrap();
+        let other_id = insert_expense(&expense, Some("key-2"), &pool).await.unwrap();
+
+        assert_eq!(retried_id, first_id);
+        assert_ne!(other_id, first_id);
+
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM expense WHERE room_id = ?")
+            .bind(&room.id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(count, 2);
+    }
+}
@@ -0,0 +1,8 @@
+pub mod balance;
+pub mod recurring;
+pub mod thumbnail;
+pub mod reminders;
+pub mod report;
+pub mod password;
+pub mod authz;
+pub mod expenses;
@@ -0,0 +1,227 @@
+use cfg_if::cfg_if;
+
+cfg_if! {
+    if #[cfg(feature = "ssr")] {
+        use argon2::password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher as _, PasswordVerifier as _, SaltString};
+        use argon2::{Argon2, Params, Version};
+        use thiserror::Error;
+
+        #[derive(Debug, Error)]
+        pub enum PasswordError {
+            #[error("bcrypt error: {0}")]
+            Bcrypt(#[from] bcrypt::BcryptError),
+            #[error("argon2 error: {0}")]
+            Argon2(String),
+            #[error("unknown password hash algorithm")]
+            UnknownAlgorithm,
+        }
+
+        pub trait PasswordHasher {
+            fn hash(&self, password: &str) -> Result<String, PasswordError>;
+            fn verify(&self, password: &str, hash: &str) -> Result<bool, PasswordError>;
+        }
+
+        // The older hashes, verified and then replaced by Argon2 ones: the new
+        // passwords are hashed with `Argon2Hasher`.
+        #[derive(Debug, Clone)]
+        pub struct BcryptHasher {
+            cost: u32,
+        }
+
+        impl Default for BcryptHasher {
+            fn default() -> Self {
+                Self::new(bcrypt::DEFAULT_COST)
+            }
+        }
+
+        impl BcryptHasher {
+            pub fn new(cost: u32) -> Self {
+                Self { cost }
+            }
+        }
+
+        impl PasswordHasher for BcryptHasher {
+            fn hash(&self, password: &str) -> Result<String, PasswordError> {
+                Ok(bcrypt::hash(password, self.cost)?)
+            }
+
+            fn verify(&self, password: &str, hash: &str) -> Result<bool, PasswordError> {
+                Ok(bcrypt::verify(password, hash)?)
+            }
+        }
+
+        // Hashes the new passwords. The parameters are read from the hash when
+        // verifying, so the hashes made with other parameters keep working.
+        #[derive(Debug, Clone)]
+        pub struct Argon2Hasher {
+            params: Params,
+        }
+
+        impl Default for Argon2Hasher {
+            fn default() -> Self {
+                Self::new(Params::default())
+            }
+        }
+
+        impl Argon2Hasher {
+            pub fn new(params: Params) -> Self {
+                Self { params }
+            }
+
+            // ARGON2_MEMORY_KIB, ARGON2_ITERATIONS and ARGON2_PARALLELISM, the
+            // missing ones keep the default of the argon2 crate.
+            pub fn from_env() -> Self {
+                let param = |name: &str, default: u32| match std::env::var(name) {
+                    Ok(value) => value
+                        .parse::<u32>()
+                        .unwrap_or_else(|_| panic!("{} must be a positive integer, got {:?}", name, value)),
+                    Err(_) => default,
+                };
+
+                let params = Params::new(
+                    param("ARGON2_MEMORY_KIB", Params::DEFAULT_M_COST),
+                    param("ARGON2_ITERATIONS", Params::DEFAULT_T_COST),
+                    param("ARGON2_PARALLELISM", Params::DEFAULT_P_COST),
+                    None,
+                )
+                .unwrap_or_else(|e| panic!("invalid argon2 parameters: {}", e));
+
+                Self::new(params)
+            }
+
+            fn argon2(&self) -> Argon2<'static> {
+                Argon2::new(argon2::Algorithm::Argon2id, Version::V0x13, self.params.clone())
+            }
+
+            // The bcrypt hashes and the Argon2 ones made with a lower cost than
+            // the current one.
+            pub fn needs_rehash(&self, hash: &str) -> bool {
+                if Algorithm::detect(hash) != Some(Algorithm::Argon2) {
+                    return true;
+                }
+
+                let Ok(params) = PasswordHash::new(hash).and_then(|hash| Params::try_from(&hash)) else {
+                    return true;
+                };
+
+                params.m_cost() < self.params.m_cost()
+                    || params.t_cost() < self.params.t_cost()
+                    || params.p_cost() < self.params.p_cost()
+            }
+        }
+
+        impl PasswordHasher for Argon2Hasher {
+            fn hash(&self, password: &str) -> Result<String, PasswordError> {
+                let salt = SaltString::generate(&mut OsRng);
+
+                self.argon2()
+                    .hash_password(password.as_bytes(), &salt)
+                    .map(|hash| hash.to_string())
+                    .map_err(|e| PasswordError::Argon2(e.to_string()))
+            }
+
+            fn verify(&self, password: &str, hash: &str) -> Result<bool, PasswordError> {
+                let hash = PasswordHash::new(hash).map_err(|e| PasswordError::Argon2(e.to_string()))?;
+
+                Ok(Argon2::default().verify_password(password.as_bytes(), &hash).is_ok())
+            }
+        }
+
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub enum Algorithm {
+            Bcrypt,
+            Argon2,
+        }
+
+        impl Algorithm {
+            // The hashes are in the PHC/modular crypt format, the prefix names the algorithm.
+            pub fn detect(hash: &str) -> Option<Self> {
+                if hash.starts_with("$argon2") {
+                    Some(Algorithm::Argon2)
+                } else if ["$2b$", "$2a$", "$2y$"].iter().any(|prefix| hash.starts_with(prefix)) {
+                    Some(Algorithm::Bcrypt)
+                } else {
+                    None
+                }
+            }
+        }
+
+        pub fn verify_password(password: &str, hash: &str) -> Result<bool, PasswordError> {
+            match Algorithm::detect(hash).ok_or(PasswordError::UnknownAlgorithm)? {
+                Algorithm::Bcrypt => BcryptHasher::default().verify(password, hash),
+                Algorithm::Argon2 => Argon2Hasher::default().verify(password, hash),
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "ssr"))]
+mod tests {
+    use super::*;
+
+    // the lowest cost, so that the tests stay fast
+    fn test_hasher() -> Argon2Hasher {
+        Argon2Hasher::new(Params::new(Params::MIN_M_COST, Params::MIN_T_COST, 1, None).unwrap())
+    }
+
+    #[test]
+    fn verifies_an_argon2_hash() {
+        let hash = test_hasher().hash("correct horse").unwrap();
+
+        assert_eq!(Algorithm::detect(&hash), Some(Algorithm::Argon2));
+        assert!(verify_password("correct horse", &hash).unwrap());
+        assert!(!verify_password("wrong horse", &hash).unwrap());
+    }
+
+    #[test]
+    fn verifies_a_legacy_bcrypt_hash() {
+        let hash = bcrypt::hash("correct horse", 4).unwrap();
+
+        assert_eq!(Algorithm::detect(&hash), Some(Algorithm::Bcrypt));
+        assert!(verify_password("correct horse", &hash).unwrap());
+        assert!(!verify_password("wrong horse", &hash).unwrap());
+    }
+
+    #[test]
+    fn the_hashers_can_be_swapped() {
+        let hashers: [Box<dyn PasswordHasher>; 2] = [Box::new(test_hasher()), Box::new(BcryptHasher::new(4))];
+
+        for hasher in hashers {
+            let hash = hasher.hash("correct horse").unwrap();
+
+            assert!(hasher.verify("correct horse", &hash).unwrap());
+            assert!(!hasher.verify("wrong horse", &hash).unwrap());
+        }
+    }
+
+    #[test]
+    fn rejects_an_unknown_hash() {
+        assert!(matches!(verify_password("password", "plain text"), Err(PasswordError::UnknownAlgorithm)));
+    }
+
+    #[test]
+    fn rehashes_bcrypt_and_cheaper_argon2_hashes() {
+        let hasher = test_hasher();
+        let stronger = Argon2Hasher::new(Params::new(Params::MIN_M_COST, Params::MIN_T_COST + 1, 1, None).unwrap());
+
+        assert!(hasher.needs_rehash(&bcrypt::hash("password", 4).unwrap()));
+        assert!(!hasher.needs_rehash(&hasher.hash("password").unwrap()));
+        assert!(stronger.needs_rehash(&hasher.hash("password").unwrap()));
+    }
+
+    #[test]
+    fn reads_the_cost_from_the_environment() {
+        // no other test reads these variables
+        std::env::set_var("ARGON2_MEMORY_KIB", Params::MIN_M_COST.to_string());
+        std::env::set_var("ARGON2_ITERATIONS", "3");
+        std::env::remove_var("ARGON2_PARALLELISM");
+
+        let hasher = Argon2Hasher::from_env();
+        let hash = hasher.hash("password").unwrap();
+
+        let params = Params::try_from(&PasswordHash::new(&hash).unwrap()).unwrap();
+        assert_eq!(params.m_cost(), Params::MIN_M_COST);
+        assert_eq!(params.t_cost(), 3);
+        assert_eq!(params.p_cost(), Params::DEFAULT_P_COST);
+    }
+}
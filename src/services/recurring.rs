@@ -0,0 +1,92 @@
+use cfg_if::cfg_if;
+
+cfg_if! {
+    if #[cfg(feature = "ssr")] {
+        use chrono::NaiveDate;
+        use sqlx::sqlite::SqlitePool;
+
+        use crate::models::recurring_expense::RecurringExpense;
+
+        // Inserts an expense for every occurrence due up to today, so that the
+        // intervals missed while the server was down are posted too.
+        pub async fn post_due_recurring_expenses(today: NaiveDate, pool: &SqlitePool) -> Result<usize, sqlx::Error> {
+            let mut posted = 0;
+
+            for recurring in RecurringExpense::list_due(today, pool).await? {
+                let mut tx = pool.begin().await?;
+                let mut occurrences = recurring.occurrences;
+                let mut next_due = recurring.next_due;
+
+                while next_due <= today {
+                    sqlx::query(
+                        "INSERT INTO expense (paid_by, amount_cents, title, room_id, created_at) VALUES (?, ?, ?, ?, ?)",
+                    )
+                    .bind(recurring.paid_by)
+                    .bind(recurring.amount_cents)
+                    .bind(&recurring.title)
+                    .bind(&recurring.room_id)
+                    .bind(next_due.and_hms_opt(0, 0, 0))
+                    .execute(&mut *tx)
+                    .await?;
+
+                    occurrences += 1;
+                    next_due = recurring.interval.occurrence(recurring.first_due, occurrences);
+                    posted += 1;
+                }
+
+                // posting and advancing in the same transaction avoids double posting
+                sqlx::query("UPDATE recurring_expense SET occurrences = ?, next_due = ? WHERE id = ?")
+                    .bind(occurrences)
+                    .bind(next_due)
+                    .bind(recurring.id)
+                    .execute(&mut *tx)
+                    .await?;
+
+                tx.commit().await?;
+            }
+
+            log::info!("fn: post_due_recurring_expenses() - posted {} expenses", posted);
+            return Ok(posted);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "ssr"))]
+mod tests {
+    use super::*;
+    use crate::test_utils::{create_room, create_user, test_pool};
+
+    #[tokio::test]
+    async fn posts_the_monthly_expenses_on_the_day_of_the_first_one() {
+        let pool = test_pool().await;
+        let alice = create_user("alice", &pool).await;
+        let room = create_room("Flat", &alice, &pool).await;
+        let first_due = NaiveDate::from_ymd_opt(2024, 1, 31).unwrap();
+
+        sqlx::query("INSERT INTO recurring_expense (room_id, title, amount_cents, paid_by, interval, first_due, next_due) VALUES (?, 'Rent', 50000, ?, 'monthly', ?, ?)")
+            .bind(&room.id)
+            .bind(alice.id)
+            .bind(first_due)
+            .bind(first_due)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let posted = post_due_recurring_expenses(NaiveDate::from_ymd_opt(2024, 4, 30).unwrap(), &pool).await.unwrap();
+        assert_eq!(posted, 4);
+
+        let days: Vec<String> = sqlx::query_scalar("SELECT date(created_at) FROM expense WHERE room_id = ? ORDER BY created_at")
+            .bind(&room.id)
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(days, vec!["2024-01-31", "2024-02-29", "2024-03-31", "2024-04-30"]);
+
+        let next_due: NaiveDate = sqlx::query_scalar("SELECT next_due FROM recurring_expense WHERE room_id = ?")
+            .bind(&room.id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(next_due, NaiveDate::from_ymd_opt(2024, 5, 31).unwrap());
+    }
+}
@@ -0,0 +1,49 @@
+use cfg_if::cfg_if;
+
+cfg_if! {
+    if #[cfg(feature = "ssr")] {
+        use chrono::NaiveDateTime;
+        use sqlx::sqlite::SqlitePool;
+
+        use crate::models::{expense::Expense, reminder::Reminder, room::Room, settlement::Settlement};
+        use crate::services::balance::compute_balances;
+
+        // A debt is overdue if the user already owed money at `cutoff` and still
+        // owes now. Every debt has a single reminder, removed once it is settled.
+        pub async fn record_overdue_reminders(cutoff: NaiveDateTime, pool: &SqlitePool) -> Result<usize, sqlx::Error> {
+            let mut reminded = 0;
+
+            let room_ids: Vec<String> = sqlx::query_scalar("SELECT id FROM room")
+                .fetch_all(pool)
+                .await?;
+
+            for room_id in room_ids {
+                let expenses = Expense::list_for_room(&room_id, pool).await?;
+                let settlements = Settlement::list_for_room(&room_id, pool).await?;
+                let members = Room::member_ids(&room_id, pool).await?;
+
+                let is_before_cutoff = |created_at: Option<NaiveDateTime>| created_at.is_some_and(|created_at| created_at < cutoff);
+                let old_expenses: Vec<Expense> = expenses.iter().filter(|expense| is_before_cutoff(expense.created_at)).cloned().collect();
+                let old_settlements: Vec<Settlement> = settlements.iter().filter(|settlement| is_before_cutoff(settlement.created_at)).cloned().collect();
+
+                let balances = compute_balances(&expenses, &settlements, &members);
+                let old_balances = compute_balances(&old_expenses, &old_settlements, &members);
+
+                for user_id in members {
+                    let balance = balances.get(&user_id).copied().unwrap_or_default();
+                    let old_balance = old_balances.get(&user_id).copied().unwrap_or_default();
+
+                    if balance < 0 && old_balance < 0 {
+                        Reminder::upsert(&room_id, user_id, -balance, pool).await?;
+                        reminded += 1;
+                    } else {
+                        Reminder::delete(&room_id, user_id, pool).await?;
+                    }
+                }
+            }
+
+            log::info!("fn: record_overdue_reminders() - {} overdue debts", reminded);
+            return Ok(reminded);
+        }
+    }
+}
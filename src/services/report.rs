@@ -0,0 +1,128 @@
+use cfg_if::cfg_if;
+
+cfg_if! {
+    if #[cfg(feature = "ssr")] {
+        use std::collections::HashMap;
+
+        use leptos::ServerFnError;
+        use printpdf::{BuiltinFont, IndirectFontRef, Mm, PdfDocument, PdfDocumentReference, PdfLayerReference};
+        use sqlx::sqlite::SqlitePool;
+
+        use crate::i18n::Lang;
+        use crate::models::{expense::Expense, room::Room, user::{User, UserLabels}};
+        use crate::services::balance::{room_balances, simplify_debts};
+        use crate::utils::money::{format_cents, Currency};
+
+        const PAGE_WIDTH: Mm = Mm(210.0);
+        const PAGE_HEIGHT: Mm = Mm(297.0);
+        const MARGIN: f32 = 20.0;
+        const LINE_HEIGHT: f32 = 6.0;
+
+        // Writes the lines top to bottom, adding the pages as they fill up.
+        struct ReportWriter {
+            doc: PdfDocumentReference,
+            layer: PdfLayerReference,
+            font: IndirectFontRef,
+            bold: IndirectFontRef,
+            y: f32,
+        }
+
+        impl ReportWriter {
+            fn new(title: &str) -> Result<Self, ServerFnError> {
+                let (doc, page, layer) = PdfDocument::new(title, PAGE_WIDTH, PAGE_HEIGHT, "Layer 1");
+                let font = doc.add_builtin_font(BuiltinFont::Helvetica)?;
+                let bold = doc.add_builtin_font(BuiltinFont::HelveticaBold)?;
+                let layer = doc.get_page(page).get_layer(layer);
+
+                Ok(Self { doc, layer, font, bold, y: PAGE_HEIGHT.0 - MARGIN })
+            }
+
+            fn next_line(&mut self, height: f32) {
+                self.y -= height;
+
+                if self.y < MARGIN {
+                    let (page, layer) = self.doc.add_page(PAGE_WIDTH, PAGE_HEIGHT, "Layer 1");
+                    self.layer = self.doc.get_page(page).get_layer(layer);
+                    self.y = PAGE_HEIGHT.0 - MARGIN;
+                }
+            }
+
+            fn heading(&mut self, text: &str, size: f32) {
+                self.next_line(LINE_HEIGHT);
+                self.layer.use_text(text, size, Mm(MARGIN), Mm(self.y), &self.bold);
+                self.next_line(LINE_HEIGHT / 2.0);
+            }
+
+            // every column starts at the given distance from the left margin, in mm
+            fn row(&mut self, columns: &[(f32, &str)], is_header: bool) {
+                let font = if is_header { &self.bold } else { &self.font };
+                for (x, text) in columns {
+                    self.layer.use_text(*text, 10.0, Mm(MARGIN + x), Mm(self.y), font);
+                }
+                self.next_line(LINE_HEIGHT);
+            }
+        }
+
+        // A title, the table of the expenses, the totals paid by every member and the
+        // payments that settle the room, amounts in the room base currency.
+        pub async fn room_report_pdf(room_id: &str, pool: &SqlitePool) -> Result<Vec<u8>, ServerFnError> {
+            log::info!("fn: room_report_pdf() - room {}", room_id);
+
+            let room = sqlx::query_as::<_, Room>("SELECT * FROM room WHERE id = ?")
+                .bind(room_id)
+                .fetch_one(pool)
+                .await?;
+
+            let users = sqlx::query_as::<_, User>(include_str!("../../queries/get_users_in_room.sql"))
+                .bind(room_id)
+                .fetch_all(pool)
+                .await?;
+
+            let labels = UserLabels::new(&users);
+
+            let currency = room.base_currency.parse().unwrap_or(Currency::USD);
+            let money = |cents: i64| format_cents(cents, currency, Lang::En);
+
+            let expenses = Expense::list_for_room(room_id, pool).await?;
+            let settlements = simplify_debts(&room_balances(room_id, pool).await?);
+
+            let mut writer = ReportWriter::new(&room.room_name)?;
+            writer.heading(&room.room_name, 18.0);
+
+            writer.heading("Expenses", 14.0);
+            writer.row(&[(0.0, "Date"), (30.0, "Title"), (105.0, "Paid by"), (145.0, "Amount")], true);
+
+            let mut totals: HashMap<i64, i64> = HashMap::new();
+            for expense in &expenses {
+                // the refunds are money coming back, they lower the total of who paid
+                let amount = expense.kind.sign() * expense.base_amount_cents();
+                *totals.entry(expense.paid_by).or_default() += amount;
+
+                let date = expense.occurred_on.format("%Y-%m-%d").to_string();
+                let title: String = expense.title.chars().take(40).collect();
+                writer.row(
+                    &[(0.0, date.as_str()), (30.0, title.as_str()), (105.0, labels.get(expense.paid_by).as_str()), (145.0, money(amount).as_str())],
+                    false,
+                );
+            }
+
+            writer.heading("Totals paid", 14.0);
+            for user in &users {
+                let total = totals.get(&user.id).copied().unwrap_or_default();
+                writer.row(&[(0.0, user.label().as_str()), (105.0, money(total).as_str())], false);
+            }
+
+            writer.heading("Settlements", 14.0);
+            if settlements.is_empty() {
+                writer.row(&[(0.0, "Everybody is settled up")], false);
+            }
+            for settlement in &settlements {
+                let payment = format!("{} pays {}", labels.get(settlement.from), labels.get(settlement.to));
+                writer.row(&[(0.0, payment.as_str()), (105.0, money(settlement.amount_cents).as_str())], false);
+            }
+
+            let bytes = writer.doc.save_to_bytes()?;
+            return Ok(bytes);
+        }
+    }
+}
@@ -0,0 +1,37 @@
+use cfg_if::cfg_if;
+
+cfg_if! {
+    if #[cfg(feature = "ssr")] {
+        use image::{DynamicImage, ImageFormat, ImageOutputFormat};
+        use std::io::Cursor;
+
+        const THUMBNAIL_MAX_SIZE: u32 = 200;
+        const THUMBNAIL_JPEG_QUALITY: u8 = 80;
+
+        // The type of the image from its bytes, whatever the client declared.
+        // None for the formats that are not allowed, e.g. SVG can run scripts.
+        pub fn image_mime_type(data: &[u8]) -> Option<&'static str> {
+            match image::guess_format(data).ok()? {
+                ImageFormat::Png => Some("image/png"),
+                ImageFormat::Jpeg => Some("image/jpeg"),
+                ImageFormat::Gif => Some("image/gif"),
+                ImageFormat::WebP => Some("image/webp"),
+                _ => None,
+            }
+        }
+
+        // Downscales the image to fit in a 200x200 box keeping the aspect ratio,
+        // fails if the bytes are not an image in a supported format.
+        pub fn make_thumbnail(data: &[u8]) -> Result<Vec<u8>, image::ImageError> {
+            let image = image::load_from_memory(data)?;
+
+            // JPEG has no alpha channel
+            let thumbnail = DynamicImage::ImageRgb8(image.thumbnail(THUMBNAIL_MAX_SIZE, THUMBNAIL_MAX_SIZE).to_rgb8());
+
+            let mut bytes = Cursor::new(vec![]);
+            thumbnail.write_to(&mut bytes, ImageOutputFormat::Jpeg(THUMBNAIL_JPEG_QUALITY))?;
+
+            return Ok(bytes.into_inner());
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use cfg_if::cfg_if;
+
+cfg_if! {
+    if #[cfg(feature = "ssr")] {
+        use std::collections::BTreeMap;
+        use std::fmt::Write;
+        use std::sync::{Arc, RwLock};
+        use std::time::{Duration, Instant};
+
+        // Upper bounds of the latency buckets, in seconds.
+        const BUCKETS: [f64; 10] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0];
+
+        #[derive(Debug, Default, Clone)]
+        struct Histogram {
+            // not cumulative, one more than `BUCKETS` for the slower calls
+            bucket_counts: [u64; BUCKETS.len() + 1],
+            count: u64,
+            sum_seconds: f64,
+        }
+
+        // Latency histograms of the server functions, by name. Kept in memory
+        // only, so they restart from zero with the server.
+        #[derive(Debug, Default)]
+        pub struct Metrics {
+            histograms: RwLock<BTreeMap<&'static str, Histogram>>,
+        }
+
+        impl Metrics {
+            pub fn record(&self, name: &'static str, duration: Duration) {
+                let seconds = duration.as_secs_f64();
+                let bucket = BUCKETS.iter().position(|bound| seconds <= *bound).unwrap_or(BUCKETS.len());
+
+                let mut histograms = self.histograms.write().unwrap();
+                let histogram = histograms.entry(name).or_default();
+                histogram.bucket_counts[bucket] += 1;
+                histogram.count += 1;
+                histogram.sum_seconds += seconds;
+            }
+
+            // Prometheus text exposition format.
+            pub fn render(&self) -> String {
+                let histograms = self.histograms.read().unwrap();
+                let mut out = String::new();
+
+                out.push_str("# HELP server_fn_duration_seconds Duration of the server functions.\n");
+                out.push_str("# TYPE server_fn_duration_seconds histogram\n");
+
+                for (name, histogram) in histograms.iter() {
+                    let mut cumulative = 0;
+                    for (bound, count) in BUCKETS.iter().zip(histogram.bucket_counts.iter()) {
+                        cumulative += count;
+                        let _ = writeln!(out, "server_fn_duration_seconds_bucket{{name=\"{}\",le=\"{}\"}} {}", name, bound, cumulative);
+                    }
+
+                    let _ = writeln!(out, "server_fn_duration_seconds_bucket{{name=\"{}\",le=\"+Inf\"}} {}", name, histogram.count);
+                    let _ = writeln!(out, "server_fn_duration_seconds_sum{{name=\"{}\"}} {}", name, histogram.sum_seconds);
+                    let _ = writeln!(out, "server_fn_duration_seconds_count{{name=\"{}\"}} {}", name, histogram.count);
+                }
+
+                return out;
+            }
+        }
+
+        // Records the time from its creation to its drop, so that it also
+        // covers the early returns of the server function.
+        pub struct ServerFnTimer {
+            name: &'static str,
+            start: Instant,
+            metrics: Option<Arc<Metrics>>,
+        }
+
+        impl ServerFnTimer {
+            pub fn new(name: &'static str, metrics: Option<Arc<Metrics>>) -> Self {
+                Self {
+                    name,
+                    start: Instant::now(),
+                    metrics,
+                }
+            }
+        }
+
+        impl Drop for ServerFnTimer {
+            fn drop(&mut self) {
+                let duration = self.start.elapsed();
+                log::info!("fn: {}() - took {:?}", self.name, duration);
+
+                if let Some(metrics) = &self.metrics {
+                    metrics.record(self.name, duration);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,205 @@
+use cfg_if::cfg_if;
+
+pub mod metrics;
+pub mod rate_limiter;
+pub mod room_events;
+
+cfg_if! {
+    if #[cfg(feature = "ssr")] {
+        use leptos::{LeptosOptions, ServerFnError, use_context};
+        use sqlx::SqlitePool;
+        use sqlx::migrate::{MigrateError, Migrator};
+        use axum::extract::FromRef;
+        use leptos_router::RouteListing;
+        use std::net::{IpAddr, SocketAddr};
+        use std::sync::Arc;
+        use axum::http::HeaderMap;
+        use axum_session::SessionStore;
+        use axum_session_auth::SessionSqlitePool;
+        use crate::pages::auth::AuthSession;
+        use crate::state::metrics::{Metrics, ServerFnTimer};
+        use crate::state::rate_limiter::RateLimiter;
+        use crate::state::room_events::{RoomEvent, RoomEvents};
+        use crate::services::password::Argon2Hasher;
+        use crate::utils::validation::PasswordPolicy;
+
+        #[derive(FromRef, Debug, Clone)]
+        pub struct AppState{
+            pub leptos_options: LeptosOptions,
+            pub pool: SqlitePool,
+            pub routes: Vec<RouteListing>,
+            // skipped because two fields of the same type can't both be extracted
+            #[from_ref(skip)]
+            pub login_rate_limiter: Arc<RateLimiter>,
+            #[from_ref(skip)]
+            pub register_rate_limiter: Arc<RateLimiter>,
+            pub room_events: Arc<RoomEvents>,
+            pub password_policy: PasswordPolicy,
+            pub password_hasher: Argon2Hasher,
+            // to end the sessions of other devices, cached by the store
+            pub session_store: SessionStore<SessionSqlitePool>,
+            pub metrics: Arc<Metrics>,
+            pub behind_proxy: bool,
+            // the bearer token of /metrics, None disables it
+            pub metrics_token: Option<String>,
+        }
+
+        // The address of the client. Behind a proxy every connection comes from
+        // the proxy, so the client is the last X-Forwarded-For hop instead: the one
+        // appended by the proxy, the earlier ones are whatever the client sent.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub struct ClientIp(pub IpAddr);
+
+        impl ClientIp {
+            pub fn from_request(headers: &HeaderMap, peer: SocketAddr, behind_proxy: bool) -> Self {
+                let forwarded = behind_proxy
+                    .then(|| headers.get("x-forwarded-for"))
+                    .flatten()
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(|value| value.rsplit(',').next())
+                    .and_then(|hop| hop.trim().parse::<IpAddr>().ok());
+
+                return ClientIp(forwarded.unwrap_or(peer.ip()));
+            }
+        }
+
+        pub fn pool() -> Result<SqlitePool, ServerFnError> {
+            let pool = use_context::<SqlitePool>()
+                .ok_or_else(|| ServerFnError::ServerError("Pool missing.".into()))?;
+
+            // the pool is closed during the graceful shutdown
+            if pool.is_closed() {
+                return Err(ServerFnError::ServerError("Database unavailable".into()));
+            }
+
+            Ok(pool)
+        }
+
+        static MIGRATOR: Migrator = sqlx::migrate!();
+
+        pub async fn run_migrations(pool: &SqlitePool) -> Result<(), MigrateError> {
+            run_migrator(&MIGRATOR, pool).await
+        }
+
+        // The migrations that rebuild a table drop it, which fails or cascades to
+        // the rows pointing to it while the foreign keys are enabled. SQLite ignores
+        // the pragma inside a transaction, and sqlx runs every migration in one, so
+        // it is set on the connection before.
+        pub(crate) async fn run_migrator(migrator: &Migrator, pool: &SqlitePool) -> Result<(), MigrateError> {
+            let mut conn = pool.acquire().await?;
+
+            sqlx::query("PRAGMA foreign_keys = OFF").execute(&mut *conn).await?;
+            let result = migrator.run(&mut *conn).await;
+            sqlx::query("PRAGMA foreign_keys = ON").execute(&mut *conn).await?;
+
+            return result;
+        }
+
+        pub fn app_state() -> Result<AppState, ServerFnError> {
+            use_context::<AppState>()
+                .ok_or_else(|| ServerFnError::ServerError("App state missing.".into()))
+        }
+
+        pub fn auth() -> Result<AuthSession, ServerFnError> {
+            use_context::<AuthSession>()
+                .ok_or_else(|| ServerFnError::ServerError("Auth session missing.".into()))
+        }
+
+        // Notifies the clients viewing the room, a missing state only means nobody is notified.
+        pub fn publish_room_event(room_id: &str, event: RoomEvent) {
+            if let Some(app_state) = use_context::<AppState>() {
+                app_state.room_events.publish(room_id, event);
+            }
+        }
+
+        // Times the server function until the returned value is dropped, e.g.
+        // `let _timer = time_server_fn("login");` as its first line.
+        pub fn time_server_fn(name: &'static str) -> ServerFnTimer {
+            let metrics = use_context::<AppState>().map(|app_state| app_state.metrics);
+            ServerFnTimer::new(name, metrics)
+        }
+
+    }
+}
+
+#[cfg(all(test, feature = "ssr"))]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn the_room_cascade_migration_keeps_the_rows_of_the_expenses() {
+        use crate::test_utils::empty_pool;
+        use std::borrow::Cow;
+
+        let pool = empty_pool().await;
+
+        // a database created before 0038, the one that rebuilds `expense`
+        let mut before_cascade = sqlx::migrate!();
+        before_cascade.migrations = Cow::Owned(before_cascade.migrations.iter().filter(|migration| migration.version <= 37).cloned().collect());
+        run_migrator(&before_cascade, &pool).await.unwrap();
+
+        for statement in [
+            "INSERT INTO user (id, username, password) VALUES (1, 'alice', '')",
+            "INSERT INTO room (id, room_name, max_participants, owner, slug) VALUES ('room', 'Trip', 10, 1, 'trip')",
+            "INSERT INTO user_room (room_id, user_id, role) VALUES ('room', 1, 'owner')",
+            "INSERT INTO expense (id, paid_by, amount_cents, title, room_id) VALUES (1, 1, 1000, 'Lunch', 'room')",
+            "INSERT INTO user_expense (user_id, expense_id) VALUES (1, 1)",
+            "INSERT INTO expense_split (expense_id, user_id, amount_cents) VALUES (1, 1, 1000)",
+            "INSERT INTO expense_item (expense_id, description, amount_cents, assigned_to) VALUES (1, 'Pasta', 1000, 1)",
+            "INSERT INTO comment (expense_id, user_id, body) VALUES (1, 1, 'Thanks!')",
+            "INSERT INTO attachment (expense_id, filename, mime_type, data) VALUES (1, 'bill.png', 'image/png', x'00')",
+        ] {
+            sqlx::query(statement).execute(&pool).await.unwrap();
+        }
+
+        run_migrations(&pool).await.unwrap();
+
+        for table in ["expense", "user_expense", "expense_split", "expense_item", "comment", "attachment"] {
+            let count: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {}", table))
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+            assert_eq!(count, 1, "rows left in {}", table);
+        }
+
+        let foreign_keys: bool = sqlx::query_scalar("PRAGMA foreign_keys").fetch_one(&pool).await.unwrap();
+        assert!(foreign_keys);
+    }
+
+    fn peer() -> SocketAddr {
+        "10.0.0.1:4000".parse().unwrap()
+    }
+
+    fn forwarded_for(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", value.parse().unwrap());
+        return headers;
+    }
+
+    #[test]
+    fn uses_the_hop_appended_by_the_proxy() {
+        let client_ip = ClientIp::from_request(&forwarded_for("203.0.113.7"), peer(), true);
+
+        assert_eq!(client_ip, ClientIp("203.0.113.7".parse().unwrap()));
+    }
+
+    #[test]
+    fn ignores_the_hops_sent_by_the_client() {
+        let client_ip = ClientIp::from_request(&forwarded_for("198.51.100.1, 203.0.113.7"), peer(), true);
+
+        assert_eq!(client_ip, ClientIp("203.0.113.7".parse().unwrap()));
+    }
+
+    #[test]
+    fn ignores_the_forwarded_header_without_a_proxy() {
+        let client_ip = ClientIp::from_request(&forwarded_for("203.0.113.7"), peer(), false);
+
+        assert_eq!(client_ip, ClientIp(peer().ip()));
+    }
+
+    #[test]
+    fn falls_back_to_the_peer_on_a_missing_or_invalid_header() {
+        assert_eq!(ClientIp::from_request(&HeaderMap::new(), peer(), true), ClientIp(peer().ip()));
+        assert_eq!(ClientIp::from_request(&forwarded_for("unknown"), peer(), true), ClientIp(peer().ip()));
+    }
+}
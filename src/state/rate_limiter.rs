@@ -0,0 +1,120 @@
+use cfg_if::cfg_if;
+
+cfg_if! {
+    if #[cfg(feature = "ssr")] {
+        use std::collections::HashMap;
+        use std::sync::RwLock;
+        use std::time::{Duration, Instant};
+
+        // Counts the attempts per key (e.g. the failed logins of a username or
+        // the registrations from an IP) inside a fixed time window, starting
+        // from the first attempt.
+        #[derive(Debug)]
+        pub struct RateLimiter {
+            attempts: RwLock<HashMap<String, (u32, Instant)>>,
+            max_attempts: u32,
+            window: Duration,
+        }
+
+        impl RateLimiter {
+            pub fn new(max_attempts: u32, window: Duration) -> Self {
+                Self {
+                    attempts: RwLock::new(HashMap::new()),
+                    max_attempts,
+                    window,
+                }
+            }
+
+            // Records an attempt and tells if it's allowed. The check and the
+            // record are under the same lock, otherwise concurrent requests could
+            // all pass the check before any of them is recorded.
+            pub fn try_attempt(&self, key: &str) -> bool {
+                let mut attempts = self.attempts.write().unwrap();
+                let now = Instant::now();
+
+                // the expired windows would start from zero anyway, dropping them
+                // keeps the map from growing with every key ever seen
+                attempts.retain(|_, (_, first_attempt)| now.duration_since(*first_attempt) < self.window);
+
+                let entry = attempts.entry(key.to_string()).or_insert((0, now));
+                if entry.0 >= self.max_attempts {
+                    return false;
+                }
+
+                entry.0 += 1;
+                return true;
+            }
+
+            // Tells if the key used all its attempts, without recording one. With
+            // `record_attempt`, for the attempts that only count when they succeed
+            // (e.g. the registrations, not the forms with a typo).
+            pub fn is_limited(&self, key: &str) -> bool {
+                let attempts = self.attempts.read().unwrap();
+
+                return attempts.get(key).is_some_and(|(count, first_attempt)| {
+                    *count >= self.max_attempts && first_attempt.elapsed() < self.window
+                });
+            }
+
+            pub fn record_attempt(&self, key: &str) {
+                self.try_attempt(key);
+            }
+
+            pub fn reset(&self, key: &str) {
+                self.attempts.write().unwrap().remove(key);
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "ssr"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limits_after_the_max_attempts() {
+        let rate_limiter = RateLimiter::new(2, Duration::from_secs(60));
+
+        assert!(rate_limiter.try_attempt("alice"));
+        assert!(rate_limiter.try_attempt("alice"));
+        assert!(!rate_limiter.try_attempt("alice"));
+        // every key has its own count
+        assert!(rate_limiter.try_attempt("bob"));
+    }
+
+    #[test]
+    fn only_the_recorded_attempts_count() {
+        let rate_limiter = RateLimiter::new(2, Duration::from_secs(60));
+
+        assert!(!rate_limiter.is_limited("10.0.0.1"));
+        rate_limiter.record_attempt("10.0.0.1");
+        assert!(!rate_limiter.is_limited("10.0.0.1"));
+        rate_limiter.record_attempt("10.0.0.1");
+
+        assert!(rate_limiter.is_limited("10.0.0.1"));
+        assert!(!rate_limiter.is_limited("10.0.0.2"));
+    }
+
+    #[test]
+    fn reset_clears_the_attempts() {
+        let rate_limiter = RateLimiter::new(1, Duration::from_secs(60));
+
+        assert!(rate_limiter.try_attempt("alice"));
+        rate_limiter.reset("alice");
+
+        assert!(rate_limiter.try_attempt("alice"));
+    }
+
+    #[test]
+    fn drops_the_expired_windows() {
+        let rate_limiter = RateLimiter::new(1, Duration::from_millis(10));
+
+        assert!(rate_limiter.try_attempt("alice"));
+        assert!(!rate_limiter.try_attempt("alice"));
+        std::thread::sleep(Duration::from_millis(20));
+
+        assert!(rate_limiter.try_attempt("bob"));
+        assert_eq!(rate_limiter.attempts.read().unwrap().len(), 1);
+        assert!(rate_limiter.try_attempt("alice"));
+    }
+}
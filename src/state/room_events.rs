@@ -0,0 +1,75 @@
+use cfg_if::cfg_if;
+use serde::{Deserialize, Serialize};
+
+// Sent as JSON to the clients viewing the room, so that they refresh it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RoomEvent {
+    ExpenseCreated { expense_id: i64 },
+    ExpenseDeleted { expense_id: i64 },
+}
+
+cfg_if! {
+    if #[cfg(feature = "ssr")] {
+        use std::collections::HashMap;
+        use std::sync::RwLock;
+        use tokio::sync::broadcast;
+
+        const ROOM_EVENTS_CAPACITY: usize = 16;
+
+        // A broadcast channel per room, created by the first subscriber.
+        #[derive(Debug, Default)]
+        pub struct RoomEvents {
+            senders: RwLock<HashMap<String, broadcast::Sender<RoomEvent>>>,
+        }
+
+        impl RoomEvents {
+            pub fn subscribe(&self, room_id: &str) -> broadcast::Receiver<RoomEvent> {
+                let mut senders = self.senders.write().unwrap();
+
+                senders
+                    .entry(room_id.to_string())
+                    .or_insert_with(|| broadcast::channel(ROOM_EVENTS_CAPACITY).0)
+                    .subscribe()
+            }
+
+            pub fn publish(&self, room_id: &str, event: RoomEvent) {
+                let mut senders = self.senders.write().unwrap();
+
+                if let Some(sender) = senders.get(room_id) {
+                    // every receiver has been dropped, nobody is viewing the room
+                    if sender.send(event).is_err() {
+                        senders.remove(room_id);
+                    }
+                }
+            }
+        }
+    }
+}
+
+cfg_if! {
+    if #[cfg(feature = "hydrate")] {
+        use leptos::{on_cleanup, window};
+        use wasm_bindgen::{closure::Closure, JsCast};
+
+        // Calls `on_event` for every event of the room, until the component is unmounted.
+        pub fn subscribe(room_id: &str, on_event: impl Fn() + 'static) {
+            let location = window().location();
+            let protocol = if location.protocol().as_deref() == Ok("https:") { "wss" } else { "ws" };
+            let url = format!("{}://{}/ws/group/{}", protocol, location.host().unwrap_or_default(), room_id);
+
+            let Ok(socket) = web_sys::WebSocket::new(&url) else {
+                log::warn!("fn: subscribe() - could not connect to {}", url);
+                return;
+            };
+
+            let on_message = Closure::<dyn FnMut(web_sys::MessageEvent)>::new(move |_| on_event());
+            socket.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+            on_message.forget();
+
+            on_cleanup(move || {
+                let _ = socket.close();
+            });
+        }
+    }
+}
@@ -0,0 +1,44 @@
+// Helpers for the tests that need a database.
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
+
+use crate::models::room::Room;
+use crate::models::user::User;
+use crate::state::run_migrations;
+
+// A new in-memory database, without any table. With a single connection,
+// because every connection to ":memory:" opens another database.
+pub async fn empty_pool() -> SqlitePool {
+    let options = "sqlite::memory:"
+        .parse::<SqliteConnectOptions>()
+        .unwrap()
+        .foreign_keys(true);
+
+    return SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect_with(options)
+        .await
+        .unwrap();
+}
+
+// A new in-memory database with all the migrations applied.
+pub async fn test_pool() -> SqlitePool {
+    let pool = empty_pool().await;
+    run_migrations(&pool).await.unwrap();
+
+    return pool;
+}
+
+pub async fn create_user(username: &str, pool: &SqlitePool) -> User {
+    sqlx::query("INSERT INTO user (username, password) VALUES (?, '')")
+        .bind(username)
+        .execute(pool)
+        .await
+        .unwrap();
+
+    return User::get_user_from_username(username.to_string(), pool).await.unwrap();
+}
+
+// The room is owned by `owner`, who is its only member.
+pub async fn create_room(room_name: &str, owner: &User, pool: &SqlitePool) -> Room {
+    return Room::create(room_name.to_string(), owner.id, "USD", pool).await.unwrap();
+}
@@ -0,0 +1,3 @@
+pub mod money;
+pub mod time;
+pub mod validation;
@@ -0,0 +1,150 @@
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+use crate::error_template::AppError;
+use crate::i18n::Lang;
+
+// 1 000 000.00 in any currency, anything above is surely a typo
+pub const MAX_AMOUNT_CENTS: i64 = 1_000_000_00;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Currency {
+    #[default]
+    USD,
+    EUR,
+    GBP,
+}
+
+impl Currency {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Currency::USD => "USD",
+            Currency::EUR => "EUR",
+            Currency::GBP => "GBP",
+        }
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Currency::USD => "$",
+            Currency::EUR => "€",
+            Currency::GBP => "£",
+        }
+    }
+
+    pub fn is_symbol_prefix(&self) -> bool {
+        match self {
+            Currency::USD => true,
+            Currency::EUR => false,
+            Currency::GBP => true,
+        }
+    }
+}
+
+impl FromStr for Currency {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "USD" => Ok(Currency::USD),
+            "EUR" => Ok(Currency::EUR),
+            "GBP" => Ok(Currency::GBP),
+            _ => Err(format!("Unknown currency: {}", s)),
+        }
+    }
+}
+
+// Formats an amount of cents with the separators of the locale, e.g.
+// "$1,234.56", "-£5.00" or "1.234,56 €".
+pub fn format_cents(cents: i64, currency: Currency, locale: Lang) -> String {
+    let sign = if cents < 0 { "-" } else { "" };
+    let units = (cents.unsigned_abs() / 100).to_string();
+    let rest = cents.unsigned_abs() % 100;
+
+    let mut grouped = String::new();
+    for (i, digit) in units.chars().enumerate() {
+        if i > 0 && (units.len() - i) % 3 == 0 {
+            grouped.push(locale.thousands_separator());
+        }
+        grouped.push(digit);
+    }
+
+    let number = format!("{}{}{:02}", grouped, locale.decimal_separator(), rest);
+
+    if currency.is_symbol_prefix() {
+        return format!("{}{}{}", sign, currency.symbol(), number);
+    } else {
+        return format!("{}{} {}", sign, number, currency.symbol());
+    }
+}
+
+// The client checks it too, but its requests can be crafted.
+pub fn validate_amount(cents: i64) -> Result<(), AppError> {
+    if cents <= 0 {
+        return Err(AppError::Validation("Amount must be positive".to_string()));
+    }
+
+    if cents > MAX_AMOUNT_CENTS {
+        return Err(AppError::Validation("Amount is too large".to_string()));
+    }
+
+    Ok(())
+}
+
+// Parses a decimal amount like "12", "12.3", "12.34" or ".5" into cents.
+pub fn parse_cents(amount: &str) -> Option<i64> {
+    let amount = amount.trim();
+    let (units, decimals) = amount.split_once('.').unwrap_or((amount, ""));
+
+    if (units.is_empty() && decimals.is_empty()) || decimals.len() > 2 {
+        return None;
+    }
+    let units = if units.is_empty() { "0" } else { units };
+    if !units.chars().all(|c| c.is_ascii_digit()) || !decimals.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    let units = units.parse::<i64>().ok()?;
+    let decimals = format!("{:0<2}", decimals).parse::<i64>().ok()?;
+
+    return units.checked_mul(100)?.checked_add(decimals);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_with_the_separators_of_the_locale() {
+        assert_eq!(format_cents(123456, Currency::USD, Lang::En), "$1,234.56");
+        assert_eq!(format_cents(123456, Currency::EUR, Lang::It), "1.234,56 €");
+        assert_eq!(format_cents(123456789, Currency::GBP, Lang::En), "£1,234,567.89");
+    }
+
+    #[test]
+    fn formats_small_and_negative_amounts() {
+        assert_eq!(format_cents(0, Currency::USD, Lang::En), "$0.00");
+        assert_eq!(format_cents(5, Currency::USD, Lang::En), "$0.05");
+        assert_eq!(format_cents(-500, Currency::GBP, Lang::En), "-£5.00");
+        assert_eq!(format_cents(-500, Currency::EUR, Lang::En), "-5.00 €");
+    }
+
+    #[test]
+    fn parses_the_amounts_into_cents() {
+        assert_eq!(parse_cents("12"), Some(1200));
+        assert_eq!(parse_cents("12.3"), Some(1230));
+        assert_eq!(parse_cents("12.34"), Some(1234));
+        assert_eq!(parse_cents(".5"), Some(50));
+        assert_eq!(parse_cents("7."), Some(700));
+    }
+
+    #[test]
+    fn rejects_the_malformed_amounts() {
+        assert_eq!(parse_cents(""), None);
+        assert_eq!(parse_cents("."), None);
+        assert_eq!(parse_cents("1.234"), None);
+        assert_eq!(parse_cents("-5"), None);
+        assert_eq!(parse_cents("12a"), None);
+        assert_eq!(parse_cents("99999999999999999999"), None);
+    }
+}
@@ -0,0 +1,128 @@
+use chrono::{Datelike, Months, NaiveDate, NaiveDateTime};
+
+// The first instant of the month and the first instant of the next one, to be
+// used as a [start, end) range. None if the month is not valid.
+pub fn month_bounds(year: i32, month: u32) -> Option<(NaiveDateTime, NaiveDateTime)> {
+    let start = NaiveDate::from_ymd_opt(year, month, 1)?;
+    let end = start.checked_add_months(Months::new(1))?;
+
+    return Some((start.and_hms_opt(0, 0, 0)?, end.and_hms_opt(0, 0, 0)?));
+}
+
+// The format used by SQLite for CURRENT_TIMESTAMP, so that the comparisons
+// between timestamps can be done on the strings.
+pub fn to_sql_timestamp(timestamp: NaiveDateTime) -> String {
+    return timestamp.format("%Y-%m-%d %H:%M:%S").to_string();
+}
+
+// How long ago something happened, kept apart from the text so that the
+// translations can be added without touching the thresholds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelativeTime {
+    JustNow,
+    MinutesAgo(i64),
+    HoursAgo(i64),
+    DaysAgo(i64),
+    // more than a week ago, the year is shown only if it's not the current one
+    On { date: NaiveDate, same_year: bool },
+}
+
+impl RelativeTime {
+    // The timestamps in the future (e.g. a clock skew between client and
+    // server) are "just now" too.
+    pub fn between(ts: NaiveDateTime, now: NaiveDateTime) -> Self {
+        let elapsed = now - ts;
+
+        if elapsed.num_seconds() < 60 {
+            return RelativeTime::JustNow;
+        }
+        if elapsed.num_minutes() < 60 {
+            return RelativeTime::MinutesAgo(elapsed.num_minutes());
+        }
+        if elapsed.num_hours() < 24 {
+            return RelativeTime::HoursAgo(elapsed.num_hours());
+        }
+        if elapsed.num_days() < 7 {
+            return RelativeTime::DaysAgo(elapsed.num_days());
+        }
+
+        return RelativeTime::On { date: ts.date(), same_year: ts.year() == now.year() };
+    }
+}
+
+impl std::fmt::Display for RelativeTime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let plural = |count: i64| if count == 1 { "" } else { "s" };
+
+        match self {
+            RelativeTime::JustNow => write!(f, "just now"),
+            RelativeTime::MinutesAgo(minutes) => write!(f, "{} minute{} ago", minutes, plural(*minutes)),
+            RelativeTime::HoursAgo(hours) => write!(f, "{} hour{} ago", hours, plural(*hours)),
+            RelativeTime::DaysAgo(days) => write!(f, "{} day{} ago", days, plural(*days)),
+            RelativeTime::On { date, same_year: true } => write!(f, "on {}", date.format("%b %-d")),
+            RelativeTime::On { date, same_year: false } => write!(f, "on {}", date.format("%b %-d, %Y")),
+        }
+    }
+}
+
+// e.g. "just now", "5 minutes ago", "3 days ago" or "on Jan 2".
+pub fn relative(ts: NaiveDateTime, now: NaiveDateTime) -> String {
+    return RelativeTime::between(ts, now).to_string();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn now() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 3, 15).unwrap().and_hms_opt(12, 0, 0).unwrap()
+    }
+
+    fn ago(duration: Duration) -> RelativeTime {
+        RelativeTime::between(now() - duration, now())
+    }
+
+    #[test]
+    fn just_now_under_a_minute() {
+        assert_eq!(ago(Duration::seconds(59)), RelativeTime::JustNow);
+        assert_eq!(ago(Duration::seconds(-30)), RelativeTime::JustNow);
+    }
+
+    #[test]
+    fn minutes_from_a_minute() {
+        assert_eq!(ago(Duration::seconds(60)), RelativeTime::MinutesAgo(1));
+    }
+
+    #[test]
+    fn hours_under_a_day() {
+        assert_eq!(ago(Duration::hours(23)), RelativeTime::HoursAgo(23));
+    }
+
+    #[test]
+    fn days_from_a_day() {
+        assert_eq!(ago(Duration::hours(25)), RelativeTime::DaysAgo(1));
+        assert_eq!(ago(Duration::days(6)), RelativeTime::DaysAgo(6));
+    }
+
+    #[test]
+    fn the_date_after_a_week() {
+        let date = NaiveDate::from_ymd_opt(2024, 3, 7).unwrap();
+
+        assert_eq!(ago(Duration::days(8)), RelativeTime::On { date, same_year: true });
+        assert_eq!(relative(now() - Duration::days(8), now()), "on Mar 7");
+    }
+
+    #[test]
+    fn the_year_only_for_another_year() {
+        let ts = NaiveDate::from_ymd_opt(2023, 12, 31).unwrap().and_hms_opt(12, 0, 0).unwrap();
+
+        assert_eq!(relative(ts, now()), "on Dec 31, 2023");
+    }
+
+    #[test]
+    fn singular_and_plural() {
+        assert_eq!(RelativeTime::MinutesAgo(1).to_string(), "1 minute ago");
+        assert_eq!(RelativeTime::HoursAgo(2).to_string(), "2 hours ago");
+    }
+}
@@ -0,0 +1,248 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::error_template::AppError;
+
+// Upper bounds for the user input, checked on the server whatever the client does.
+pub const USERNAME_MAX_LENGTH: usize = 64;
+pub const PASSWORD_MAX_LENGTH: usize = 256;
+pub const DESCRIPTION_MAX_LENGTH: usize = 500;
+pub const TAG_MAX_LENGTH: usize = 32;
+// also the description of an item of an itemized expense, a line of the bill
+pub const TITLE_MAX_LENGTH: usize = 100;
+pub const ROOM_NAME_MAX_LENGTH: usize = 64;
+pub const COMMENT_MAX_LENGTH: usize = 1000;
+
+// `field` is the name shown in the error, e.g. "Username".
+pub fn check_max_length(field: &str, value: &str, max: usize) -> Result<(), AppError> {
+    if value.chars().count() > max {
+        return Err(AppError::Validation(format!(
+            "{} must be at most {} characters long",
+            field, max
+        )));
+    }
+
+    Ok(())
+}
+
+// e.g. the day of an expense, that can be backdated but not postdated.
+pub fn check_not_in_future(field: &str, date: NaiveDate, today: NaiveDate) -> Result<(), AppError> {
+    if date > today {
+        return Err(AppError::Validation(format!("{} cannot be in the future", field)));
+    }
+
+    Ok(())
+}
+
+// e.g. the first day of a recurring expense, the past ones would be posted at once.
+pub fn check_not_in_past(field: &str, date: NaiveDate, today: NaiveDate) -> Result<(), AppError> {
+    if date < today {
+        return Err(AppError::Validation(format!("{} cannot be in the past", field)));
+    }
+
+    Ok(())
+}
+
+// The users of a split by percentage, shares or exact amounts: a user listed
+// twice would be charged twice.
+pub fn check_unique_users(user_ids: impl IntoIterator<Item = i64>) -> Result<(), AppError> {
+    let mut seen = std::collections::HashSet::new();
+    if !user_ids.into_iter().all(|user_id| seen.insert(user_id)) {
+        return Err(AppError::Validation("Each participant can appear only once in the split".to_string()));
+    }
+
+    Ok(())
+}
+
+// The percentages of a split are rounded to two decimals, so e.g. three times
+// 33.33 is accepted. The splits give back or take the cents that are missing
+// or over the amount (see `distribute_remainder`).
+const PERCENTAGE_TOLERANCE: f64 = 0.01;
+// the float sum of 33.33 + 33.33 + 33.33 is a hair more than 0.01 off
+const PERCENTAGE_FLOAT_ERROR: f64 = 1e-9;
+
+pub fn check_percentages(percentages: &[(i64, f64)]) -> Result<(), AppError> {
+    if percentages.iter().any(|(_, percentage)| !percentage.is_finite() || *percentage < 0.0) {
+        return Err(AppError::Validation("Percentages cannot be negative".to_string()));
+    }
+
+    let total: f64 = percentages.iter().map(|(_, percentage)| percentage).sum();
+    if (total - 100.0).abs() > PERCENTAGE_TOLERANCE + PERCENTAGE_FLOAT_ERROR {
+        return Err(AppError::Validation("Percentages must sum to 100".to_string()));
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PasswordRule {
+    MinLength(usize),
+    Digit,
+    Symbol,
+}
+
+impl fmt::Display for PasswordRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PasswordRule::MinLength(min) => write!(f, "be at least {} characters long", min),
+            PasswordRule::Digit => write!(f, "contain a digit"),
+            PasswordRule::Symbol => write!(f, "contain a symbol"),
+        }
+    }
+}
+
+// Loaded from the environment at startup, the register page asks the server
+// for it so that the hints match what the server enforces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PasswordPolicy {
+    pub min_length: usize,
+    pub require_digit: bool,
+    pub require_symbol: bool,
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        Self {
+            min_length: 8,
+            require_digit: false,
+            require_symbol: false,
+        }
+    }
+}
+
+impl PasswordPolicy {
+    // Every rule the password breaks, empty when the password is accepted.
+    pub fn unmet_rules(&self, password: &str) -> Vec<PasswordRule> {
+        let mut rules = vec![];
+
+        if password.chars().count() < self.min_length {
+            rules.push(PasswordRule::MinLength(self.min_length));
+        }
+
+        if self.require_digit && !password.chars().any(|c| c.is_ascii_digit()) {
+            rules.push(PasswordRule::Digit);
+        }
+
+        if self.require_symbol && !password.chars().any(|c| !c.is_alphanumeric() && !c.is_whitespace()) {
+            rules.push(PasswordRule::Symbol);
+        }
+
+        return rules;
+    }
+
+    pub fn check(&self, password: &str) -> Result<(), AppError> {
+        let rules = self.unmet_rules(password);
+        if rules.is_empty() {
+            return Ok(());
+        }
+
+        let errors: Vec<String> = rules.iter().map(|rule| format!("Password must {}", rule)).collect();
+        Err(AppError::Validation(join_password_errors(&errors)))
+    }
+
+    // PASSWORD_MIN_LENGTH, PASSWORD_REQUIRE_DIGIT and PASSWORD_REQUIRE_SYMBOL,
+    // the missing ones keep the default.
+    #[cfg(feature = "ssr")]
+    pub fn from_env() -> Result<Self, String> {
+        let flag = |name: &str| std::env::var(name).ok().map(|value| value == "1" || value.eq_ignore_ascii_case("true"));
+        let default = Self::default();
+
+        let min_length = match std::env::var("PASSWORD_MIN_LENGTH") {
+            Ok(value) => parse_min_length(&value)?,
+            Err(_) => default.min_length,
+        };
+
+        Ok(Self {
+            min_length,
+            require_digit: flag("PASSWORD_REQUIRE_DIGIT").unwrap_or(default.require_digit),
+            require_symbol: flag("PASSWORD_REQUIRE_SYMBOL").unwrap_or(default.require_symbol),
+        })
+    }
+}
+
+// A minimum of 0 would accept the empty password and one above the maximum
+// no password at all, so the value is clamped to 1..=PASSWORD_MAX_LENGTH.
+#[cfg(any(feature = "ssr", test))]
+fn parse_min_length(value: &str) -> Result<usize, String> {
+    let min_length = value
+        .trim()
+        .parse::<usize>()
+        .map_err(|_| format!("PASSWORD_MIN_LENGTH must be an integer between 1 and {}, got {:?}", PASSWORD_MAX_LENGTH, value))?;
+
+    return Ok(min_length.clamp(1, PASSWORD_MAX_LENGTH));
+}
+
+// The errors of the broken rules, joined the same way on the client and on the server.
+pub fn join_password_errors(errors: &[String]) -> String {
+    errors.join(". ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_percentages_summing_to_100() {
+        assert!(check_percentages(&[(1, 33.33), (2, 33.33), (3, 33.34)]).is_ok());
+        assert!(check_percentages(&[(1, 100.0)]).is_ok());
+    }
+
+    #[test]
+    fn accepts_percentages_off_by_a_hundredth() {
+        assert!(check_percentages(&[(1, 33.33), (2, 33.33), (3, 33.33)]).is_ok());
+        assert!(check_percentages(&[(1, 50.0), (2, 50.01)]).is_ok());
+    }
+
+    #[test]
+    fn rejects_percentages_off_by_more_than_a_hundredth() {
+        assert!(matches!(check_percentages(&[(1, 50.0), (2, 50.02)]), Err(AppError::Validation(_))));
+        assert!(matches!(check_percentages(&[(1, 50.0), (2, 49.98)]), Err(AppError::Validation(_))));
+    }
+
+    #[test]
+    fn accepts_today_but_not_the_past() {
+        let today = NaiveDate::from_ymd_opt(2024, 3, 10).unwrap();
+
+        assert!(check_not_in_past("Date", today, today).is_ok());
+        assert!(check_not_in_past("Date", today.succ_opt().unwrap(), today).is_ok());
+        assert!(matches!(check_not_in_past("Date", today.pred_opt().unwrap(), today), Err(AppError::Validation(_))));
+    }
+
+    #[test]
+    fn rejects_a_user_listed_twice() {
+        assert!(check_unique_users([1, 2, 3]).is_ok());
+        assert!(matches!(check_unique_users([1, 2, 1]), Err(AppError::Validation(_))));
+    }
+
+    #[test]
+    fn clamps_the_minimum_password_length() {
+        assert_eq!(parse_min_length("12"), Ok(12));
+        assert_eq!(parse_min_length("0"), Ok(1));
+        assert_eq!(parse_min_length("100000"), Ok(PASSWORD_MAX_LENGTH));
+        assert!(parse_min_length("-1").is_err());
+        assert!(parse_min_length("eight").is_err());
+    }
+
+    #[test]
+    fn lists_every_broken_password_rule() {
+        let policy = PasswordPolicy {
+            min_length: 8,
+            require_digit: true,
+            require_symbol: false,
+        };
+
+        match policy.check("short") {
+            Err(AppError::Validation(message)) => {
+                assert_eq!(message, "Password must be at least 8 characters long. Password must contain a digit")
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(policy.check("longer password 1").is_ok());
+    }
+
+    #[test]
+    fn rejects_negative_percentages() {
+        assert!(matches!(check_percentages(&[(1, 110.0), (2, -10.0)]), Err(AppError::Validation(_))));
+    }
+}